@@ -9,25 +9,38 @@ use screeps::{OrderType, ResourceType, RoomName, raw_memory};
 use serde::{Deserialize, Serialize};
 
 use crate::movement::Movement;
+use crate::resources::minerals::{self, MineralAction, MineralBalance, Stock};
+use crate::resources::network::{self, Shipment};
 use crate::rooms::register_rooms;
 use crate::rooms::state::requests::assignment::Assignment;
 use crate::rooms::state::requests::{
-    CaravanData, DepositData, LRWData, PowerbankData, ProtectData, Request, RequestKind,
-    TransferData,
+    CaravanData, DepositData, FactoryData, LRWData, PowerbankData, ProtectData, Request,
+    RequestKind, TransferData,
 };
-use crate::rooms::state::{RoomState, TradeData};
+use crate::rooms::state::constructions::visualizer::PlanVisual;
+use crate::rooms::state::{FarmInfo, RoomState, TradeData};
 use crate::rooms::wrappers::claimed::Claimed;
 use crate::statistics::Statistic;
 use crate::units::creeps::{CreepMemory, run_creeps};
 use crate::units::power_creep::{PowerCreepMemory, run_power_creeps};
 use crate::units::roles::Kind;
-use crate::utils::constants::MAX_POWER_CAPACITY;
+use crate::utils::constants::{
+    CANDIDATE_INTERVAL, MARKET_INTERVAL, MAX_POWER_CAPACITY, MINERAL_INTERVAL, NETWORK_INTERVAL,
+};
+use crate::utils::profiler;
 
 pub mod events;
+pub mod expansion;
+pub mod journal;
+pub mod market;
 mod orders;
+pub mod upgrade;
 
 use events::ColonyContext;
 pub use events::ColonyEvent;
+use journal::{Category, Journal, JournalEvent, Severity};
+use expansion::Candidate;
+use market::Market;
 
 use crate::colony::orders::ColonyOrder;
 
@@ -53,6 +66,17 @@ pub struct GlobalState {
     pub white_list: HashSet<String>,
     #[serde(default = "HashSet::new")]
     pub black_list: HashSet<String>,
+    #[serde(default)]
+    pub journal: Journal,
+    #[serde(default)]
+    pub minerals: HashMap<ResourceType, MineralBalance>,
+    #[serde(default)]
+    pub market: Market,
+    #[serde(skip)]
+    pub visuals: HashMap<RoomName, PlanVisual>,
+    /// rooms to claim ranked by the planner
+    #[serde(default)]
+    pub candidates: HashMap<RoomName, Candidate>,
 }
 
 impl Default for GlobalState {
@@ -68,6 +92,11 @@ impl Default for GlobalState {
             statistic: Statistic::default(),
             white_list: HashSet::new(),
             black_list: HashSet::new(),
+            journal: Journal::default(),
+            minerals: HashMap::new(),
+            market: Market::default(),
+            visuals: HashMap::new(),
+            candidates: HashMap::new(),
         }
     }
 }
@@ -82,10 +111,15 @@ impl GlobalState {
         for home in homes.values_mut() {
             let cpu_start = game::cpu::get_used();
             events.extend(home.run_shelter(&mut self.creeps, &orders));
-            debug!("{} run_base for {} cpu!", home.name(), game::cpu::get_used() - cpu_start);
+            self.statistic.metrics.record_room(&home.base);
+            let used = game::cpu::get_used() - cpu_start;
+            self.statistic.metrics.record_cpu("rooms", used);
         }
 
+        let cpu_start = game::cpu::get_used();
         events.extend(neutrals.into_iter().flat_map(|neutral| neutral.run_room()));
+        let used = game::cpu::get_used() - cpu_start;
+        self.statistic.metrics.record_cpu("neutrals", used);
 
         let owned_rooms: Vec<RoomName> = homes
             .iter()
@@ -98,27 +132,62 @@ impl GlobalState {
         // new one
         let cpu_start = game::cpu::get_used();
         run_power_creeps(&mut self.power_creeps, &mut homes, &mut movement);
-        debug!("run_power_creeps {} cpu!", game::cpu::get_used() - cpu_start);
+        let used = game::cpu::get_used() - cpu_start;
+        self.statistic.metrics.record_cpu("power_creeps", used);
 
         let cpu_start = game::cpu::get_used();
         run_creeps(&mut self.creeps, &mut homes, &mut movement, &self.black_list);
-        debug!("finished run creeps {} cpu!", game::cpu::get_used() - cpu_start);
+        let used = game::cpu::get_used() - cpu_start;
+        self.statistic.metrics.record_cpu("creeps", used);
 
+        let cpu_start = game::cpu::get_used();
         movement.swap_move();
+        let used = game::cpu::get_used() - cpu_start;
+        self.statistic.metrics.record_cpu("movement", used);
 
         let bases: HashMap<RoomName, Claimed> =
             homes.into_iter().map(|(name, home)| (name, home.base())).collect();
 
+        let cpu_start = game::cpu::get_used();
         let context = ColonyContext::new(movement, &bases);
         for event in events {
             event.assign(self, &context);
         }
+        let used = game::cpu::get_used() - cpu_start;
+        self.statistic.metrics.record_cpu("events", used);
 
+        // a colony without a plan yet gets it right away instead of waiting for the interval
+        let unplanned = bases
+            .keys()
+            .any(|name| self.rooms.get(name).is_some_and(|state| state.upgrade.is_none()));
         if game::time().is_multiple_of(100) {
             self.update_avoid_rooms();
             self.orders.retain(|order| game::time() < order.timeout());
         }
+        if game::time().is_multiple_of(100) || unplanned {
+            self.plan_upgrades(&bases);
+        }
+        if game::time().is_multiple_of(NETWORK_INTERVAL) {
+            self.balance_energy(&bases);
+        }
+        if game::time().is_multiple_of(MINERAL_INTERVAL) {
+            self.balance_minerals(&bases);
+        }
+        if game::time().is_multiple_of(MARKET_INTERVAL) {
+            for event in self.market.manage(&mut self.rooms) {
+                self.journal.record(event);
+            }
+        }
+        if game::time().is_multiple_of(CANDIDATE_INTERVAL) {
+            self.candidates.retain(|room_name, _| !self.rooms.contains_key(room_name));
+            expansion::prune(&mut self.candidates, game::time());
+            if let Some(event) = expansion::rank_next(&mut self.candidates) {
+                self.journal.record(event);
+            }
+        }
+        self.draw_plans();
         self.gc();
+        self.statistic.metrics.export(&self.creeps);
     }
 
     fn add_request(&mut self, to: RoomName, request: Request) {
@@ -142,6 +211,96 @@ impl GlobalState {
         });
     }
 
+    // upgrade goals per room
+    fn plan_upgrades(&mut self, bases: &HashMap<RoomName, Claimed>) {
+        for (name, room_state) in &mut self.rooms {
+            room_state.upgrade = bases.get(name).map(upgrade::plan);
+        }
+    }
+
+    // terminal sends that bring every base to its energy target at the lowest cost
+    fn balance_energy(&mut self, bases: &HashMap<RoomName, Claimed>) {
+        for shipment in network::plan(bases, &self.rooms) {
+            let Shipment { from, to, resource, amount, pack } = shipment;
+            info!("{} energy network: {} {} to {}, pack: {}", from, amount, resource, to, pack);
+            if pack {
+                self.add_request(
+                    from,
+                    Request::new(
+                        RequestKind::Factory(FactoryData::new(ResourceType::Battery, amount)),
+                        Assignment::None,
+                    ),
+                );
+            }
+            if resource == ResourceType::Battery {
+                self.add_request(
+                    to,
+                    Request::new(
+                        RequestKind::Factory(FactoryData::new(ResourceType::Energy, amount * 10)),
+                        Assignment::None,
+                    ),
+                );
+            }
+            self.add_request(
+                from,
+                Request::new(
+                    RequestKind::Transfer(TransferData::new(
+                        resource,
+                        amount,
+                        to,
+                        Some(format!("energy network from: {from}, to: {to}")),
+                    )),
+                    Assignment::None,
+                ),
+            );
+        }
+    }
+
+    // colony mineral stock against the targets and the lab plan
+    fn balance_minerals(&mut self, bases: &HashMap<RoomName, Claimed>) {
+        let stocks: Vec<Stock> = bases.values().map(Stock::new).collect();
+        self.minerals = minerals::balances(&stocks, &self.rooms);
+        for action in minerals::plan(&stocks, &self.rooms, &self.minerals, minerals::price) {
+            match action {
+                MineralAction::Request(room, kind) => {
+                    info!("{} mineral economy: {}", room, kind);
+                    self.add_request(room, Request::new(kind, Assignment::None));
+                }
+                MineralAction::Trade(room, trade) => {
+                    self.journal.record(
+                        JournalEvent::new(
+                            Category::Trade,
+                            Severity::Info,
+                            Some(room),
+                            format!(
+                                "{:?} {}: {} at most {:.3}",
+                                trade.order_type, trade.resource, trade.amount, trade.price
+                            ),
+                        )
+                        .with_key(trade.resource),
+                    );
+                    self.rooms.entry(room).and_modify(|room_state| {
+                        room_state.trades.replace(trade);
+                    });
+                }
+            }
+        }
+    }
+
+    // plans the operator asked to look at
+    fn draw_plans(&mut self) {
+        self.visuals.retain(|_, visual| game::time() <= visual.until);
+        for (name, visual) in &self.visuals {
+            let plan = self.rooms.get(name).and_then(|state| match visual.farm {
+                Some(farm) => state.farms.get(&farm).and_then(FarmInfo::plan),
+                None => state.plan.as_ref(),
+            });
+            if let Some(plan) = plan {
+                visual.draw(visual.farm.unwrap_or(*name), plan);
+            }
+        }
+    }
+
     fn update_avoid_rooms(&mut self) {
         let time = game::time();
         self.avoid_rooms.retain(|_, v| *v > time);
@@ -150,7 +309,7 @@ impl GlobalState {
     pub fn load_or_default() -> GlobalState {
         let s = raw_memory::get().as_string().unwrap();
         info!("Raw memory: {s:?}");
-        match serde_json::from_str(&s) {
+        match profiler::measure(("memory", "deserialize"), || serde_json::from_str(&s)) {
             Ok(v) => {
                 info!("v: {:?}", v);
                 v
@@ -164,7 +323,7 @@ impl GlobalState {
 
     pub fn write(&self) {
         debug!("Writing GameMemory to persistent memory");
-        match serde_json::to_string(&self) {
+        match profiler::measure(("memory", "serialize"), || serde_json::to_string(&self)) {
             Ok(state) => {
                 raw_memory::set(&JsString::from(state));
             }
@@ -179,6 +338,16 @@ impl GlobalState {
             if game::creeps().get(name.clone()).is_some() {
                 true
             } else if !mem.respawned && mem.role.respawn_timeout(None).is_some() {
+                // died before its replacement was ordered
+                self.journal.record(
+                    JournalEvent::new(
+                        Category::CreepLost,
+                        Severity::Warning,
+                        mem.role.get_home().copied(),
+                        format!("{} {} was lost", mem.role, name),
+                    )
+                    .with_key(name),
+                );
                 mem.respawned = true;
                 let _ = mem.role.get_home().map(|home| {
                     self.rooms
@@ -1,7 +1,7 @@
 use std::cmp::Reverse;
 use std::collections::{BTreeMap, HashMap};
 
-use log::{info, warn};
+use log::info;
 use screeps::{
     Deposit, ObjectId, Position, RawObjectId, ResourceType, RoomName, StructurePowerBank, game,
 };
@@ -12,11 +12,14 @@ use super::{
     PowerbankData, ProtectData, Request, RequestKind, TransferData, less_cga, less_power,
     most_ctrl_lvl, most_money, prefered_room,
 };
+use crate::colony::expansion::Candidate;
+use crate::colony::journal::JournalEvent;
 use crate::colony::orders::{
     CaravanOrder, DepositOrder, PowerbankOrder, ProtectOrder, ResourceOrder, WithdrawOrder,
 };
 use crate::resources::chain_config::factory_chain_config;
 use crate::resources::lack_handler_for;
+use crate::rooms::state::constructions::BaseSite;
 use crate::statistics::RoomStats;
 use crate::utils::constants::AVOID_HOSTILE_ROOM_TIMEOUT;
 
@@ -28,10 +31,11 @@ pub enum ColonyEvent {
     DeclareNew(RoomName),
     Caravan(BTreeMap<String, u32>, RoomName),
     Expansion(RoomName, u8, Option<String>, bool),
+    Site(RoomName, BaseSite),
     Powerbank(ObjectId<StructurePowerBank>, Position, u32),
     Deposit(ObjectId<Deposit>, Position, usize),
     Withdraw(RawObjectId, Position, ResourceType, u32),
-    Notify(String, Option<u32>),
+    Journal(JournalEvent),
     Stats(RoomName, RoomStats),
     BlackList(String),
 }
@@ -138,17 +142,18 @@ impl ColonyEvent {
             ColonyEvent::Deposit(id, pos, empty_cells) => {
                 let mut order = ColonyOrder::Deposit(DepositOrder::new(id, pos, empty_cells));
                 if !state.orders.contains(&order)
-                    && let Some((base_name, _)) =
+                    && let Some((base_name, distance)) =
                         prefered_room(pos.room_name(), movement, bases.values(), most_money)
                 {
                     if let ColonyOrder::Deposit(deposit_order) = &mut order {
                         deposit_order.room = Some(base_name);
                     }
                     state.orders.insert(order);
+                    let distance = u32::try_from(distance).unwrap_or_default();
                     state.add_request(
                         base_name,
                         Request::new(
-                            RequestKind::Deposit(DepositData::new(id, pos, empty_cells)),
+                            RequestKind::Deposit(DepositData::new(id, pos, empty_cells, distance)),
                             Assignment::Squads(Vec::new()),
                         ),
                     );
@@ -157,7 +162,7 @@ impl ColonyEvent {
             ColonyEvent::Powerbank(id, pos, amount) => {
                 let mut order = ColonyOrder::Powerbank(PowerbankOrder::new(id, pos, amount));
                 if !state.orders.contains(&order)
-                    && let Some((base_name, _)) =
+                    && let Some((base_name, distance)) =
                         prefered_room(pos.room_name(), movement, bases.values(), less_power)
                 {
                     if let ColonyOrder::Powerbank(powerbank_order) = &mut order {
@@ -165,10 +170,11 @@ impl ColonyEvent {
                     }
                     state.orders.insert(order);
 
+                    let distance = u32::try_from(distance).unwrap_or_default();
                     let pb_data = if state.postponed_farms.contains(&pos.room_name()) {
-                        PowerbankData::postponed(id, pos, amount)
+                        PowerbankData::postponed(id, pos, amount, distance)
                     } else {
-                        PowerbankData::new(id, pos, amount)
+                        PowerbankData::new(id, pos, amount, distance)
                     };
 
                     state.add_request(
@@ -261,9 +267,16 @@ impl ColonyEvent {
                     state.orders.insert(ColonyOrder::Lack(lack_order));
                 }
             }
-            ColonyEvent::Notify(message, interval) => {
-                notify_me(&message, interval);
+            ColonyEvent::Site(room_name, site) => {
+                if !state.rooms.contains_key(&room_name) {
+                    state
+                        .candidates
+                        .entry(room_name)
+                        .and_modify(|candidate| candidate.seen_at = game::time())
+                        .or_insert_with(|| Candidate::new(site, game::time()));
+                }
             }
+            ColonyEvent::Journal(event) => state.journal.record(event),
             ColonyEvent::BlackList(username) => {
                 state.black_list.insert(username);
             }
@@ -273,8 +286,3 @@ impl ColonyEvent {
         }
     }
 }
-
-fn notify_me(message: &str, interval: Option<u32>) {
-    warn!("{}", message);
-    game::notify(message, interval);
-}
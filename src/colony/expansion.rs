@@ -0,0 +1,113 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use itertools::Itertools;
+use screeps::{RoomName, game};
+use serde::{Deserialize, Serialize};
+
+use super::journal::{Category, JournalEvent, Severity};
+use crate::commons::is_cpu_on_low;
+use crate::rooms::state::constructions::{BaseSite, PlanScore, plan_site};
+use crate::utils::constants::{CANDIDATE_MAX, CANDIDATE_RESCORE, CANDIDATE_TTL};
+
+/// A room seen by the scouts that a base could be claimed in
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Candidate {
+    pub site: BaseSite,
+    #[serde(default)]
+    pub score: Option<PlanScore>,
+    /// the planner error when the base does not fit
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub scored_at: Option<u32>,
+    /// the last tick a scout saw the room
+    #[serde(default)]
+    pub seen_at: u32,
+}
+
+impl Candidate {
+    pub const fn new(site: BaseSite, seen_at: u32) -> Self {
+        Self { site, score: None, error: None, scored_at: None, seen_at }
+    }
+
+    fn is_due(&self) -> bool {
+        self.scored_at.is_none_or(|tick| game::time() > tick + CANDIDATE_RESCORE)
+    }
+
+    /// Runs the planner on the room and keeps the score or the error
+    pub fn rank(&mut self, room_name: RoomName) -> Option<JournalEvent> {
+        self.scored_at = Some(game::time());
+        match plan_site(room_name, &self.site) {
+            Ok((_, score)) => {
+                self.score = Some(score);
+                self.error = None;
+                Some(JournalEvent::new(
+                    Category::Planner,
+                    Severity::Info,
+                    Some(room_name),
+                    format!("expansion candidate {room_name}: {score}"),
+                ))
+            }
+            Err(err) => {
+                self.score = None;
+                self.error = Some(err.to_string());
+                None
+            }
+        }
+    }
+}
+
+/// Scores the candidate which waited the longest, one a run since the planner is expensive
+pub(crate) fn rank_next(candidates: &mut HashMap<RoomName, Candidate>) -> Option<JournalEvent> {
+    if is_cpu_on_low() {
+        return None;
+    }
+    let (room_name, candidate) = candidates
+        .iter_mut()
+        .filter(|(_, candidate)| candidate.is_due())
+        .min_by_key(|(_, candidate)| candidate.scored_at)?;
+    candidate.rank(*room_name)
+}
+
+/// Forgets the rooms the scouts lost sight of and the rejected ones once they are due again,
+/// then keeps the best scored rooms and the ones waiting for a score up to `CANDIDATE_MAX`
+pub(crate) fn prune(candidates: &mut HashMap<RoomName, Candidate>, now: u32) {
+    candidates.retain(|_, candidate| {
+        now <= candidate.seen_at + CANDIDATE_TTL
+            && (candidate.error.is_none()
+                || candidate.scored_at.is_some_and(|tick| now <= tick + CANDIDATE_RESCORE))
+    });
+    if candidates.len() <= CANDIDATE_MAX {
+        return;
+    }
+
+    let worst: Vec<RoomName> = candidates
+        .iter()
+        .sorted_by_key(|(_, candidate)| match (&candidate.score, &candidate.error) {
+            (Some(score), _) => (0, Reverse(score.value())),
+            (None, None) => (1, Reverse(0)),
+            (None, Some(_)) => (2, Reverse(0)),
+        })
+        .skip(CANDIDATE_MAX)
+        .map(|(room_name, _)| *room_name)
+        .collect();
+    for room_name in worst {
+        candidates.remove(&room_name);
+    }
+}
+
+pub(crate) fn report(candidates: &HashMap<RoomName, Candidate>) -> String {
+    let mut result = String::new();
+    for (room_name, candidate) in candidates.iter().sorted_by_key(|(_, candidate)| {
+        Reverse(candidate.score.map_or(i64::MIN, |score| score.value()))
+    }) {
+        let _ = match (&candidate.score, &candidate.error) {
+            (Some(score), _) => writeln!(result, "{room_name}: {score}"),
+            (None, Some(error)) => writeln!(result, "{room_name}: {error}"),
+            (None, None) => writeln!(result, "{room_name}: not planned yet"),
+        };
+    }
+    result
+}
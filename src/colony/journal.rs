@@ -0,0 +1,201 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Display, Write};
+
+use log::{info, warn};
+use screeps::{RoomName, game};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::constants::{JOURNAL_DEDUP_TICKS, JOURNAL_SIZE};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    Invasion,
+    SafeMode,
+    Nuke,
+    CreepLost,
+    Trade,
+    Planner,
+    Request,
+}
+
+impl Category {
+    const ALL: [Category; 7] = [
+        Category::Invasion,
+        Category::SafeMode,
+        Category::Nuke,
+        Category::CreepLost,
+        Category::Trade,
+        Category::Planner,
+        Category::Request,
+    ];
+
+    const fn name(self) -> &'static str {
+        match self {
+            Category::Invasion => "invasion",
+            Category::SafeMode => "safe_mode",
+            Category::Nuke => "nuke",
+            Category::CreepLost => "creep_lost",
+            Category::Trade => "trade",
+            Category::Planner => "planner",
+            Category::Request => "request",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|category| category.name() == name)
+    }
+
+    // the lowest severity worth an email and the ticks between two emails with the same key
+    const fn route(self) -> Option<(Severity, u32)> {
+        match self {
+            Category::Invasion => Some((Severity::Warning, 1_500)),
+            Category::SafeMode => Some((Severity::Info, 1_500)),
+            Category::Nuke => Some((Severity::Info, 5_000)),
+            Category::CreepLost | Category::Trade | Category::Request => None,
+            Category::Planner => Some((Severity::Critical, 20_000)),
+        }
+    }
+}
+
+impl Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Operational event worth keeping: what happened, where and how bad
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalEvent {
+    pub category: Category,
+    pub severity: Severity,
+    pub room: Option<RoomName>,
+    pub message: String,
+    // events with the same key are merged
+    key: String,
+}
+
+impl JournalEvent {
+    pub fn new(
+        category: Category,
+        severity: Severity,
+        room: Option<RoomName>,
+        message: String,
+    ) -> Self {
+        let key = room.map_or_else(|| category.to_string(), |room| format!("{category}:{room}"));
+        Self { category, severity, room, message, key }
+    }
+
+    /// Replaces the default category and room key
+    #[must_use]
+    pub fn with_key(self, key: impl Display) -> Self {
+        Self { key: format!("{}:{key}", self.category), ..self }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Entry {
+    first: u32,
+    last: u32,
+    count: u32,
+    event: JournalEvent,
+}
+
+/// Bounded log of operational events, routing the important ones to `game::notify`
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Journal {
+    #[serde(default)]
+    entries: VecDeque<Entry>,
+    // the last email tick per key
+    #[serde(default)]
+    notified: HashMap<String, u32>,
+}
+
+impl Journal {
+    pub fn record(&mut self, event: JournalEvent) {
+        let time = game::time();
+        if event.severity == Severity::Info {
+            info!("journal {}: {}", event.category, event.message);
+        } else {
+            warn!("journal {}: {}", event.category, event.message);
+        }
+        self.route(&event, time);
+
+        if let Some(entry) =
+            self.entries.iter_mut().rev().find(|entry| {
+                entry.event.key == event.key && entry.last + JOURNAL_DEDUP_TICKS >= time
+            })
+        {
+            entry.last = time;
+            entry.count += 1;
+            entry.event.severity = entry.event.severity.max(event.severity);
+            entry.event.message = event.message;
+            return;
+        }
+
+        self.entries.push_back(Entry { first: time, last: time, count: 1, event });
+        while self.entries.len() > JOURNAL_SIZE {
+            self.entries.pop_front();
+        }
+    }
+
+    fn route(&mut self, event: &JournalEvent, time: u32) {
+        let Some((severity, interval)) = event.category.route() else {
+            return;
+        };
+        if event.severity < severity
+            || self.notified.get(&event.key).is_some_and(|last| *last + interval > time)
+        {
+            return;
+        }
+
+        game::notify(&format!("[{time}] {}", event.message), None);
+        self.notified.insert(event.key.clone(), time);
+
+        let longest = Category::ALL
+            .into_iter()
+            .filter_map(Category::route)
+            .map(|(_, interval)| interval)
+            .max()
+            .unwrap_or_default();
+        self.notified.retain(|_, last| *last + longest >= time);
+    }
+
+    /// The latest entries, newest first, optionally of one category
+    pub fn report(&self, category: Option<&str>, limit: usize) -> String {
+        let category = match category.map(Category::from_name) {
+            Some(Some(category)) => Some(category),
+            Some(None) => {
+                let names = Category::ALL.map(Category::name).join(", ");
+                return format!("unknown category, expected one of: {names}");
+            }
+            None => None,
+        };
+
+        let mut result = String::new();
+        for entry in self
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| category.is_none_or(|category| entry.event.category == category))
+            .take(limit)
+        {
+            let _ = write!(
+                result,
+                "[{}] {:?} {}: {}",
+                entry.last, entry.event.severity, entry.event.category, entry.event.message
+            );
+            if entry.count > 1 {
+                let _ = write!(result, " (x{} since {})", entry.count, entry.first);
+            }
+            result.push('\n');
+        }
+        result
+    }
+}
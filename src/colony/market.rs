@@ -0,0 +1,299 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::str::FromStr;
+
+use js_sys::{JsString, Object, Reflect};
+use screeps::game::market::MyOrder;
+use screeps::{MARKET_FEE, MarketResourceType, OrderType, ResourceType, RoomName, game};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+
+use super::journal::{Category, JournalEvent, Severity};
+use crate::rooms::state::{RoomState, TradeData};
+use crate::utils::constants::{
+    MARKET_BAND, MARKET_BUDGET, MARKET_HISTORY_DAYS, MARKET_ORDER_MIN_AMOUNT, MARKET_ORDER_TTL,
+    MARKET_REPRICE,
+};
+
+/// Our own market orders posted for the room trades
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Market {
+    /// credits the orders of a resource may tie up, `MARKET_BUDGET` when not set
+    #[serde(default)]
+    budgets: HashMap<ResourceType, f64>,
+    // remaining amount of every order at the last check, the difference is filled
+    #[serde(default)]
+    remaining: HashMap<String, u32>,
+    // orders posted since the last check, the game shows their ids the tick after
+    #[serde(default)]
+    pending: Vec<(RoomName, ResourceType, OrderType)>,
+}
+
+// one of our orders read from the game
+struct Posted {
+    id: JsString,
+    room: RoomName,
+    resource: ResourceType,
+    order_type: OrderType,
+    price: f64,
+    remaining: u32,
+    // the posted amount with the extensions
+    total: u32,
+    created: u32,
+}
+
+impl Posted {
+    fn from_order(id: String, order: &MyOrder) -> Option<(String, Self)> {
+        let MarketResourceType::Resource(resource) = order.resource_type() else {
+            return None;
+        };
+        let room =
+            order.room_name().and_then(|room| RoomName::from_str(&String::from(room)).ok())?;
+        let posted = Self {
+            id: order.id(),
+            room,
+            resource,
+            order_type: order.order_type(),
+            price: order.price(),
+            remaining: order.remaining_amount(),
+            total: order.total_amount(),
+            created: order.created().unwrap_or_default(),
+        };
+        Some((id, posted))
+    }
+
+    // credits the order keeps busy: the fee and the buy price
+    fn committed(&self) -> f64 {
+        committed(self.order_type, self.price, self.remaining)
+    }
+}
+
+fn committed(order_type: OrderType, price: f64, amount: u32) -> f64 {
+    let total = price * f64::from(amount);
+    let fee = total * f64::from(MARKET_FEE);
+    if order_type == OrderType::Buy { total + fee } else { fee }
+}
+
+impl Market {
+    pub fn set_budget(&mut self, resource: ResourceType, credits: f64) {
+        self.budgets.insert(resource, credits);
+    }
+
+    fn budget(&self, resource: ResourceType) -> f64 {
+        self.budgets.get(&resource).copied().unwrap_or(MARKET_BUDGET)
+    }
+
+    /// Books the fills, reprices, extends or cancels the orders we posted and posts new ones for
+    /// the bulk trades, the orders posted by hand are left alone
+    pub(crate) fn manage(&mut self, rooms: &mut HashMap<RoomName, RoomState>) -> Vec<JournalEvent> {
+        let mut events = Vec::new();
+        let posted = self.posted();
+
+        let mut tied: HashMap<ResourceType, f64> = HashMap::new();
+        for (_, order) in &posted {
+            *tied.entry(order.resource).or_default() += order.committed();
+        }
+
+        let mut covered = HashSet::new();
+        let mut remaining = HashMap::new();
+        for (id, order) in posted {
+            // an order seen the first time books everything filled since it was posted
+            let last = self.remaining.get(&id).copied().unwrap_or(order.total);
+            let filled = last.saturating_sub(order.remaining);
+            let trade = rooms.get_mut(&order.room).and_then(|state| {
+                let mut trade = state
+                    .trades
+                    .get(&TradeData::new(order.order_type, order.resource))
+                    .copied()
+                    .filter(|trade| trade.order_type == order.order_type)?;
+                trade.amount = trade.amount.saturating_sub(filled);
+                state.trades.replace(trade);
+                Some(trade)
+            });
+
+            let stale = game::time().saturating_sub(order.created) > MARKET_ORDER_TTL;
+            let Some(trade) =
+                trade.filter(|trade| trade.amount > 0 && order.remaining > 0 && !stale)
+            else {
+                match game::market::cancel_order(&order.id) {
+                    Ok(()) => {
+                        *tied.entry(order.resource).or_default() -= order.committed();
+                        events.push(event(
+                            Severity::Info,
+                            order.room,
+                            format!(
+                                "cancel {:?} {} order, left: {}",
+                                order.order_type, order.resource, order.remaining
+                            ),
+                        ));
+                    }
+                    Err(err) => events.push(event(
+                        Severity::Warning,
+                        order.room,
+                        format!("cancel {} order error: {:?}", order.resource, err),
+                    )),
+                }
+                continue;
+            };
+
+            if let Some(price) = price(&trade)
+                && (price - order.price).abs() > order.price * MARKET_REPRICE
+                && let Err(err) = game::market::change_order_price(&order.id, price)
+            {
+                events.push(event(
+                    Severity::Warning,
+                    order.room,
+                    format!("reprice {} order error: {:?}", order.resource, err),
+                ));
+            }
+
+            let lack = trade.amount.saturating_sub(order.remaining);
+            let spent = tied.get(&order.resource).copied().unwrap_or_default();
+            if lack > 0
+                && spent + committed(order.order_type, order.price, lack)
+                    <= self.budget(order.resource)
+            {
+                match game::market::extend_order(&order.id, lack) {
+                    Ok(()) => {
+                        *tied.entry(order.resource).or_default() +=
+                            committed(order.order_type, order.price, lack);
+                    }
+                    Err(err) => events.push(event(
+                        Severity::Warning,
+                        order.room,
+                        format!("extend {} order error: {:?}", order.resource, err),
+                    )),
+                }
+            }
+
+            covered.insert((order.room, order.resource));
+            remaining.insert(id, order.remaining);
+        }
+        self.remaining = remaining;
+
+        for (room, state) in rooms.iter_mut() {
+            let trades: Vec<TradeData> = state.trades.iter().copied().collect();
+            for mut trade in trades {
+                trade.posted = covered.contains(&(*room, trade.resource));
+                if !trade.posted
+                    && trade.amount >= MARKET_ORDER_MIN_AMOUNT
+                    && let Some(price) = price(&trade)
+                {
+                    let cost = committed(trade.order_type, price, trade.amount);
+                    let spent = tied.get(&trade.resource).copied().unwrap_or_default();
+                    if spent + cost <= self.budget(trade.resource)
+                        && cost <= game::market::credits()
+                    {
+                        match create_order(*room, &trade, price) {
+                            Ok(()) => {
+                                trade.posted = true;
+                                self.pending.push((*room, trade.resource, trade.order_type));
+                                *tied.entry(trade.resource).or_default() += cost;
+                                events.push(event(
+                                    Severity::Info,
+                                    *room,
+                                    format!(
+                                        "post {:?} {}: {} at {:.3}",
+                                        trade.order_type, trade.resource, trade.amount, price
+                                    ),
+                                ));
+                            }
+                            Err(err) => events.push(event(Severity::Warning, *room, err)),
+                        }
+                    }
+                }
+                state.trades.replace(trade);
+            }
+        }
+        events
+    }
+
+    // the game orders we posted, the pending ones are matched by room, resource and type
+    fn posted(&mut self) -> Vec<(String, Posted)> {
+        let mut pending = std::mem::take(&mut self.pending);
+        game::market::orders()
+            .entries()
+            .filter_map(|(id, order)| Posted::from_order(id, &order))
+            .filter(|(id, order)| {
+                self.remaining.contains_key(id)
+                    || pending
+                        .iter()
+                        .position(|posted| {
+                            *posted == (order.room, order.resource, order.order_type)
+                        })
+                        .map(|index| pending.swap_remove(index))
+                        .is_some()
+            })
+            .collect()
+    }
+
+    pub fn report(&self) -> String {
+        let orders = game::market::orders();
+        let mut result = format!("credits: {:.0}\n", game::market::credits());
+        for (id, order) in orders.entries() {
+            let Some((_, order)) =
+                Posted::from_order(id, &order).filter(|(id, _)| self.remaining.contains_key(id))
+            else {
+                continue;
+            };
+            let _ = writeln!(
+                result,
+                "{} {:?} {}: {} at {:.3}, since {}, budget: {:.0}",
+                order.room,
+                order.order_type,
+                order.resource,
+                order.remaining,
+                order.price,
+                order.created,
+                self.budget(order.resource)
+            );
+        }
+        result
+    }
+}
+
+// the recent band around the average price, held by the trade price limit
+fn price(trade: &TradeData) -> Option<f64> {
+    let history = game::market::get_history(Some(trade.resource));
+    let days = history.iter().rev().take(MARKET_HISTORY_DAYS).collect::<Vec<_>>();
+    if days.is_empty() {
+        return None;
+    }
+    let count = f64::from(u32::try_from(days.len()).unwrap_or(1));
+    let average = days.iter().map(|record| record.avg_price()).sum::<f64>() / count;
+    let stddev = days.iter().map(|record| record.stddev_price()).sum::<f64>() / count;
+
+    let limit = *trade.price;
+    let price = if trade.order_type == OrderType::Sell {
+        (average + stddev * MARKET_BAND).max(limit)
+    } else if limit > 0. {
+        (average - stddev * MARKET_BAND).min(limit)
+    } else {
+        average - stddev * MARKET_BAND
+    };
+    (price > 0.).then_some(price)
+}
+
+fn create_order(room: RoomName, trade: &TradeData, price: f64) -> Result<(), String> {
+    let order_type = if trade.order_type == OrderType::Sell { "sell" } else { "buy" };
+    let parameters = Object::new();
+    [
+        ("type", JsValue::from_str(order_type)),
+        ("resourceType", JsValue::from_str(&trade.resource.to_string())),
+        ("price", JsValue::from_f64(price)),
+        ("totalAmount", JsValue::from(trade.amount)),
+        ("roomName", JsValue::from_str(&room.to_string())),
+    ]
+    .into_iter()
+    .try_for_each(|(key, value)| {
+        Reflect::set(&parameters, &JsValue::from_str(key), &value).map(|_| ())
+    })
+    .map_err(|err| format!("order parameters error: {err:?}"))?;
+
+    game::market::create_order(&parameters)
+        .map_err(|err| format!("post {} order error: {:?}", trade.resource, err))
+}
+
+fn event(severity: Severity, room: RoomName, message: String) -> JournalEvent {
+    JournalEvent::new(Category::Trade, severity, Some(room), message)
+}
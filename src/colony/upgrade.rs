@@ -0,0 +1,127 @@
+use std::fmt::{self, Display};
+
+use screeps::{
+    CONTROLLER_MAX_UPGRADE_PER_TICK, HasPosition, LINK_CAPACITY, ResourceType, StructureContainer,
+    controller_downgrade,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::rooms::wrappers::claimed::Claimed;
+use crate::utils::constants::{
+    UPGRADE_EARLY_HORIZON, UPGRADE_ENERGY_RESERVE, UPGRADE_GCL_ENERGY, UPGRADE_HORIZON,
+};
+
+/// What the room spends its upgrade energy for
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpgradeGoal {
+    /// RCL 6 unlocks the terminal
+    Terminal,
+    /// RCL 7 unlocks the factory
+    Factory,
+    Rcl8,
+    /// RCL 8 with spare energy farms GCL at 15 energy per tick
+    Gcl,
+    /// keep the controller from downgrading only
+    Hold,
+}
+
+impl UpgradeGoal {
+    pub const fn pushes_level(self) -> bool {
+        matches!(self, UpgradeGoal::Terminal | UpgradeGoal::Factory | UpgradeGoal::Rcl8)
+    }
+}
+
+/// Upgrade energy allocated to the room by the colony
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpgradePlan {
+    pub goal: UpgradeGoal,
+    /// energy per tick, one work part each
+    pub work: u32,
+    pub upgraders: u32,
+    pub boost: bool,
+}
+
+impl Display for UpgradePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?}: {} work in {} upgraders, boost: {}",
+            self.goal, self.work, self.upgraders, self.boost
+        )
+    }
+}
+
+pub(crate) fn plan(base: &Claimed) -> UpgradePlan {
+    let controller = &base.controller;
+    let level = controller.level();
+    let energy = stored_energy(base);
+
+    let goal = match level {
+        ..=5 => UpgradeGoal::Terminal,
+        6 => UpgradeGoal::Factory,
+        7 => UpgradeGoal::Rcl8,
+        _ if energy > UPGRADE_GCL_ENERGY => UpgradeGoal::Gcl,
+        _ => UpgradeGoal::Hold,
+    };
+
+    // half of the downgrade timer is gone, keep at least one upgrader whatever the energy
+    let downgrading = controller.ticks_to_downgrade().is_some_and(|ticks| {
+        controller_downgrade(level).is_some_and(|downgrade| ticks < downgrade / 2)
+    });
+
+    let budget = match goal {
+        UpgradeGoal::Hold => 0,
+        UpgradeGoal::Gcl => CONTROLLER_MAX_UPGRADE_PER_TICK,
+        _ if base.storage().is_none() => energy / UPGRADE_EARLY_HORIZON,
+        _ => energy.saturating_sub(UPGRADE_ENERGY_RESERVE) / UPGRADE_HORIZON,
+    };
+    let work = budget.min(link_rate(base).unwrap_or(u32::MAX));
+    let work = if downgrading { work.max(1) } else { work };
+
+    let per_upgrader = max_work(base);
+    UpgradePlan {
+        goal,
+        work,
+        upgraders: work.div_ceil(per_upgrader),
+        boost: goal != UpgradeGoal::Hold && work > 0,
+    }
+}
+
+// the storage energy, or the energy in the controller and hub containers before the storage is
+// built. The upgraders take it from the controller container then, none without one
+fn stored_energy(base: &Claimed) -> u32 {
+    let ctrl = base.controller.pos();
+    let near_ctrl = |container: &&StructureContainer| container.pos().get_range_to(ctrl) <= 2;
+    // the hub is around the spawns until the storage is built
+    let in_hub = |container: &&StructureContainer| {
+        base.spawns.iter().any(|spawn| spawn.pos().get_range_to(container.pos()) <= 2)
+    };
+    match base.storage() {
+        Some(storage) => storage.store().get_used_capacity(Some(ResourceType::Energy)),
+        None if base.containers.iter().any(|container| near_ctrl(&container)) => base
+            .containers
+            .iter()
+            .filter(|container| near_ctrl(container) || in_hub(container))
+            .map(|container| container.store().get_used_capacity(Some(ResourceType::Energy)))
+            .sum(),
+        None => 0,
+    }
+}
+
+// energy per tick the controller link brings, the link cooldown equals the range
+fn link_rate(base: &Claimed) -> Option<u32> {
+    let ctrl = base.links.ctrl()?;
+    let sender = base.links.sender()?;
+    let range = sender.pos().get_range_to(ctrl.pos()).max(1);
+    // 3% is lost on every transfer
+    Some(LINK_CAPACITY * 97 / 100 / range)
+}
+
+// work parts one upgrader gets: 5 work and a move per group, a carry and 19 parts at RCL 8
+fn max_work(base: &Claimed) -> u32 {
+    if base.controller.level() == 8 {
+        return CONTROLLER_MAX_UPGRADE_PER_TICK;
+    }
+    let capacity = base.room.energy_capacity_available();
+    (capacity.saturating_sub(50) / 550).clamp(1, 8) * 5
+}
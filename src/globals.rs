@@ -2,22 +2,100 @@ use std::collections::HashMap;
 use std::fmt::Write;
 use std::str::FromStr;
 
+use itertools::Itertools;
 use log::info;
 use ordered_float::OrderedFloat;
 use screeps::{
-    OrderType, OwnedStructureProperties, ResourceType, RoomName, RoomXY, StructureObject,
-    StructureProperties, find, game,
+    HasPosition, OrderType, OwnedStructureProperties, ResourceType, RoomName, RoomXY,
+    StructureObject, StructureProperties, find, game,
 };
 use wasm_bindgen::JsValue;
 use wasm_bindgen::prelude::wasm_bindgen;
 
 use crate::GLOBAL_MEMORY;
+use crate::colony::expansion;
+use crate::rooms::state::constructions::exchange;
+use crate::rooms::state::constructions::migration::Migration;
+use crate::rooms::state::constructions::visualizer::PlanVisual;
 use crate::rooms::state::constructions::{PlannedCell, RoomStructure};
-use crate::rooms::state::requests::Request;
+use crate::rooms::state::requests::assignment::Assignment;
+use crate::rooms::state::requests::{Request, RequestKind, SiegeData};
 use crate::rooms::state::{BoostReason, FarmInfo, RoomState, TradeData};
 use crate::rooms::wrappers::claimed::Claimed;
 use crate::units::creeps::CreepMemory;
 use crate::units::roles::Role;
+use crate::utils::constants::PLAN_VISUAL_TICKS;
+use crate::utils::profiler;
+
+/// Top cpu consumers of the last profiler window
+#[wasm_bindgen]
+pub fn profile(top: Option<usize>) -> String {
+    profiler::report(top.unwrap_or(20))
+}
+
+/// Latest journal entries, optionally of one category
+#[wasm_bindgen]
+pub fn journal(category: Option<String>, limit: Option<usize>) -> String {
+    GLOBAL_MEMORY.with(|mem_refcell| {
+        mem_refcell.borrow().journal.report(category.as_deref(), limit.unwrap_or(20))
+    })
+}
+
+/// Colony balance of the base minerals from the last mineral economy plan
+#[wasm_bindgen]
+pub fn minerals() -> String {
+    GLOBAL_MEMORY.with(|mem_refcell| {
+        let state = mem_refcell.borrow();
+        state
+            .minerals
+            .iter()
+            .sorted_by_key(|(mineral, _)| mineral.to_string())
+            .map(|(mineral, balance)| format!("{mineral}: {balance}"))
+            .join("\n")
+    })
+}
+
+/// Expansion candidates from the best plan score down
+#[wasm_bindgen]
+pub fn candidates() -> String {
+    GLOBAL_MEMORY.with(|mem_refcell| expansion::report(&mem_refcell.borrow().candidates))
+}
+
+/// Plans and scores an expansion candidate right now
+#[wasm_bindgen]
+pub fn rank_candidate(room_name: String) -> String {
+    match RoomName::from_str(&room_name) {
+        Ok(room_name) => GLOBAL_MEMORY.with(|mem_refcell| {
+            let mut memory = mem_refcell.borrow_mut();
+            let Some(candidate) = memory.candidates.get_mut(&room_name) else {
+                return format!("candidate: {room_name} not found!");
+            };
+            candidate.rank(room_name);
+            match (&candidate.score, &candidate.error) {
+                (Some(score), _) => format!("{room_name}: {score}"),
+                (None, error) => format!("{room_name}: {}", error.as_deref().unwrap_or_default()),
+            }
+        }),
+        Err(error) => {
+            format!("incorrect room name: {error}")
+        }
+    }
+}
+
+/// Our market orders with the credits and budgets
+#[wasm_bindgen]
+pub fn market() -> String {
+    GLOBAL_MEMORY.with(|mem_refcell| mem_refcell.borrow().market.report())
+}
+
+/// Credits the market orders of a resource may tie up
+#[wasm_bindgen]
+pub fn market_budget(resource: ResourceType, credits: f64) -> String {
+    GLOBAL_MEMORY.with(|mem_refcell| {
+        mem_refcell.borrow_mut().market.set_budget(resource, credits);
+        format!("{resource} market budget: {credits}")
+    })
+}
 
 #[wasm_bindgen]
 pub fn info() -> String {
@@ -174,10 +252,29 @@ pub fn requests(room_name: String) -> String {
         Ok(room_name) => {
             GLOBAL_MEMORY.with(|mem_refcell| match mem_refcell.borrow().rooms.get(&room_name) {
                 Some(claimed) => {
+                    let time = game::time();
                     let mut result = format!("room: {room_name} requests: \n");
-                    for (i, request) in claimed.requests.iter().enumerate() {
-                        let _ = writeln!(result, "{i}: {request} ");
-                        // result.push_str(&format!("{i}: {request} \n"));
+                    for (i, request) in
+                        claimed.requests.iter().sorted_by_key(|r| r.created_at()).enumerate()
+                    {
+                        let meta = &request.meta;
+                        let _ = write!(
+                            result,
+                            "{i}: {} priority: {}, age: {}, idle: {}, assign: {}, status: ",
+                            request.kind,
+                            request.priority(),
+                            time.saturating_sub(meta.created_at),
+                            time.saturating_sub(meta.updated_at),
+                            request.assignment
+                        );
+                        for (status, tick) in &meta.history {
+                            let _ = write!(result, "{status:?} -> ({tick}) ");
+                        }
+                        let _ = write!(result, "{:?}", meta.status);
+                        if let Some(note) = &meta.note {
+                            let _ = write!(result, ", note: {note}");
+                        }
+                        result.push('\n');
                     }
                     result
                 }
@@ -192,6 +289,35 @@ pub fn requests(room_name: String) -> String {
     }
 }
 
+//siege('W1N1', 'W2N1')
+#[wasm_bindgen]
+pub fn siege(room_name: String, target: String) -> String {
+    match RoomName::from_str(&room_name) {
+        Ok(home_room) => match RoomName::from_str(&target) {
+            Ok(target_room) => GLOBAL_MEMORY.with(|mem_refcell| {
+                match mem_refcell.borrow_mut().rooms.get_mut(&home_room) {
+                    Some(claimed) => {
+                        let request = Request::new(
+                            RequestKind::Siege(SiegeData::new(target_room)),
+                            Assignment::Squads(Vec::new()),
+                        );
+                        if claimed.requests.insert(request) {
+                            format!("room: {room_name} started siege of {target}")
+                        } else {
+                            format!("room: {room_name} already sieges {target}")
+                        }
+                    }
+                    _ => {
+                        format!("room: {room_name} is not claimed room")
+                    }
+                }
+            }),
+            Err(err) => format!("incorrect target room name: {err}"),
+        },
+        Err(error) => format!("incorrect room name: {error}"),
+    }
+}
+
 #[wasm_bindgen]
 pub fn resolve_request(room_name: String, request: JsValue) -> String {
     match RoomName::from_str(&room_name) {
@@ -277,6 +403,32 @@ pub fn add_farm(room_name: String, remote_name: String) -> String {
     }
 }
 
+#[wasm_bindgen]
+pub fn farms(room_name: String) -> String {
+    match RoomName::from_str(&room_name) {
+        Ok(room_name) => GLOBAL_MEMORY.with(|mem_refcell| {
+            mem_refcell.borrow().rooms.get(&room_name).map_or_else(
+                || format!("room: {room_name} is not claimed room"),
+                |claimed| {
+                    claimed
+                        .farms
+                        .iter()
+                        .map(|(name, info)| {
+                            format!(
+                                "{name}: active: {}, planned: {}, road upkeep: {:.2} energy/tick",
+                                info.is_active(),
+                                info.plan().is_some(),
+                                info.upkeep()
+                            )
+                        })
+                        .join("\n")
+                },
+            )
+        }),
+        Err(error) => format!("incorrect room name: {error}"),
+    }
+}
+
 #[wasm_bindgen]
 pub fn add_boost(room_name: String, boost: u8, timeout: u32) -> String {
     info!("room_name: {}, add boost: {}", room_name, boost);
@@ -291,6 +443,7 @@ pub fn add_boost(room_name: String, boost: u8, timeout: u32) -> String {
                         3 => BoostReason::Dismantle,
                         4 => BoostReason::Caravan,
                         5 => BoostReason::Carry,
+                        6 => BoostReason::Powerbank,
                         _ => BoostReason::Pvp,
                     };
                     claimed.boosts.insert(boost_reason, game::time() + timeout);
@@ -319,6 +472,7 @@ pub fn delete_boost(room_name: String, boost: u8) -> String {
                         3 => BoostReason::Dismantle,
                         4 => BoostReason::Caravan,
                         5 => BoostReason::Carry,
+                        6 => BoostReason::Powerbank,
                         _ => BoostReason::Pvp,
                     };
                     claimed.boosts.remove(&boost_reason);
@@ -421,6 +575,85 @@ pub fn plan_for(room_name: String, x: u8, y: u8) -> String {
     }
 }
 
+#[wasm_bindgen]
+pub fn visualize_plan(
+    room_name: String,
+    farm: Option<String>,
+    lvl: Option<u8>,
+    ticks: Option<u32>,
+    zones: Option<bool>,
+) -> String {
+    let room_name = match RoomName::from_str(&room_name) {
+        Ok(room_name) => room_name,
+        Err(error) => return format!("incorrect room name: {error}"),
+    };
+    let farm = match farm.map(|farm| RoomName::from_str(&farm)).transpose() {
+        Ok(farm) => farm,
+        Err(error) => return format!("incorrect farm name: {error}"),
+    };
+    GLOBAL_MEMORY.with(|mem_refcell| {
+        let mut memory = mem_refcell.borrow_mut();
+        if !memory.rooms.contains_key(&room_name) {
+            return format!("memory: {room_name} not found!");
+        }
+        let until = game::time() + ticks.unwrap_or(PLAN_VISUAL_TICKS);
+        let visual = PlanVisual::new(farm, lvl, zones.unwrap_or_default(), until);
+        memory.visuals.insert(room_name, visual);
+        format!("draw plan of: {} until: {until}", farm.unwrap_or(room_name))
+    })
+}
+
+#[wasm_bindgen]
+pub fn export_plan(room_name: String, rcl: Option<u8>) -> String {
+    match RoomName::from_str(&room_name) {
+        Ok(room_name) => GLOBAL_MEMORY.with(|mem_refcell| {
+            let memory = mem_refcell.borrow();
+            let Some(plan) = memory.rooms.get(&room_name).and_then(|state| state.plan.as_ref())
+            else {
+                return format!("plan for: {room_name} not found!");
+            };
+            let document = exchange::export(plan, Some(room_name.to_string()), rcl.unwrap_or(8));
+            serde_json::to_string(&document)
+                .unwrap_or_else(|error| format!("plan export error: {error}"))
+        }),
+        Err(error) => {
+            format!("incorrect room name: {error}")
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub fn import_plan(room_name: String, document: String) -> String {
+    let room_name = match RoomName::from_str(&room_name) {
+        Ok(room_name) => room_name,
+        Err(error) => return format!("incorrect room name: {error}"),
+    };
+    let document = match serde_json::from_str::<exchange::BuildingPlan>(&document) {
+        Ok(document) => document,
+        Err(error) => return format!("incorrect plan: {error}"),
+    };
+    let Some(terrain) = game::map::get_room_terrain(room_name) else {
+        return format!("terrain of: {room_name} not found!");
+    };
+    let ctrl = game::rooms()
+        .get(room_name)
+        .and_then(|room| room.controller())
+        .map(|controller| controller.pos().xy());
+
+    match exchange::import(&document, &|x, y| terrain.get(x, y), ctrl) {
+        Ok(plan) => GLOBAL_MEMORY.with(|mem_refcell| {
+            match mem_refcell.borrow_mut().rooms.get_mut(&room_name) {
+                Some(state) => {
+                    state.plan = Some(plan);
+                    format!("plan imported for: {room_name}")
+                }
+                None => format!("memory: {room_name} not found!"),
+            }
+        }),
+        Err(error) => format!("plan import error: {error}"),
+    }
+}
+
 #[wasm_bindgen]
 pub fn add_plan(
     room_name: String,
@@ -482,6 +715,50 @@ pub fn delete_plan_for(room_name: String, x: u8, y: u8, structure: JsValue) -> S
     }
 }
 
+/// Moves the built structures of a base onto its current plan
+#[wasm_bindgen]
+pub fn migrate_base(room_name: String) -> String {
+    match RoomName::from_str(&room_name) {
+        Ok(room_name) => GLOBAL_MEMORY.with(|mem_refcell| {
+            match mem_refcell.borrow_mut().rooms.get_mut(&room_name) {
+                Some(state) if state.plan.is_none() => format!("plan for: {room_name} not found!"),
+                Some(state) => {
+                    if let Some(migration) = state.migration {
+                        format!("{room_name} migration is running: {migration}")
+                    } else {
+                        state.migration = Some(Migration::new(game::time()));
+                        format!("{room_name} migration started")
+                    }
+                }
+                None => format!("room: {room_name} is not claimed room"),
+            }
+        }),
+        Err(error) => {
+            format!("incorrect room name: {error}")
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub fn abort_migration(room_name: String) -> String {
+    match RoomName::from_str(&room_name) {
+        Ok(room_name) => GLOBAL_MEMORY.with(|mem_refcell| {
+            let migration = mem_refcell
+                .borrow_mut()
+                .rooms
+                .get_mut(&room_name)
+                .and_then(|state| state.migration.take());
+            migration.map_or_else(
+                || format!("{room_name} has no migration"),
+                |migration| format!("{room_name} migration aborted: {migration}"),
+            )
+        }),
+        Err(error) => {
+            format!("incorrect room name: {error}")
+        }
+    }
+}
+
 #[wasm_bindgen]
 pub fn generate_plan(room_name: String, x0: u8, y0: u8, x1: u8, y1: u8) -> String {
     match RoomName::from_str(&room_name) {
@@ -9,7 +9,6 @@ use wasm_bindgen::prelude::wasm_bindgen;
 use web_sys::console;
 
 struct JsLog;
-struct JsNotify;
 
 impl log::Log for JsLog {
     fn enabled(&self, _: &log::Metadata<'_>) -> bool {
@@ -22,17 +21,6 @@ impl log::Log for JsLog {
 
     fn flush(&self) {}
 }
-impl log::Log for JsNotify {
-    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
-        true
-    }
-
-    fn log(&self, record: &log::Record<'_>) {
-        game::notify(&format!("{}", record.args()), None);
-    }
-
-    fn flush(&self) {}
-}
 
 pub fn setup_logging(verbosity: log::LevelFilter) {
     fern::Dispatch::new()
@@ -41,15 +29,6 @@ pub fn setup_logging(verbosity: log::LevelFilter) {
             out.finish(format_args!("({}) {}: {}", record.level(), record.target(), message));
         })
         .chain(Box::new(JsLog) as Box<dyn log::Log>)
-        .chain(
-            fern::Dispatch::new()
-                .level(log::LevelFilter::Warn)
-                .format(|out, message, _record| {
-                    let time = game::time();
-                    out.finish(format_args!("[{time}] {message}"));
-                })
-                .chain(Box::new(JsNotify) as Box<dyn log::Log>),
-        )
         .apply()
         .expect("expected setup_logging to only ever be called once per instance");
     panic::set_hook(Box::new(panic_hook));
@@ -103,4 +82,6 @@ fn panic_hook(info: &std::panic::PanicHookInfo) {
     }
 
     error!("{}", fmt_error);
+    // the journal lives in the borrowed global state, a panic goes to the mail directly
+    game::notify(&format!("[{}] {}", game::time(), fmt_error), Some(60));
 }
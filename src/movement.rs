@@ -22,6 +22,7 @@ use crate::movement::callback::{PathOptions, SingleRoomCallback};
 use crate::utils::constants::{HEURISTIC_WEIGHT, MAX_OPS};
 
 pub mod callback;
+pub mod formation;
 mod goal;
 mod path_state;
 pub mod walker;
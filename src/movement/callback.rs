@@ -99,9 +99,10 @@ pub fn construction_single_room(
     }
 }
 
-pub fn construction_multi_rooms(
-    planned: &HashMap<RoomName, Vec<RoomXY>>,
-) -> impl FnMut(RoomName) -> MultiRoomCostResult + use<'_> {
+pub fn construction_multi_rooms<'a>(
+    planned: &'a HashMap<RoomName, Vec<RoomXY>>,
+    roads: &'a HashSet<Position>,
+) -> impl FnMut(RoomName) -> MultiRoomCostResult + use<'a> {
     move |room_name: RoomName| -> MultiRoomCostResult {
         let mut matrix = LocalCostMatrix::new();
         if let Some(room) = game::rooms().get(room_name) {
@@ -138,6 +139,10 @@ pub fn construction_multi_rooms(
                             2 => matrix.set(xy, 0xc8), //200
                             3 => matrix.set(xy, 0x05), //5
                             4 => matrix.set(xy, 0x04), //4
+                            // planned roads draw the routes together
+                            _ if roads.contains(&Position::new(xy.x, xy.y, room_name)) => {
+                                matrix.set(xy, 0x01);
+                            }
                             _ => {}
                         }
                     }
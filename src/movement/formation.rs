@@ -0,0 +1,129 @@
+use std::collections::HashSet;
+
+use log::debug;
+use screeps::local::{LocalCostMatrix, RoomName};
+use screeps::pathfinder::{MultiRoomCostResult, SearchOptions};
+use screeps::prelude::*;
+use screeps::{LocalRoomTerrain, Position, RoomXY, StructureObject, Terrain, find, game};
+use serde::{Deserialize, Serialize};
+
+use crate::movement::MovementGoal;
+use crate::utils::constants::{HEURISTIC_WEIGHT, MAX_OPS, MAX_ROOMS};
+
+/// Quad slots relative to the top left member
+pub const QUAD_OFFSETS: [(i32, i32); 4] = [(0, 0), (1, 0), (0, 1), (1, 1)];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Formation {
+    /// every member moves on its own
+    #[default]
+    Loose,
+    /// the leader paths, the follower steps on the leader's previous tile
+    Duo,
+    /// 2x2 block paths as one unit
+    Quad,
+}
+
+// path of the top left quad member, shared by the whole squad
+#[derive(Debug, Clone)]
+pub struct FormationPath {
+    pub goal: MovementGoal,
+    pub path: Vec<Position>,
+    /// the anchor gathering point behind a room edge
+    pub rally: Option<Position>,
+}
+
+impl FormationPath {
+    pub fn try_new(from: Position, goal: MovementGoal) -> Option<Self> {
+        let options = SearchOptions::new(quad_callback)
+            .max_ops(MAX_OPS)
+            .max_rooms(MAX_ROOMS)
+            .plain_cost(1)
+            .swamp_cost(5)
+            .heuristic_weight(HEURISTIC_WEIGHT);
+
+        let search = screeps::pathfinder::search(from, goal.pos, goal.range, Some(options));
+        if search.incomplete() {
+            debug!("incomplete quad path from: {}, to: {}", from, goal.pos);
+        }
+        let path = search.path();
+        (!path.is_empty()).then_some(Self { goal, path, rally: None })
+    }
+
+    /// next anchor position or None if the anchor is out of the path
+    pub fn next_step(&self, anchor: Position) -> Option<Position> {
+        if self.goal.is_goal_met(anchor) {
+            None
+        } else if let Some(index) = self.path.iter().position(|pos| *pos == anchor) {
+            self.path.get(index + 1).copied()
+        } else {
+            self.path.first().copied().filter(|pos| pos.is_near_to(anchor))
+        }
+    }
+
+    /// the first anchor position behind the next room edge with the whole quad off the edges
+    pub fn rally_after(&self, anchor: Position) -> Option<Position> {
+        let start = self.path.iter().position(|pos| *pos == anchor).unwrap_or_default();
+        self.path
+            .iter()
+            .skip(start)
+            .filter(|pos| pos.room_name() != anchor.room_name())
+            .find(|pos| quad_positions(**pos).iter().all(|slot| !slot.is_room_edge()))
+            .copied()
+    }
+}
+
+/// positions of the quad slots for the top left anchor
+pub fn quad_positions(anchor: Position) -> Vec<Position> {
+    QUAD_OFFSETS.iter().filter_map(|offset| anchor.checked_add(*offset).ok()).collect()
+}
+
+fn quad_callback(room_name: RoomName) -> MultiRoomCostResult {
+    match game::map::get_room_terrain(room_name) {
+        Some(terrain) => MultiRoomCostResult::CostMatrix(
+            quad_cost_matrix(room_name, &LocalRoomTerrain::from(terrain)).into(),
+        ),
+        None => MultiRoomCostResult::Impassable,
+    }
+}
+
+// every tile costs as the worst tile of the 2x2 square with this tile at the top left
+fn quad_cost_matrix(room_name: RoomName, terrain: &LocalRoomTerrain) -> LocalCostMatrix {
+    let mut blocked: HashSet<RoomXY> = HashSet::new();
+    if let Some(room) = game::rooms().get(room_name) {
+        for structure in room.find(find::STRUCTURES, None) {
+            match structure {
+                StructureObject::StructureRoad(_) | StructureObject::StructureContainer(_) => {}
+                StructureObject::StructureRampart(rampart) if rampart.my() => {}
+                _ => {
+                    blocked.insert(structure.pos().xy());
+                }
+            }
+        }
+    }
+
+    let mut matrix = LocalCostMatrix::new();
+    for x in 0..50 {
+        for y in 0..50 {
+            // tiles out of the room are checked in the next room
+            let cost = QUAD_OFFSETS
+                .into_iter()
+                .filter_map(|(dx, dy)| {
+                    RoomXY::checked_new(x + u8::try_from(dx).ok()?, y + u8::try_from(dy).ok()?).ok()
+                })
+                .map(|xy| match terrain.get_xy(xy) {
+                    _ if blocked.contains(&xy) => 0xff,
+                    Terrain::Wall => 0xff,
+                    Terrain::Swamp => 5,
+                    Terrain::Plain => 1,
+                })
+                .max()
+                .unwrap_or(0xff);
+
+            if let Ok(xy) = RoomXY::checked_new(x, y) {
+                matrix.set(xy, cost);
+            }
+        }
+    }
+    matrix
+}
@@ -11,6 +11,7 @@ use crate::movement::MovementProfile;
 use crate::movement::callback::PathOptions;
 use crate::utils::commons::is_cpu_on_low;
 use crate::utils::constants::MAX_ROOMS;
+use crate::utils::profiler;
 
 // struct for specifying where a creep wants to move and the options the
 // pathfinder will need to know to get them there
@@ -126,12 +127,14 @@ impl MovementGoal {
                 allowed_rooms,
             };
 
-            Some(screeps::pathfinder::search(
-                from_position,
-                self.pos,
-                self.range,
-                self.profile.search_options(options, self.flee, max_rooms),
-            ))
+            Some(profiler::measure(("movement", "pathfinder"), || {
+                screeps::pathfinder::search(
+                    from_position,
+                    self.pos,
+                    self.range,
+                    self.profile.search_options(options, self.flee, max_rooms),
+                )
+            }))
         }
     }
 
@@ -140,8 +143,11 @@ impl MovementGoal {
         from_room: RoomName,
         route_options: map::FindRouteOptions<impl FnMut(RoomName, RoomName) -> f64>,
     ) -> HashSet<RoomName> {
+        let route = profiler::measure(("movement", "find_route"), || {
+            map::find_route(from_room, self.pos.room_name(), Some(route_options))
+        });
         let mut allowed_rooms =
-            match map::find_route(from_room, self.pos.room_name(), Some(route_options)) {
+            match route {
                 Ok(steps) => steps.into_iter().map(|step| step.room).collect(),
                 Err(e) => {
                     warn!("can't find high level route: {:?}", e);
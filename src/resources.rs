@@ -1,7 +1,7 @@
 use std::cmp;
 use std::collections::HashMap;
 
-use screeps::{RawObjectId, ResourceType, RoomName};
+use screeps::{ResourceType, RoomName};
 
 use crate::colony::events::ColonyContext;
 use crate::resources::handlers::get_handler_for;
@@ -10,25 +10,25 @@ use crate::rooms::RoomEvent;
 // mod policy;
 pub mod chain_config;
 mod handlers;
+pub mod minerals;
+pub mod network;
 
 //todo statistics and colony_events resource handlers
 pub struct RoomContext {
     pub rcl: u8,
-    pub terminal: Option<RawObjectId>,
-    pub storage: Option<RawObjectId>,
     pub fl: u8,
     pub built_all: bool,
+    /// recent deposit harvest by resource
+    pub income: HashMap<ResourceType, u32>,
 }
 
 impl RoomContext {
-    pub const fn new(
-        rcl: u8,
-        terminal: Option<RawObjectId>,
-        storage: Option<RawObjectId>,
-        fl: u8,
-        built_all: bool,
-    ) -> Self {
-        Self { rcl, terminal, storage, fl, built_all }
+    pub const fn new(rcl: u8, fl: u8, built_all: bool, income: HashMap<ResourceType, u32>) -> Self {
+        Self { rcl, fl, built_all, income }
+    }
+
+    pub fn income(&self, res: ResourceType) -> u32 {
+        self.income.get(&res).copied().unwrap_or_default()
     }
 }
 
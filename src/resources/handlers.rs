@@ -3,10 +3,11 @@ use std::cmp::max;
 use screeps::{ResourceType, game};
 
 use crate::resources::chain_config::factory_chain_config;
+use crate::resources::minerals::compressed;
 use crate::resources::{Resources, RoomContext};
 use crate::rooms::RoomEvent;
 use crate::rooms::state::requests::assignment::Assignment;
-use crate::rooms::state::requests::{CarryData, FactoryData, LabData, Request, RequestKind};
+use crate::rooms::state::requests::{FactoryData, LabData, Request, RequestKind};
 use crate::utils::constants::LAB_PRODUCTION;
 
 pub type ResourceRoomHandlerFn =
@@ -118,8 +119,10 @@ fn factory_chain_handler(
     ctx: &RoomContext,
 ) -> Option<RoomEvent> {
     let cfg = factory_chain_config(res)?;
+    // steady deposit income refills the stock, so compress it earlier
+    let limit = cfg.limit.saturating_sub(ctx.income(res)).max(cfg.limit / 2);
 
-    (amount >= cfg.limit).then(|| {
+    (amount >= limit).then(|| {
         if let Some(chain) = cfg
             .opt2
             .as_ref()
@@ -229,15 +232,10 @@ fn energy_handler(
             RequestKind::Factory(FactoryData::new(ResourceType::Battery, 5000)),
             Assignment::None,
         ))),
-        8 if battery > 20_000 => Some(RoomEvent::Excess(ResourceType::Battery, battery - 20_000)),
         7 | 8 if energy < 50_000 && battery >= 50 => Some(RoomEvent::Request(Request::new(
             RequestKind::Factory(FactoryData::new(ResourceType::Energy, 50_000)),
             Assignment::None,
         ))),
-        7 | 8 if energy < 50_000 => Some(RoomEvent::Lack(ResourceType::Battery, 5000)),
-        6 if energy < 50_000 && ctx.terminal.is_some() => {
-            Some(RoomEvent::Lack(ResourceType::Energy, 50_000))
-        }
         _ => None,
     }
 }
@@ -351,46 +349,18 @@ fn mineral_handler(
     res: ResourceType,
     amount: u32,
     resources: &Resources,
-    ctx: &RoomContext,
+    _ctx: &RoomContext,
 ) -> Option<RoomEvent> {
-    if let Some(compressed_resource) = get_compressed_resource(res) {
-        let compressed_amount = resources.amount(compressed_resource);
-
-        if amount > 50_000 {
-            if compressed_amount > 100_000
-                && let (Some(t_id), Some(s_id)) = (ctx.terminal, ctx.storage)
-            {
-                Some(RoomEvent::Request(Request::new(
-                    RequestKind::Carry(CarryData::new(s_id, t_id, res, amount - 50_000)),
-                    Assignment::Single(None),
-                )))
-            } else {
-                Some(RoomEvent::Request(Request::new(
-                    RequestKind::Factory(FactoryData::new(compressed_resource, 5_000)),
-                    Assignment::None,
-                )))
-            }
-        } else if amount < 5_000 {
-            if compressed_amount > 10_000 {
-                Some(RoomEvent::Request(Request::new(
-                    RequestKind::Factory(FactoryData::new(res, 5_000)),
-                    Assignment::None,
-                )))
-            } else {
-                Some(RoomEvent::Lack(res, 5_000 - amount))
-            }
+    // the colony stock is kept by the mineral economy, the room only asks for its share
+    let compressed_resource = compressed(res)?;
+    (amount < 5_000).then(|| {
+        if resources.amount(compressed_resource) > 10_000 {
+            RoomEvent::Request(Request::new(
+                RequestKind::Factory(FactoryData::new(res, 5_000)),
+                Assignment::None,
+            ))
         } else {
-            None
+            RoomEvent::Lack(res, 5_000 - amount)
         }
-    } else {
-        None
-    }
-}
-
-fn get_compressed_resource(resource: ResourceType) -> Option<ResourceType> {
-    resource.commodity_recipe().and_then(|recipe| {
-        recipe.components.iter().find_map(|(component, _)| {
-            if *component == ResourceType::Energy { None } else { Some(*component) }
-        })
     })
 }
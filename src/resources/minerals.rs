@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+use ordered_float::OrderedFloat;
+use screeps::game::market::OrderHistoryRecord;
+use screeps::{MINERAL_REGEN_TIME, OrderType, ResourceType, RoomName, game};
+use serde::{Deserialize, Serialize};
+
+use crate::rooms::state::requests::{FactoryData, RequestKind};
+use crate::rooms::state::{RoomState, TradeData};
+use crate::rooms::wrappers::claimed::Claimed;
+use crate::utils::constants::{
+    MINERAL_BAR_LIMIT, MINERAL_BUY_MARKUP, MINERAL_HORIZON, MINERAL_SELL_MARKDOWN,
+    MINERAL_STOCK_TARGET, MINERAL_SURPLUS,
+};
+
+pub const MINERALS: [ResourceType; 7] = [
+    ResourceType::Hydrogen,
+    ResourceType::Oxygen,
+    ResourceType::Utrium,
+    ResourceType::Lemergium,
+    ResourceType::Keanium,
+    ResourceType::Zynthium,
+    ResourceType::Catalyst,
+];
+
+// the factory makes 100 bars of 500 minerals and gives them back the same way
+const BAR_MINERALS: u32 = 5;
+// bars per factory request, as the room handlers ask
+const FACTORY_BATCH: u32 = 5_000;
+
+/// Colony stock of one base mineral over the next `MINERAL_HORIZON` ticks
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MineralBalance {
+    pub stock: u32,
+    pub bars: u32,
+    /// mined by the extractors
+    pub output: u32,
+    /// planned by the labs and the factory
+    pub demand: u32,
+    pub target: u32,
+}
+
+impl MineralBalance {
+    /// Minerals left at the horizon, bars counted as decompressed
+    pub const fn projected(&self) -> u32 {
+        (self.stock + self.bars * BAR_MINERALS + self.output).saturating_sub(self.demand)
+    }
+}
+
+impl Display for MineralBalance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "stock: {}, bars: {}, output: {}, demand: {}, target: {}, projected: {}",
+            self.stock,
+            self.bars,
+            self.output,
+            self.demand,
+            self.target,
+            self.projected()
+        )
+    }
+}
+
+/// What the economy asks a room for
+#[derive(Debug, Clone)]
+pub enum MineralAction {
+    Request(RoomName, RequestKind),
+    Trade(RoomName, TradeData),
+}
+
+/// Minerals and bars one base holds, with what it can do with them
+#[derive(Debug, Clone)]
+pub(crate) struct Stock {
+    pub name: RoomName,
+    pub terminal: bool,
+    pub factory: bool,
+    pub mineral: ResourceType,
+    /// mined by the extractor over the horizon
+    pub output: u32,
+    pub amounts: HashMap<ResourceType, u32>,
+}
+
+impl Stock {
+    pub(crate) fn new(base: &Claimed) -> Self {
+        Self {
+            name: base.get_name(),
+            terminal: base.terminal().is_some(),
+            factory: base.factory().is_some(),
+            mineral: base.mineral.mineral_type(),
+            output: output(base),
+            amounts: base.resources.amounts().collect(),
+        }
+    }
+
+    fn amount(&self, resource: ResourceType) -> u32 {
+        self.amounts.get(&resource).copied().unwrap_or_default()
+    }
+}
+
+pub(crate) fn balances(
+    stocks: &[Stock],
+    states: &HashMap<RoomName, RoomState>,
+) -> HashMap<ResourceType, MineralBalance> {
+    let demand = demand(states);
+    let terminals =
+        u32::try_from(stocks.iter().filter(|stock| stock.terminal).count()).unwrap_or_default();
+
+    MINERALS
+        .into_iter()
+        .map(|mineral| {
+            let bar = compressed(mineral);
+            let demand = demand.get(&mineral).copied().unwrap_or_default();
+            let balance = MineralBalance {
+                stock: stocks.iter().map(|stock| stock.amount(mineral)).sum(),
+                bars: bar.map_or(0, |bar| stocks.iter().map(|stock| stock.amount(bar)).sum()),
+                output: stocks
+                    .iter()
+                    .filter(|stock| stock.mineral == mineral)
+                    .map(|stock| stock.output)
+                    .sum(),
+                demand,
+                target: MINERAL_STOCK_TARGET * terminals + demand,
+            };
+            (mineral, balance)
+        })
+        .collect()
+}
+
+/// Compresses and sells the surplus, decompresses or buys the predicted shortage. A room
+/// compresses and sells no more than it holds itself
+pub(crate) fn plan(
+    stocks: &[Stock],
+    states: &HashMap<RoomName, RoomState>,
+    balances: &HashMap<ResourceType, MineralBalance>,
+    price: impl Fn(ResourceType) -> Option<f64>,
+) -> Vec<MineralAction> {
+    let mut actions = Vec::new();
+    for (mineral, balance) in balances {
+        let Some(bar) = compressed(*mineral) else {
+            continue;
+        };
+        let holder = |resource: ResourceType, factory: bool| {
+            stocks
+                .iter()
+                .filter(|stock| stock.terminal && (!factory || stock.factory))
+                .max_by_key(|stock| stock.amount(resource))
+        };
+
+        if balance.projected() < balance.target {
+            let shortage = balance.target - balance.projected();
+            if balance.bars > 0
+                && let Some(stock) = holder(bar, true)
+            {
+                let amount = (balance.bars * BAR_MINERALS).min(shortage).min(FACTORY_BATCH);
+                actions.push(MineralAction::Request(
+                    stock.name,
+                    RequestKind::Factory(FactoryData::new(*mineral, amount)),
+                ));
+            } else if let Some(stock) = stocks
+                .iter()
+                .filter(|stock| stock.terminal)
+                .min_by_key(|stock| stock.amount(*mineral))
+                && let Some(price) = price(*mineral)
+            {
+                let trade = TradeData::with_price_and_amount(
+                    OrderType::Buy,
+                    *mineral,
+                    OrderedFloat(price * MINERAL_BUY_MARKUP),
+                    shortage,
+                );
+                actions.push(MineralAction::Trade(stock.name, trade));
+            }
+        } else if balance.stock > balance.target + MINERAL_SURPLUS {
+            let surplus = balance.stock - balance.target;
+            if balance.bars < MINERAL_BAR_LIMIT
+                && let Some(stock) = holder(*mineral, true)
+            {
+                let amount =
+                    (surplus.min(stock.amount(*mineral)) / BAR_MINERALS).min(FACTORY_BATCH);
+                actions.push(MineralAction::Request(
+                    stock.name,
+                    RequestKind::Factory(FactoryData::new(bar, amount)),
+                ));
+            } else if let Some(stock) = holder(bar, false)
+                && let Some(price) = price(bar)
+            {
+                let excess = balance.bars - MINERAL_BAR_LIMIT.min(balance.bars);
+                let trade = TradeData::with_price_and_amount(
+                    OrderType::Sell,
+                    bar,
+                    OrderedFloat(price * MINERAL_SELL_MARKDOWN),
+                    excess.min(stock.amount(bar)),
+                );
+                actions.push(MineralAction::Trade(stock.name, trade));
+            }
+        }
+    }
+
+    // a running trade is not replaced until it is done
+    actions.retain(|action| match action {
+        MineralAction::Trade(room, trade) => {
+            trade.amount > 0
+                && states.get(room).is_none_or(|state| {
+                    state.trades.get(trade).is_none_or(|current| current.amount == 0)
+                })
+        }
+        MineralAction::Request(..) => true,
+    });
+    actions
+}
+
+// extracted over the horizon, the extractor comes at RCL 6
+fn output(base: &Claimed) -> u32 {
+    if base.controller.level() < 6 {
+        return 0;
+    }
+    base.mineral.density().amount() * MINERAL_HORIZON / MINERAL_REGEN_TIME
+}
+
+// base minerals used by the pending lab and factory requests
+fn demand(states: &HashMap<RoomName, RoomState>) -> HashMap<ResourceType, u32> {
+    let mut demand = HashMap::new();
+    for request in states.values().flat_map(|state| state.requests.iter()) {
+        match &request.kind {
+            RequestKind::Lab(data) if !data.reverse => {
+                expand(data.resource, data.amount, &mut demand);
+            }
+            RequestKind::Factory(data) if !is_compression(data.resource) => {
+                let Some(recipe) = data.resource.commodity_recipe() else {
+                    continue;
+                };
+                let batches = data.amount.div_ceil(recipe.amount.max(1));
+                for (component, amount) in recipe.components {
+                    if let Some(mineral) =
+                        MINERALS.into_iter().find(|m| compressed(*m) == Some(component))
+                    {
+                        *demand.entry(mineral).or_default() += amount * batches * BAR_MINERALS;
+                    } else {
+                        expand(component, amount * batches, &mut demand);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    demand
+}
+
+// splits a compound down to the base minerals
+fn expand(resource: ResourceType, amount: u32, demand: &mut HashMap<ResourceType, u32>) {
+    if MINERALS.contains(&resource) {
+        *demand.entry(resource).or_default() += amount;
+    } else if let Some(components) = resource.reaction_components() {
+        for component in components {
+            expand(component, amount, demand);
+        }
+    }
+}
+
+// bars and minerals made by the factory only move the stock between its forms
+fn is_compression(resource: ResourceType) -> bool {
+    MINERALS.contains(&resource) || MINERALS.into_iter().any(|m| compressed(m) == Some(resource))
+}
+
+pub(crate) fn compressed(mineral: ResourceType) -> Option<ResourceType> {
+    mineral.commodity_recipe().and_then(|recipe| {
+        recipe.components.iter().find_map(|(component, _)| {
+            if *component == ResourceType::Energy { None } else { Some(*component) }
+        })
+    })
+}
+
+/// Average deal price of the last day in the history
+pub(crate) fn price(resource: ResourceType) -> Option<f64> {
+    game::market::get_history(Some(resource)).last().map(OrderHistoryRecord::avg_price)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use screeps::{OrderType, ResourceType, RoomName};
+
+    use crate::resources::minerals::{MineralAction, MineralBalance, Stock, balances, plan};
+    use crate::rooms::state::requests::RequestKind;
+
+    fn stock(name: &str, factory: bool, amounts: &[(ResourceType, u32)]) -> Stock {
+        Stock {
+            name: RoomName::new(name).unwrap(),
+            terminal: true,
+            factory,
+            mineral: ResourceType::Hydrogen,
+            output: 0,
+            amounts: amounts.iter().copied().collect(),
+        }
+    }
+
+    #[test]
+    fn plan_test() {
+        let stocks = [
+            stock(
+                "W1N1",
+                true,
+                &[(ResourceType::Utrium, 20_000), (ResourceType::KeaniumBar, 22_000)],
+            ),
+            stock(
+                "W2N1",
+                false,
+                &[(ResourceType::Utrium, 60_000), (ResourceType::KeaniumBar, 20_000)],
+            ),
+            stock(
+                "W3N1",
+                false,
+                &[(ResourceType::Keanium, 70_000), (ResourceType::KeaniumBar, 20_000)],
+            ),
+        ];
+        let states = HashMap::new();
+        let balances = balances(&stocks, &states);
+        assert_eq!(
+            balances[&ResourceType::Keanium],
+            MineralBalance { stock: 70_000, bars: 62_000, output: 0, demand: 0, target: 30_000 }
+        );
+
+        let price = |resource| (resource == ResourceType::KeaniumBar).then_some(1.);
+        let actions = plan(&stocks, &states, &balances, price);
+        assert_eq!(actions.len(), 2);
+        let home = RoomName::new("W1N1").unwrap();
+        // the only factory compresses its own utrium, not the surplus of the colony
+        let request = actions.iter().find_map(|action| match action {
+            MineralAction::Request(room, RequestKind::Factory(data)) => {
+                Some((*room, data.resource, data.amount))
+            }
+            _ => None,
+        });
+        assert_eq!(request, Some((home, ResourceType::UtriumBar, 4_000)));
+        // the biggest holder sells all of its bars, the rest are in the other terminals
+        let trade = actions.iter().find_map(|action| match action {
+            MineralAction::Trade(room, trade) => {
+                Some((*room, trade.order_type, trade.resource, trade.amount))
+            }
+            MineralAction::Request(..) => None,
+        });
+        assert_eq!(trade, Some((home, OrderType::Sell, ResourceType::KeaniumBar, 22_000)));
+    }
+}
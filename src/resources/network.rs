@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use js_sys::JsString;
+use screeps::{ResourceType, RoomName, game};
+
+use crate::rooms::state::RoomState;
+use crate::rooms::state::requests::RequestKind;
+use crate::rooms::wrappers::claimed::Claimed;
+use crate::utils::constants::{NETWORK_CHUNK, NETWORK_DEADBAND, NETWORK_UPGRADE_BONUS};
+
+// energy per battery when the factory unpacks them, and the energy spent to pack one
+const BATTERY_ENERGY: u32 = 10;
+const BATTERY_PACK_COST: u32 = 12;
+
+/// One terminal send planned by the energy network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shipment {
+    pub from: RoomName,
+    pub to: RoomName,
+    pub resource: ResourceType,
+    pub amount: u32,
+    /// the sender packs energy into batteries first
+    pub pack: bool,
+}
+
+// how energy leaves a room: terminal energy, stocked batteries or energy packed on demand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Supply {
+    Energy,
+    Battery,
+    Packed,
+}
+
+/// Energy a room keeps by its level and role, batteries counted as unpacked
+fn target(base: &Claimed, state: Option<&RoomState>) -> u32 {
+    let by_level = match base.controller.level() {
+        ..=5 => 0,
+        6 => 100_000,
+        7 => 150_000,
+        _ => 200_000,
+    };
+    let upgrading =
+        state.and_then(|state| state.upgrade).is_some_and(|plan| plan.goal.pushes_level());
+    if upgrading { by_level + NETWORK_UPGRADE_BONUS } else { by_level }
+}
+
+/// Controller progress the room misses to its next level, none when it doesn't push the level
+fn to_next_level(base: &Claimed, state: Option<&RoomState>) -> Option<u32> {
+    state.and_then(|state| state.upgrade).filter(|plan| plan.goal.pushes_level())?;
+    let controller = &base.controller;
+    Some(controller.progress_total()?.saturating_sub(controller.progress()?))
+}
+
+/// Cost of a chunk delivered to every room in need, by the progress it misses to the next level.
+/// The rooms closest to their level come first and the other ones last, a step outweighs any
+/// transaction cost
+fn demand_costs(to_next_level: &[Option<u32>]) -> Vec<u32> {
+    let upgrading: Vec<u32> = to_next_level.iter().flatten().copied().sorted().collect();
+    let last = u32::try_from(upgrading.len()).unwrap_or_default();
+    to_next_level
+        .iter()
+        .map(|left| {
+            let rank = left.map_or(last, |left| {
+                u32::try_from(upgrading.partition_point(|other| *other < left)).unwrap_or_default()
+            });
+            rank * NETWORK_CHUNK
+        })
+        .collect()
+}
+
+/// Energy still on the way by the pending terminal sends, as sent and received by every room.
+/// Batteries count as unpacked, so the pending pack and unpack requests don't move the stock
+fn in_flight(states: &HashMap<RoomName, RoomState>) -> HashMap<RoomName, (u32, u32)> {
+    let mut result: HashMap<RoomName, (u32, u32)> = HashMap::new();
+    for (name, state) in states {
+        for request in &state.requests {
+            let RequestKind::Transfer(data) = &request.kind else {
+                continue;
+            };
+            let energy = match data.resource {
+                ResourceType::Energy => data.amount,
+                ResourceType::Battery => data.amount * BATTERY_ENERGY,
+                _ => continue,
+            };
+            result.entry(*name).or_default().0 += energy;
+            result.entry(data.destination).or_default().1 += energy;
+        }
+    }
+    result
+}
+
+/// Solves the min-cost flow from the rooms above their energy target to the rooms below it,
+/// in `NETWORK_CHUNK` units of delivered energy. The surplus goes to the room closest to its
+/// next level first
+pub(crate) fn plan(
+    bases: &HashMap<RoomName, Claimed>,
+    states: &HashMap<RoomName, RoomState>,
+) -> Vec<Shipment> {
+    let mut network = Network::default();
+    let source = network.node();
+    let sink = network.node();
+    // stock node, room, batteries in stock and whether the room has a factory
+    let mut supplies = Vec::new();
+    let mut demands = Vec::new();
+    // room, missing chunks, whether it has a factory and the progress to its next level
+    let mut wants = Vec::new();
+    let in_flight = in_flight(states);
+
+    for base in bases.values().filter(|base| base.terminal().is_some()) {
+        let energy = base.resources.amount(ResourceType::Energy);
+        let battery = base.resources.amount(ResourceType::Battery);
+        let (sent, received) = in_flight.get(&base.get_name()).copied().unwrap_or_default();
+        let stock = (energy + battery * BATTERY_ENERGY + received).saturating_sub(sent);
+        let target = target(base, states.get(&base.get_name()));
+        let factory = base.factory().is_some();
+
+        if stock > target + NETWORK_DEADBAND {
+            let surplus = (stock - target) / NETWORK_CHUNK;
+            let batteries = (battery * BATTERY_ENERGY / NETWORK_CHUNK).min(surplus);
+            for (kind, chunks) in
+                [(Supply::Battery, batteries), (Supply::Energy, surplus - batteries)]
+            {
+                if chunks > 0 {
+                    let node = network.node();
+                    network.edge(source, node, chunks, 0);
+                    supplies.push((node, base.get_name(), kind, factory));
+                }
+            }
+        } else if stock + NETWORK_DEADBAND < target {
+            let left = to_next_level(base, states.get(&base.get_name()));
+            wants.push((base.get_name(), (target - stock) / NETWORK_CHUNK, factory, left));
+        }
+    }
+    let costs = demand_costs(&wants.iter().map(|(_, _, _, left)| *left).collect::<Vec<_>>());
+    for ((name, chunks, factory, _), cost) in wants.into_iter().zip(costs) {
+        let node = network.node();
+        network.edge(node, sink, chunks, cost);
+        demands.push((node, name, factory));
+    }
+
+    let mut routes = Vec::new();
+    for (from_node, from, kind, packs) in &supplies {
+        for (to_node, to, unpacks) in &demands {
+            let mut options = vec![*kind];
+            if *kind == Supply::Energy && *packs {
+                options.push(Supply::Packed);
+            }
+            for kind in options.into_iter().filter(|kind| *kind == Supply::Energy || *unpacks) {
+                let edge =
+                    network.edge(*from_node, *to_node, u32::MAX, chunk_cost(*from, *to, kind));
+                routes.push((edge, *from, *to, kind));
+            }
+        }
+    }
+
+    network.solve(source, sink);
+
+    let mut shipments: Vec<Shipment> = Vec::new();
+    for (edge, from, to, kind) in routes {
+        let chunks = network.flow(edge);
+        if chunks == 0 {
+            continue;
+        }
+        let energy = chunks * NETWORK_CHUNK;
+        let (resource, amount) = match kind {
+            Supply::Energy => (ResourceType::Energy, energy),
+            Supply::Battery | Supply::Packed => (ResourceType::Battery, energy / BATTERY_ENERGY),
+        };
+        let pack = kind == Supply::Packed;
+        if let Some(shipment) = shipments.iter_mut().find(|shipment| {
+            shipment.from == from && shipment.to == to && shipment.resource == resource
+        }) {
+            shipment.amount += amount;
+            shipment.pack |= pack;
+        } else {
+            shipments.push(Shipment { from, to, resource, amount, pack });
+        }
+    }
+    shipments
+}
+
+// energy lost to deliver one chunk: the terminal fee and the battery packing
+fn chunk_cost(from: RoomName, to: RoomName, kind: Supply) -> u32 {
+    let (from, to) = (JsString::from(from.to_string()), JsString::from(to.to_string()));
+    match kind {
+        Supply::Energy => game::market::calc_transaction_cost(NETWORK_CHUNK, &from, &to),
+        Supply::Battery => {
+            game::market::calc_transaction_cost(NETWORK_CHUNK / BATTERY_ENERGY, &from, &to)
+        }
+        Supply::Packed => {
+            let batteries = NETWORK_CHUNK / BATTERY_ENERGY;
+            game::market::calc_transaction_cost(batteries, &from, &to)
+                + batteries * BATTERY_PACK_COST
+                - NETWORK_CHUNK
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    to: usize,
+    capacity: i64,
+    cost: i64,
+    flow: i64,
+}
+
+// successive shortest paths over the residual graph, the colony is small enough for
+// Bellman-Ford, edges go in pairs with the reverse one right after
+#[derive(Debug, Default)]
+struct Network {
+    edges: Vec<Edge>,
+    adjacent: Vec<Vec<usize>>,
+}
+
+impl Network {
+    fn node(&mut self) -> usize {
+        self.adjacent.push(Vec::new());
+        self.adjacent.len() - 1
+    }
+
+    fn edge(&mut self, from: usize, to: usize, capacity: u32, cost: u32) -> usize {
+        let index = self.edges.len();
+        let cost = i64::from(cost);
+        self.adjacent[from].push(index);
+        self.edges.push(Edge { to, capacity: i64::from(capacity), cost, flow: 0 });
+        self.adjacent[to].push(index + 1);
+        self.edges.push(Edge { to: from, capacity: 0, cost: -cost, flow: 0 });
+        index
+    }
+
+    fn residual(&self, edge: usize) -> i64 {
+        self.edges[edge].capacity - self.edges[edge].flow
+    }
+
+    fn solve(&mut self, source: usize, sink: usize) {
+        let nodes = self.adjacent.len();
+        loop {
+            let mut distance = vec![i64::MAX; nodes];
+            let mut previous: Vec<Option<usize>> = vec![None; nodes];
+            distance[source] = 0;
+            for _ in 0..nodes {
+                let mut changed = false;
+                for node in 0..nodes {
+                    if distance[node] == i64::MAX {
+                        continue;
+                    }
+                    for &edge in &self.adjacent[node] {
+                        let Edge { to, cost, .. } = self.edges[edge];
+                        if self.residual(edge) > 0 && distance[node] + cost < distance[to] {
+                            distance[to] = distance[node] + cost;
+                            previous[to] = Some(edge);
+                            changed = true;
+                        }
+                    }
+                }
+                if !changed {
+                    break;
+                }
+            }
+
+            let mut path = Vec::new();
+            let mut node = sink;
+            while let Some(edge) = previous[node] {
+                path.push(edge);
+                node = self.edges[edge ^ 1].to;
+            }
+            let Some(amount) = path.iter().map(|edge| self.residual(*edge)).min() else {
+                // the sink is unreachable
+                return;
+            };
+            for edge in path {
+                self.edges[edge].flow += amount;
+                self.edges[edge ^ 1].flow -= amount;
+            }
+        }
+    }
+
+    fn flow(&self, edge: usize) -> u32 {
+        u32::try_from(self.edges[edge].flow).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resources::network::{Network, demand_costs};
+
+    #[test]
+    fn min_cost_flow_test() {
+        let mut network = Network::default();
+        let source = network.node();
+        let sink = network.node();
+        let supplies = [network.node(), network.node()];
+        let demands = [network.node(), network.node()];
+        for node in supplies {
+            network.edge(source, node, 1, 0);
+        }
+        for node in demands {
+            network.edge(node, sink, 1, 0);
+        }
+        let costs = [[1, 2], [2, 100]];
+        let mut edges = Vec::new();
+        for (from, row) in supplies.into_iter().zip(costs) {
+            for (to, cost) in demands.into_iter().zip(row) {
+                edges.push((network.edge(from, to, u32::MAX, cost), cost));
+            }
+        }
+
+        network.solve(source, sink);
+
+        // the cheapest first pick is undone: both rooms ship crosswise for 4, not 1 + 100
+        let flows: Vec<u32> = edges.iter().map(|(edge, _)| network.flow(*edge)).collect();
+        assert_eq!(flows, vec![0, 1, 1, 0]);
+        let cost: u32 = edges.iter().map(|(edge, cost)| network.flow(*edge) * cost).sum();
+        assert_eq!(cost, 4);
+    }
+
+    #[test]
+    fn demand_costs_test() {
+        // the room closest to its next level first, the rooms holding their level last
+        assert_eq!(
+            demand_costs(&[Some(500_000), None, Some(20_000), Some(500_000)]),
+            vec![1_000, 3_000, 0, 1_000]
+        );
+    }
+}
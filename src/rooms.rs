@@ -5,12 +5,14 @@ use itertools::Itertools;
 use js_sys::JsString;
 use log::debug;
 use screeps::{
-    HasPosition, Mineral, PowerType, ResourceType, Room, RoomName, RoomPosition, RoomXY,
-    StructureType, game,
+    HasPosition, Mineral, ObjectId, PowerType, ResourceType, Room, RoomName, RoomPosition,
+    RoomXY, Structure, StructureType, game,
 };
 
+use crate::colony::journal::JournalEvent;
 use crate::commons::look_for;
 use crate::rooms::shelter::Shelter;
+use crate::rooms::state::boosts::BoostOrder;
 use crate::rooms::state::constructions::{PlannedCell, RoomPlan};
 use crate::rooms::state::requests::Request;
 use crate::rooms::state::{BoostReason, RoomState};
@@ -18,6 +20,7 @@ use crate::rooms::wrappers::farm::Farm;
 use crate::rooms::wrappers::neutral::Neutral;
 use crate::units::roles::Role;
 
+pub mod logistics;
 pub mod shelter;
 pub mod state;
 pub mod wrappers;
@@ -27,21 +30,25 @@ pub enum RoomEvent {
     Spawned(String, Role, usize),
     Spawn(Role, usize),
     MayBeSpawn(Role),
-    CancelRespawn(Role),
+    /// the creeps of the role over the number are not respawned, the queued ones are dropped
+    CancelRespawn(Role, usize),
     AddPower(PowerType),
     DeletePower(PowerType),
     AddBoost(BoostReason, u32),
     RetainBoosts,
+    ReserveBoosts(BoostOrder),
     UpdateFarmStatus(RoomName, bool),
     // StopFarm(RoomName),
     // StartFarm(RoomName),
-    EditPlans(HashMap<RoomName, RoomPlan>),
+    EditPlans(HashMap<RoomName, RoomPlan>, HashMap<RoomName, f64>),
     Plan(RoomPlan),
     ReplaceCell(PlannedCell),
     Construct(HashMap<RoomXY, StructureType>),
     IncrementPlanLvl,
+    Migrate(Vec<ObjectId<Structure>>, usize),
     Lack(ResourceType, u32),
     Excess(ResourceType, u32),
+    DepositYield(ResourceType, u32),
     Avoid(RoomName, u32),
     // Sos, //if claimed room is attacked and has no power to defends by itself
     Request(Request),
@@ -53,6 +60,7 @@ pub enum RoomEvent {
     Defend(RoomName),
     ActivateSafeMode(String),
     BlackList(String),
+    Journal(JournalEvent),
     UpdateStatistic,
     Origin(bool),
 }
@@ -0,0 +1,501 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use screeps::{
+    Creep, HasId, HasPosition, HasStore, Position, RawObjectId, ResourceType, Ruin,
+    SharedCreepProperties, Store, Structure, StructureObject, Tombstone, game,
+};
+use wasm_bindgen::JsCast;
+
+use crate::rooms::shelter::Shelter;
+use crate::rooms::state::requests::meta::Status;
+use crate::rooms::state::requests::{Request, RequestKind};
+use crate::utils::constants::URGENT_REQUEST_PRIORITY;
+
+const TOWER_INVASION_PRIORITY: u8 = 4;
+const SPAWN_PRIORITY: u8 = 3;
+const REQUEST_PRIORITY: u8 = 2;
+const TOWER_PRIORITY: u8 = 1;
+const CORE_PRIORITY: u8 = 1;
+const STORAGE_PRIORITY: u8 = 0;
+
+// energy the traders keep in the core structures
+const LAB_ENERGY: u32 = 1_000;
+const TERMINAL_ENERGY: u32 = 10_000;
+// storage energy left before the nuker and the power spawn are filled
+const NUKER_RESERVE: u32 = 150_000;
+const POWER_RESERVE: u32 = 250_000;
+
+/// Who takes the route: haulers serve the spawn block and clean up the room, traders move
+/// resources between the core structures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Courier {
+    Hauler,
+    Trader,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopAction {
+    Pickup,
+    Withdraw,
+    Deliver,
+}
+
+/// One pickup or drop off of a multi-stop route
+#[derive(Debug, Clone)]
+pub struct Stop {
+    pub action: StopAction,
+    pub pos: Position,
+    pub id: RawObjectId,
+    pub resource: ResourceType,
+    pub amount: Option<u32>,
+    /// resolved once the last stop of the request is done
+    pub request: Option<Request>,
+    /// the job importance, a route of unimportant stops can be preempted
+    pub priority: u8,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Place {
+    pos: Position,
+    id: RawObjectId,
+    action: StopAction,
+}
+
+// a supply matched to its demand, no source means the creep already carries the cargo
+#[derive(Debug, Clone)]
+struct Job {
+    priority: u8,
+    from: Option<Place>,
+    to: Option<Place>,
+    resource: ResourceType,
+    amount: u32,
+    // withdraw and pickup requests are taken as a whole
+    splittable: bool,
+    request: Option<Request>,
+}
+
+/// The planned route and the room bookkeeping for it
+#[derive(Debug, Default)]
+pub struct Route {
+    pub stops: Vec<Stop>,
+    /// requests served completely by the route
+    pub claimed: Vec<Request>,
+    /// carry requests served partially, with the amount left for others
+    pub split: Vec<(Request, u32)>,
+    /// energy promised to the spawn block and towers
+    pub reserved: Vec<(RawObjectId, u32)>,
+}
+
+/// Gathers every supply and demand the courier serves and plans a route filling its capacity
+pub fn plan_route(home: &Shelter, creep: &Creep, courier: Courier) -> Route {
+    let carried: HashMap<ResourceType, u32> = creep
+        .store()
+        .store_types()
+        .into_iter()
+        .map(|resource| (resource, creep.store().get_used_capacity(Some(resource))))
+        .collect();
+
+    if carried.is_empty() {
+        let capacity = u32::try_from(creep.store().get_free_capacity(None)).unwrap_or_default();
+        let mut jobs = request_jobs(home, Some(&creep.name()), courier);
+        jobs.extend(structure_jobs(home, courier, energy_source(home)));
+        collect(creep.pos(), jobs, capacity)
+    } else {
+        deliver_carried(home, creep, courier, &carried)
+    }
+}
+
+/// The urgent jobs of the room by courier, gathered once a tick for the preemption checks
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Urgency {
+    /// a new request
+    request: bool,
+    /// a structure the courier can bring the energy to from the room supply
+    supplied: bool,
+    /// a structure the courier can bring the energy it carries to
+    structure: bool,
+}
+
+pub fn urgency(home: &Shelter) -> HashMap<Courier, Urgency> {
+    [Courier::Hauler, Courier::Trader]
+        .into_iter()
+        .map(|courier| {
+            let urgent = |job: &Job| job.priority >= TOWER_INVASION_PRIORITY;
+            let structures = structure_jobs(home, courier, energy_source(home));
+            let urgency = Urgency {
+                request: request_jobs(home, None, courier).iter().any(urgent),
+                supplied: structures.iter().any(|job| urgent(job) && job.from.is_some()),
+                structure: structures.iter().any(urgent),
+            };
+            (courier, urgency)
+        })
+        .collect()
+}
+
+/// Whether an urgent job appeared the route doesn't serve and the courier can take right now
+pub fn should_preempt(home: &Shelter, creep: &Creep, courier: Courier, stops: &[Stop]) -> bool {
+    if stops.iter().any(|stop| stop.priority >= TOWER_INVASION_PRIORITY) {
+        return false;
+    }
+
+    let carries_energy = creep.store().get_used_capacity(Some(ResourceType::Energy)) > 0;
+    home.urgency(courier).is_some_and(|urgency| {
+        urgency.request || urgency.supplied || (carries_energy && urgency.structure)
+    })
+}
+
+// drop off the cargo left after a previous route, the spawn block first
+fn deliver_carried(
+    home: &Shelter,
+    creep: &Creep,
+    courier: Courier,
+    carried: &HashMap<ResourceType, u32>,
+) -> Route {
+    let mut route = Route::default();
+    let mut deliveries = Vec::new();
+    let mut left = carried.clone();
+
+    if let Some(energy) = left.get_mut(&ResourceType::Energy) {
+        for job in sorted(creep.pos(), structure_jobs(home, courier, None)) {
+            if *energy == 0 {
+                break;
+            }
+            let amount = job.amount.min(*energy);
+            *energy -= amount;
+            if let Some(to) = job.to {
+                route.reserved.push((to.id, amount));
+                deliveries.push(stop(to, job.priority, job.resource, Some(amount), None));
+            }
+        }
+    }
+
+    if courier == Courier::Hauler
+        && let Some(storage) = storage_sink(home)
+    {
+        for (resource, _) in left.into_iter().filter(|(_, amount)| *amount > 0) {
+            deliveries.push(stop(storage, STORAGE_PRIORITY, resource, None, None));
+        }
+    }
+
+    route.stops = order(creep.pos(), deliveries, true);
+    route
+}
+
+// the new requests and the ones the creep already serves
+fn request_jobs(home: &Shelter, creep: Option<&str>, courier: Courier) -> Vec<Job> {
+    let storage = storage_sink(home);
+    let mut jobs = Vec::new();
+
+    for request in home.requests().filter(|request| match request.status() {
+        Status::Created => true,
+        Status::InProgress => creep.is_some_and(|name| request.assigned_to(name)),
+        _ => false,
+    }) {
+        match (&request.kind, courier) {
+            (RequestKind::Carry(d), Courier::Trader) => {
+                let (Some(from), Some(to)) = (place(d.from), place(d.to)) else {
+                    continue;
+                };
+                jobs.push(Job {
+                    priority: request_priority(request),
+                    from: Some(Place { action: StopAction::Withdraw, ..from }),
+                    to: Some(Place { action: StopAction::Deliver, ..to }),
+                    resource: d.resource,
+                    amount: d.amount,
+                    splittable: true,
+                    request: Some(request.clone()),
+                });
+            }
+            (RequestKind::Withdraw(d), Courier::Hauler) => {
+                for (resource, amount) in &d.resources {
+                    let amount = amount.unwrap_or_else(|| stored(d.id, *resource));
+                    if amount == 0 {
+                        continue;
+                    }
+                    jobs.push(Job {
+                        priority: request_priority(request),
+                        from: Some(Place { pos: d.pos, id: d.id, action: StopAction::Withdraw }),
+                        to: storage,
+                        resource: *resource,
+                        amount,
+                        splittable: false,
+                        request: Some(request.clone()),
+                    });
+                }
+            }
+            (RequestKind::Pickup(d), Courier::Hauler) => {
+                if let Some(resource) = d.id.resolve() {
+                    jobs.push(Job {
+                        priority: request_priority(request),
+                        from: Some(Place {
+                            pos: resource.pos(),
+                            id: d.id.into(),
+                            action: StopAction::Pickup,
+                        }),
+                        to: storage,
+                        resource: resource.resource_type(),
+                        amount: resource.amount(),
+                        splittable: false,
+                        request: Some(request.clone()),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    jobs
+}
+
+fn structure_jobs(home: &Shelter, courier: Courier, source: Option<Place>) -> Vec<Job> {
+    match courier {
+        Courier::Hauler => energy_jobs(home, source),
+        Courier::Trader => core_jobs(home, source),
+    }
+}
+
+// spawn block and towers waiting for energy
+fn energy_jobs(home: &Shelter, source: Option<Place>) -> Vec<Job> {
+    let tower_priority = if home.invasion() { TOWER_INVASION_PRIORITY } else { TOWER_PRIORITY };
+    let base = &home.base;
+    let free = |store: Store| {
+        u32::try_from(store.get_free_capacity(Some(ResourceType::Energy))).unwrap_or_default()
+    };
+
+    let demands = base
+        .extensions
+        .iter()
+        .map(|e| (e.pos(), e.raw_id(), free(e.store()), SPAWN_PRIORITY))
+        .chain(base.spawns.iter().map(|s| (s.pos(), s.raw_id(), free(s.store()), SPAWN_PRIORITY)))
+        .chain(base.towers.iter().map(|t| (t.pos(), t.raw_id(), free(t.store()), tower_priority)));
+    deliveries(home, source, demands)
+}
+
+// boost labs, terminal, nuker and power spawn waiting for energy
+fn core_jobs(home: &Shelter, source: Option<Place>) -> Vec<Job> {
+    let base = &home.base;
+    let used = |store: Store| store.get_used_capacity(Some(ResourceType::Energy));
+    let free = |store: Store| {
+        u32::try_from(store.get_free_capacity(Some(ResourceType::Energy))).unwrap_or_default()
+    };
+    let stored = home.storage().map_or(0, |storage| used(storage.store()));
+
+    let demands = base
+        .labs
+        .boosts()
+        .values()
+        .map(|lab| (lab.pos(), lab.raw_id(), LAB_ENERGY.saturating_sub(used(lab.store()))))
+        .chain(base.terminal().map(|terminal| {
+            let amount = TERMINAL_ENERGY.saturating_sub(used(terminal.store()));
+            (terminal.pos(), terminal.raw_id(), amount)
+        }))
+        .chain(
+            base.nuker
+                .iter()
+                .filter(|_| stored > NUKER_RESERVE)
+                .map(|nuker| (nuker.pos(), nuker.raw_id(), free(nuker.store()))),
+        )
+        .chain(
+            base.power_spawn
+                .iter()
+                .filter(|power_spawn| {
+                    stored > POWER_RESERVE
+                        && power_spawn.store().get_used_capacity(Some(ResourceType::Power)) > 0
+                })
+                .map(|power_spawn| {
+                    (power_spawn.pos(), power_spawn.raw_id(), free(power_spawn.store()))
+                }),
+        )
+        .map(|(pos, id, amount)| (pos, id, amount, CORE_PRIORITY));
+    deliveries(home, source, demands)
+}
+
+// the energy demands minus what the other couriers already bring
+fn deliveries(
+    home: &Shelter,
+    source: Option<Place>,
+    demands: impl Iterator<Item = (Position, RawObjectId, u32, u8)>,
+) -> Vec<Job> {
+    demands
+        .filter_map(|(pos, id, free, priority)| {
+            let amount = free.saturating_sub(home.reserved(id));
+            (amount > 0).then_some(Job {
+                priority,
+                from: source,
+                to: Some(Place { pos, id, action: StopAction::Deliver }),
+                resource: ResourceType::Energy,
+                amount,
+                splittable: true,
+                request: None,
+            })
+        })
+        .collect()
+}
+
+fn energy_source(home: &Shelter) -> Option<Place> {
+    home.storage()
+        .filter(|storage| storage.store().get_used_capacity(Some(ResourceType::Energy)) > 2000)
+        .map(|storage| Place {
+            pos: storage.pos(),
+            id: storage.raw_id(),
+            action: StopAction::Withdraw,
+        })
+        .or_else(|| {
+            home.factory()
+                .filter(|factory| {
+                    factory.store().get_used_capacity(Some(ResourceType::Energy)) > 1000
+                })
+                .map(|factory| Place {
+                    pos: factory.pos(),
+                    id: factory.raw_id(),
+                    action: StopAction::Withdraw,
+                })
+        })
+}
+
+fn storage_sink(home: &Shelter) -> Option<Place> {
+    home.storage().filter(|storage| storage.store().get_free_capacity(None) > 5000).map(|storage| {
+        Place { pos: storage.pos(), id: storage.raw_id(), action: StopAction::Deliver }
+    })
+}
+
+// the most important and the closest jobs first, until the creep is full
+fn collect(start: Position, jobs: Vec<Job>, mut capacity: u32) -> Route {
+    let mut route = Route::default();
+    let mut takes: Vec<Stop> = Vec::new();
+    let mut deliveries = Vec::new();
+
+    for job in sorted(start, jobs) {
+        if capacity == 0 {
+            break;
+        }
+        let Some(from) = job.from else {
+            continue;
+        };
+        let fits = job.amount <= capacity;
+        if !fits && !job.splittable && !takes.is_empty() {
+            continue;
+        }
+
+        let amount = job.amount.min(capacity);
+        capacity -= amount;
+
+        let structure = job.request.is_none();
+        let request = match job.request {
+            Some(request) if !fits && job.splittable => {
+                route.split.push((request, job.amount - amount));
+                None
+            }
+            Some(request) => {
+                if !route.claimed.contains(&request) {
+                    route.claimed.push(request.clone());
+                }
+                Some(request)
+            }
+            None => None,
+        };
+
+        if let Some(to) = job.to {
+            if structure {
+                route.reserved.push((to.id, amount));
+            }
+            let delivery_request = request.clone().filter(|_| job.splittable);
+            deliveries.push(stop(to, job.priority, job.resource, Some(amount), delivery_request));
+        }
+
+        // take from the same place in one go
+        let take_request = request.filter(|_| !job.splittable);
+        if let Some(take) = takes.iter_mut().find(|take| {
+            take.id == from.id && take.resource == job.resource && take.request == take_request
+        }) {
+            take.amount = take.amount.map(|taken| taken + amount);
+            take.priority = take.priority.max(job.priority);
+        } else {
+            takes.push(stop(from, job.priority, job.resource, Some(amount), take_request));
+        }
+    }
+
+    // withdraw requests don't know the amount in advance, take everything
+    for take in &mut takes {
+        if take.request.is_some() && take.action == StopAction::Withdraw {
+            take.amount = None;
+        }
+    }
+
+    route.stops = order(start, takes, false);
+    let last = route.stops.last().map_or(start, |stop| stop.pos);
+    route.stops.extend(order(last, deliveries, true));
+    route
+}
+
+fn sorted(start: Position, jobs: Vec<Job>) -> impl Iterator<Item = Job> {
+    jobs.into_iter().sorted_by_key(|job| {
+        let pos = job.from.or(job.to).map_or(start, |place| place.pos);
+        let weight = job.request.as_ref().map(Request::priority);
+        (Reverse(job.priority), Reverse(weight), start.get_range_to(pos))
+    })
+}
+
+// nearest neighbour through the stops, drop offs to the storage go last and urgent drop offs
+// go first
+fn order(start: Position, mut stops: Vec<Stop>, deliveries: bool) -> Vec<Stop> {
+    let mut ordered = Vec::with_capacity(stops.len());
+    let mut current = start;
+    while let Some((index, _)) = stops.iter().enumerate().min_by_key(|(_, stop)| {
+        (
+            deliveries && stop.priority == STORAGE_PRIORITY,
+            deliveries && stop.priority < TOWER_INVASION_PRIORITY,
+            current.get_range_to(stop.pos),
+        )
+    }) {
+        let stop = stops.remove(index);
+        current = stop.pos;
+        ordered.push(stop);
+    }
+    ordered
+}
+
+fn stop(
+    place: Place,
+    priority: u8,
+    resource: ResourceType,
+    amount: Option<u32>,
+    request: Option<Request>,
+) -> Stop {
+    Stop { action: place.action, pos: place.pos, id: place.id, resource, amount, request, priority }
+}
+
+// urgent requests are served along with the towers under attack
+fn request_priority(request: &Request) -> u8 {
+    if request.priority() >= URGENT_REQUEST_PRIORITY {
+        TOWER_INVASION_PRIORITY
+    } else {
+        REQUEST_PRIORITY
+    }
+}
+
+fn place(id: RawObjectId) -> Option<Place> {
+    game::get_object_by_id_erased(&id).map(|object| Place {
+        pos: object.pos(),
+        id,
+        action: StopAction::Deliver,
+    })
+}
+
+// the stock of a structure, a tombstone or a ruin, read by the real type of the object
+fn stored(id: RawObjectId, resource: ResourceType) -> u32 {
+    let Some(object) = game::get_object_by_id_erased(&id) else {
+        return 0;
+    };
+    let store = if let Some(tombstone) = object.dyn_ref::<Tombstone>() {
+        Some(tombstone.store())
+    } else if let Some(ruin) = object.dyn_ref::<Ruin>() {
+        Some(ruin.store())
+    } else {
+        object.dyn_ref::<Structure>().and_then(|structure| {
+            StructureObject::from(structure.clone()).as_has_store().map(HasStore::store)
+        })
+    };
+    store.map_or(0, |store| store.get_used_capacity(Some(resource)))
+}
@@ -1,4 +1,5 @@
 use std::{
+    cmp::{Ordering, Reverse},
     collections::{HashMap, HashSet},
     iter::once,
 };
@@ -6,25 +7,39 @@ use std::{
 use itertools::Itertools;
 use log::{debug, error, info, warn};
 use screeps::{
-    Creep, HasId, HasPosition, HasStore, Mineral, ObjectId, OrderType, Part,
-    Position, PowerType, RawObjectId, ResourceType, Room, RoomName,
-    RoomPosition, RoomXY, Source, StructureController, StructureFactory, StructureLab,
-    StructureLink, StructurePowerSpawn, StructureRampart, StructureSpawn, StructureStorage,
-    StructureTerminal, StructureTower, StructureType,
+    Creep, ENERGY_REGEN_TIME, HasHits, HasId, HasPosition, HasStore, Mineral, ObjectId, OrderType,
+    OwnedStructureProperties, Position, PowerType, RawObjectId, ResourceType, Room, RoomName,
+    RoomPosition, RoomXY, SharedCreepProperties, Source, StructureController, StructureFactory,
+    StructureLab, StructureLink, StructureObject, StructurePowerSpawn, StructureRampart,
+    StructureSpawn, StructureStorage, StructureTerminal, StructureTower, StructureType, Terrain,
+    find,
     game::{self, market::Order},
 };
 
 use crate::{
-    colony::ColonyEvent,
+    colony::{
+        ColonyEvent,
+        journal::{Category, JournalEvent, Severity},
+        upgrade::UpgradePlan,
+    },
     resources::RoomContext,
-    rooms::state::{BoostReason, FarmInfo, constructions::RoomPlan, requests::FarmData},
+    rooms::state::{
+        BoostReason, FarmInfo,
+        constructions::{LinkType, PlannedCell, RoomPlan, RoomStructure, migration, network},
+        requests::{CarryData, FarmData},
+    },
     units::roles::{
         combat::overseer::Overseer, haulers::hauler::Hauler, miners::sk_miner::SKMiner,
         services::house_keeper::HouseKeeper,
     },
-    utils::commons::{is_cpu_on_low, look_for},
+    utils::{
+        commons::{is_cpu_on_low, look_for},
+        constants::{EDGE_LINK_PLAN_INTERVAL, REQUEST_WATCHDOG_INTERVAL, ROAD_MIN_TRAFFIC},
+        profiler,
+    },
 };
 use crate::{commons::find_roles, units::roles::services::upgrader::Upgrader};
+use crate::movement::formation::Formation;
 use crate::{rooms::is_extractor, statistics::RoomStats, utils::commons::find_container_near_by};
 use crate::{
     rooms::state::constructions::RoomPlannerError,
@@ -33,11 +48,19 @@ use crate::{
 use crate::{
     rooms::{
         RoomEvent,
+        logistics::{self, Courier, Stop, StopAction, Urgency},
         state::{
             RoomState, TradeData,
-            requests::{DefendData, Request, RequestKind, WithdrawData, assignment::Assignment},
+            requests::{
+                DefendData, Request, RequestKind, TransferData, WithdrawData,
+                assignment::Assignment, meta::Status, priority, watchdog,
+            },
+        },
+        wrappers::{
+            Fillable,
+            claimed::{Claimed, Fortification},
+            farm::Farm,
         },
-        wrappers::{Fillable, claimed::Claimed, farm::Farm},
     },
     units::roles::miners::mineral_miner::MineralMiner,
 };
@@ -47,6 +70,8 @@ pub struct Shelter<'s> {
     pub(crate) state: &'s mut RoomState,
     pub(crate) farms: Vec<Farm>,
     pub(crate) white_list: &'s HashSet<String>,
+    /// the urgent logistics jobs of the tick
+    urgency: HashMap<Courier, Urgency>,
 }
 
 impl<'s> Shelter<'s> {
@@ -56,7 +81,13 @@ impl<'s> Shelter<'s> {
         state: &'s mut RoomState,
         white_list: &'s HashSet<String>,
     ) -> Self {
-        Shelter { base: Claimed::new(base_room, state), state, farms, white_list }
+        Shelter {
+            base: Claimed::new(base_room, state),
+            state,
+            farms,
+            white_list,
+            urgency: HashMap::new(),
+        }
     }
 
     pub fn run_shelter(
@@ -65,34 +96,56 @@ impl<'s> Shelter<'s> {
         orders: &[Order],
     ) -> Vec<ColonyEvent> {
         let mut events = Vec::new();
+        let cpu_start = game::cpu::get_used();
+        self.state.record_threats(self.base.threatened_ramparts(), game::time());
+        self.state.update_boost_orders();
+        let fortification = self.fortification();
+        self.state.request_order.clear();
         for mut request in self
             .state
             .requests
             .drain()
             .filter(|req| !req.meta.is_finished())
-            .chain(self.base.run_ramparts())
+            .chain(self.base.run_ramparts(&fortification, &self.state.threats))
             .chain(self.base.run_power())
             .chain(self.base.run_nuker())
             .chain(self.base.run_containers())
             .chain(self.base.repair_roads(self.state.plan.as_ref()))
-            .chain(self.base.repair_walls())
+            .chain(self.base.repair_walls(&fortification, &self.state.threats))
             .chain(self.base.tomb_requests())
             .chain(self.base.pickup_requests())
             .chain(self.base.build_requests())
             .collect::<Vec<_>>()
         {
-            events.extend(request.handle(self, creeps));
+            if game::time().is_multiple_of(REQUEST_WATCHDOG_INTERVAL) {
+                self.watch(&mut request, creeps, &mut events);
+            }
+            if !request.meta.is_finished() {
+                request.meta.priority = priority::priority(&request, self);
+                events.extend(request.handle(self, creeps));
+            }
             self.add_request(request);
         }
+        self.state
+            .request_order
+            .sort_by_key(|request| (Reverse(request.priority()), request.created_at()));
+        self.urgency = logistics::urgency(self);
+        profiler::record(("shelter", "requests"), game::cpu::get_used() - cpu_start);
 
+        let cpu_start = game::cpu::get_used();
         self.base.run_towers();
         self.base.run_links();
-        self.base.run_observer();
+        self.base.run_observer(self.observation_target());
+        profiler::record(("shelter", "structures"), game::cpu::get_used() - cpu_start);
+
+        let cpu_start = game::cpu::get_used();
         // lab, terminal and factory toogle request status to InProgress only
         // because only one request can be correctly handled at one tick
         events.extend(
             self.run_labs()
                 .into_iter()
+                .chain(self.plan_boosts(creeps))
+                .chain(self.collect_unboosts())
                 .chain(self.run_factory())
                 .chain(self.run_terminal(orders))
                 .chain(self.base.security_check(self.state, creeps))
@@ -100,7 +153,9 @@ impl<'s> Shelter<'s> {
                 .chain(self.time_based_events(creeps))
                 .chain(self.farms.iter().flat_map(|farm| farm.run_farm(self.name()))),
         );
+        profiler::record(("shelter", "economy"), game::cpu::get_used() - cpu_start);
 
+        let cpu_start = game::cpu::get_used();
         let mut colony_events = Vec::new();
         for event in events {
             match event {
@@ -130,12 +185,26 @@ impl<'s> Shelter<'s> {
                         self.state.add_to_spawn(role, 1);
                     }
                 }
-                RoomEvent::CancelRespawn(role) => {
+                RoomEvent::CancelRespawn(role, mut keep) => {
                     for creep in creeps.iter_mut() {
-                        if creep.1.role == role {
-                            creep.1.respawned = true;
+                        if creep.1.role == role && !creep.1.respawned {
+                            if keep > 0 {
+                                keep -= 1;
+                            } else {
+                                creep.1.respawned = true;
+                            }
                         }
                     }
+                    self.state.spawns.retain(|queued| {
+                        if *queued != role {
+                            true
+                        } else if keep > 0 {
+                            keep -= 1;
+                            true
+                        } else {
+                            false
+                        }
+                    });
                 }
                 RoomEvent::AddPower(power) => {
                     self.state.powers.insert(power);
@@ -149,11 +218,19 @@ impl<'s> Shelter<'s> {
                 RoomEvent::RetainBoosts => {
                     self.state.update_expired_boosts();
                 }
+                RoomEvent::ReserveBoosts(order) => {
+                    info!("{} reserved boosts: {:?}", self.name(), order);
+                    self.state.boost_orders.push(order);
+                }
                 RoomEvent::ReplaceCell(cell) => {
                     if let Some(plan) = self.state.plan.as_mut() {
                         plan.replace_cell(cell);
                     } else {
-                        error!("{} no plan found!", self.name());
+                        colony_events.push(self.journal(
+                            Category::Planner,
+                            Severity::Warning,
+                            format!("{} no plan found to replace a cell", self.name()),
+                        ));
                     }
                 }
                 RoomEvent::Avoid(room_name, timeout) => {
@@ -181,6 +258,9 @@ impl<'s> Shelter<'s> {
                 RoomEvent::Excess(res, amount) => {
                     colony_events.push(ColonyEvent::Excess(self.name(), res, amount));
                 }
+                RoomEvent::DepositYield(res, amount) => {
+                    self.state.record_yield(res, amount);
+                }
                 RoomEvent::Sell(order_id, resource, amount) => {
                     if let Some(mut trade) =
                         self.state.trades.take(&TradeData::new(OrderType::Sell, resource))
@@ -192,7 +272,11 @@ impl<'s> Shelter<'s> {
                                 info!("{} sell: {}", self.name(), resource);
                             }
                             Err(err) => {
-                                error!("sell trade error: {:?}", err);
+                                colony_events.push(self.journal(
+                                    Category::Trade,
+                                    Severity::Warning,
+                                    format!("{} sell {} deal error: {:?}", self.name(), resource, err),
+                                ));
                             }
                         }
                     } else {
@@ -215,7 +299,11 @@ impl<'s> Shelter<'s> {
                                 info!("{} buy: {}", self.name(), resource);
                             }
                             Err(err) => {
-                                error!("buy trade error: {:?}", err);
+                                colony_events.push(self.journal(
+                                    Category::Trade,
+                                    Severity::Warning,
+                                    format!("{} buy {} deal error: {:?}", self.name(), resource, err),
+                                ));
                             }
                         }
                     } else {
@@ -231,7 +319,11 @@ impl<'s> Shelter<'s> {
                     if let Some(message) = message {
                         self.state.intrusion = true;
                         self.state.last_intrusion = game::time();
-                        colony_events.push(ColonyEvent::Notify(message, Some(30)));
+                        colony_events.push(self.journal(
+                            Category::Invasion,
+                            Severity::Warning,
+                            message,
+                        ));
                     } else {
                         self.state.intrusion = false;
                     }
@@ -243,9 +335,9 @@ impl<'s> Shelter<'s> {
                         self.name(),
                         land_time
                     );
-                    colony_events.push(ColonyEvent::Notify(message, Some(30)));
+                    colony_events.push(self.journal(Category::Nuke, Severity::Critical, message));
                 }
-                RoomEvent::EditPlans(plans) => {
+                RoomEvent::EditPlans(plans, upkeep) => {
                     for (name, additional) in plans {
                         if name == self.name() {
                             self.state.set_plan(additional);
@@ -253,6 +345,9 @@ impl<'s> Shelter<'s> {
                             self.state.set_farm_plan(name, additional);
                         }
                     }
+                    for (name, info) in &mut self.state.farms {
+                        info.set_upkeep(upkeep.get(name).copied().unwrap_or_default());
+                    }
                 }
                 RoomEvent::Plan(plan) => {
                     info!("{} construction plan created!", self.name());
@@ -279,6 +374,32 @@ impl<'s> Shelter<'s> {
                         }
                     }
                 }
+                RoomEvent::Migrate(ids, misplaced) => {
+                    let Some(mut migration) = self.state.migration.take() else {
+                        continue;
+                    };
+                    for id in ids {
+                        match id.resolve().map(|structure| structure.destroy()) {
+                            Some(Ok(())) => {
+                                migration.removed += 1;
+                            }
+                            Some(Err(err)) => {
+                                error!("{} can't remove: {}, err: {:?}", self.name(), id, err);
+                            }
+                            None => {}
+                        }
+                    }
+                    migration.misplaced = misplaced;
+                    if misplaced == 0 {
+                        colony_events.push(self.journal(
+                            Category::Planner,
+                            Severity::Info,
+                            format!("{} migration finished: {}", self.name(), migration),
+                        ));
+                    } else {
+                        self.state.migration = Some(migration);
+                    }
+                }
                 RoomEvent::IncrementPlanLvl => {
                     if let Some(mut plan) = self.state.plan.take() {
                         plan.increment_lvl();
@@ -288,10 +409,26 @@ impl<'s> Shelter<'s> {
                 RoomEvent::BlackList(username) => {
                     colony_events.push(ColonyEvent::BlackList(username));
                 }
+                RoomEvent::Journal(event) => {
+                    colony_events.push(ColonyEvent::Journal(event));
+                }
                 RoomEvent::ActivateSafeMode(message) => {
-                    let _ = self.base.controller.activate_safe_mode();
-                    warn!("{} activated safe mode!!", self.name());
-                    colony_events.push(ColonyEvent::Notify(message, Some(30)));
+                    match self.base.controller.activate_safe_mode() {
+                        Ok(()) => {
+                            colony_events.push(self.journal(
+                                Category::SafeMode,
+                                Severity::Critical,
+                                message,
+                            ));
+                        }
+                        Err(err) => {
+                            colony_events.push(self.journal(
+                                Category::SafeMode,
+                                Severity::Critical,
+                                format!("{} safe mode activation error: {:?}", self.name(), err),
+                            ));
+                        }
+                    }
                 }
                 RoomEvent::Origin(v) => {
                     warn!("{} origin development: {}", self.name(), v);
@@ -317,6 +454,7 @@ impl<'s> Shelter<'s> {
                    * } */
             }
         }
+        profiler::record(("shelter", "events"), game::cpu::get_used() - cpu_start);
         colony_events
     }
 
@@ -340,6 +478,7 @@ impl<'s> Shelter<'s> {
                     .chain(self.resource_handler())
                     .chain(self.manage_haulers(creeps))
                     // .chain(self.base.resource_handler())
+                    .chain(self.migration_step())
                     .chain(self.constructions_check())
                     .chain(once(RoomEvent::UpdateStatistic))
             })
@@ -352,10 +491,9 @@ impl<'s> Shelter<'s> {
         if ctrl_lvl > 6 {
             let context = RoomContext::new(
                 ctrl_lvl,
-                self.terminal().map(screeps::HasId::raw_id),
-                self.storage().map(screeps::HasId::raw_id),
                 self.factory().map(screeps::StructureFactory::level).unwrap_or_default(),
                 self.state.plan.as_ref().is_some_and(|plan| plan.built_lvl() == ctrl_lvl),
+                self.state.deposit_income(),
             );
             Some(self.base.resources.events(context))
         } else {
@@ -398,73 +536,245 @@ impl<'s> Shelter<'s> {
         &self,
         creeps: &HashMap<String, CreepMemory>,
     ) -> impl Iterator<Item = RoomEvent> {
-        self.storage()
-            .map(|storage| {
-                let boost_amount =
-                    storage.store().get_used_capacity(Some(ResourceType::CatalyzedGhodiumAcid));
-
-                if boost_amount > 500 && !self.state.boosts.contains_key(&BoostReason::Upgrade) {
-                    Some(RoomEvent::AddBoost(BoostReason::Upgrade, 1500))
-                } else {
-                    None
-                }
-                .into_iter()
-                .chain(self.manage_upgraders(storage, creeps))
-            })
-            .into_iter()
-            .flatten()
+        let boost = self.storage().zip(self.state.upgrade).and_then(|(storage, plan)| {
+            let boost_amount =
+                storage.store().get_used_capacity(Some(ResourceType::CatalyzedGhodiumAcid));
+
+            (plan.boost
+                && boost_amount > 500
+                && !self.state.boosts.contains_key(&BoostReason::Upgrade))
+            .then_some(RoomEvent::AddBoost(BoostReason::Upgrade, 1500))
+        });
+        // the rooms without storage upgrade from the controller container
+        let upgraders = self.state.upgrade.and_then(|plan| self.manage_upgraders(plan, creeps));
+        boost.into_iter().chain(upgraders)
     }
 
     fn manage_upgraders(
         &self,
-        storage: &StructureStorage,
+        plan: UpgradePlan,
         creeps: &HashMap<String, CreepMemory>,
     ) -> Option<RoomEvent> {
-        let upgrader = Role::Upgrader(Upgrader::new(Some(self.name())));
-        let is_alive = self.state.find_roles(&upgrader, creeps).next().is_some();
-        let energy_amount = storage.store().get_used_capacity(Some(ResourceType::Energy));
-
-        if self.controller().level() == 8 && energy_amount > 250_000 && !is_alive {
-            Some(RoomEvent::Spawn(upgrader, 1))
-        } else if self.controller().level() == 8 && energy_amount < 150_000 {
-            Some(RoomEvent::CancelRespawn(upgrader))
-        } else if energy_amount > 150_000 && !is_alive {
-            Some(RoomEvent::Spawn(upgrader, 1))
-        } else if energy_amount < 15_000 {
-            Some(RoomEvent::CancelRespawn(upgrader))
-        } else {
-            None
+        let work = plan.work.div_ceil(plan.upgraders.max(1));
+        let upgrader = Role::Upgrader(Upgrader::with_work(Some(self.name()), work));
+        let alive = find_roles(&upgrader, &self.state.spawns, creeps);
+        let planned = usize::try_from(plan.upgraders).unwrap_or_default();
+
+        match alive.cmp(&planned) {
+            // the plan shrank, the upgraders over it are not replaced
+            Ordering::Greater => Some(RoomEvent::CancelRespawn(upgrader, planned)),
+            Ordering::Less => {
+                debug!("{} upgrade plan {}, upgraders: {}", self.name(), plan, alive);
+                Some(RoomEvent::Spawn(upgrader, planned - alive))
+            }
+            Ordering::Equal => None,
         }
     }
 
+    // one road network for every farm, planned again when a farm has no plan yet
     fn plan_farm(
         &self,
         plan: &RoomPlan,
         farm_infos: &HashMap<RoomName, FarmInfo>,
-    ) -> Result<HashMap<RoomName, RoomPlan>, RoomPlannerError> {
-        if let Some((name, _)) = farm_infos.iter().find(|(_, info)| info.plan().is_none()) {
-            let farm = self
-                .farms
+    ) -> Result<RoomEvent, RoomPlannerError> {
+        let links = plan.get_links().collect::<Vec<_>>();
+        let edge_link_missing = links.len() < StructureType::Link.controller_structures(8) as usize
+            && links
                 .iter()
-                .find(|f| f.get_name() == *name)
-                .ok_or(RoomPlannerError::UnreachableRoom)?;
+                .all(|cell| !matches!(cell.structure, RoomStructure::Link(LinkType::Edge)));
+        // the colonies planned before get their edge link on a replan of the farms
+        let replan = edge_link_missing
+            && !farm_infos.is_empty()
+            && game::time().is_multiple_of(EDGE_LINK_PLAN_INTERVAL);
+        if farm_infos.values().all(|info| info.plan().is_some()) && !replan {
+            return Err(RoomPlannerError::AlreadyCreated);
+        }
+        let farms: Vec<&Farm> =
+            self.farms.iter().filter(|farm| farm_infos.contains_key(&farm.get_name())).collect();
+        if farm_infos
+            .iter()
+            .filter(|(_, info)| info.plan().is_none())
+            .any(|(name, _)| farms.iter().all(|farm| farm.get_name() != *name))
+        {
+            return Err(RoomPlannerError::UnreachableRoom);
+        }
 
-            let plans = farm_infos
-                .iter()
-                .filter_map(|(name, info)| info.plan().map(|plan| (*name, plan)))
-                .chain(once((self.name(), plan)))
-                .collect();
+        let hub = plan
+            .storage()
+            .map(|xy| Position::new(xy.x, xy.y, self.name()))
+            .ok_or(RoomPlannerError::StorageNotFound)?;
+        let plans = farm_infos
+            .iter()
+            .filter_map(|(name, info)| info.plan().map(|plan| (*name, plan)))
+            .chain(once((self.name(), plan)))
+            .collect();
+        let targets: Vec<network::Target> =
+            farms.iter().flat_map(|farm| farm.plan_targets()).collect();
+
+        // the planned farms are routed again, so the quiet roads they have are pruned too
+        let mut network = network::plan(hub, &targets, &plans)?;
+        network.prune(ROAD_MIN_TRAFFIC);
+        let routed = farms.iter().map(|farm| farm.get_name()).collect();
+        let mut additional = network.plans(&plans, &routed);
+
+        // the remote haulers drop their energy into a link where the farm roads come in
+        if edge_link_missing
+            && let Some(terrain) = game::map::get_room_terrain(self.name())
+            && let Some(xy) = network.edge_link(hub, |xy| {
+                !plan.is_occupied(xy) && terrain.get(xy.x.u8(), xy.y.u8()) != Terrain::Wall
+            })
+        {
+            additional.entry(self.name()).or_default().add_cell(PlannedCell::new(
+                xy,
+                RoomStructure::Link(LinkType::Edge),
+                8,
+                None,
+            ));
+        }
+        Ok(RoomEvent::EditPlans(additional, network.game_upkeep()))
+    }
 
-            farm.plan_room(plans)
-        } else {
-            Err(RoomPlannerError::AlreadyCreated)
+    // removes a few structures the plan has no place for, the new ones come with the plan
+    fn migration_step(&self) -> Vec<RoomEvent> {
+        let (Some(_), Some(plan)) = (self.state.migration, &self.state.plan) else {
+            return Vec::new();
+        };
+        if self.state.intrusion {
+            return Vec::new();
         }
+
+        let structures: Vec<StructureObject> = self
+            .base
+            .room
+            .find(find::STRUCTURES, None)
+            .into_iter()
+            .filter(|structure| {
+                !matches!(
+                    structure.structure_type(),
+                    StructureType::Controller
+                        | StructureType::KeeperLair
+                        | StructureType::Portal
+                        | StructureType::PowerBank
+                        | StructureType::InvaderCore
+                ) && structure.as_owned().is_none_or(OwnedStructureProperties::my)
+            })
+            .collect();
+        let built: Vec<(RoomXY, StructureType)> = structures
+            .iter()
+            .map(|structure| (structure.pos().xy(), structure.structure_type()))
+            .collect();
+        // with no terminal to drain into, the storage goes with what it holds
+        let storage_used = self
+            .base
+            .storage
+            .as_ref()
+            .filter(|_| self.base.terminal.is_some())
+            .map_or(0, |storage| storage.store().get_used_capacity(None));
+        let terminal_used = self
+            .base
+            .terminal
+            .as_ref()
+            .map_or(0, |terminal| terminal.store().get_used_capacity(None));
+        let removals = migration::removals(
+            &built,
+            plan,
+            self.controller().level(),
+            storage_used,
+            terminal_used,
+        );
+
+        let mut events = Vec::new();
+        if removals.drain_storage
+            && let (Some(storage), Some(terminal)) = (&self.base.storage, &self.base.terminal)
+            && let Some(resource) = storage
+                .store()
+                .store_types()
+                .into_iter()
+                .max_by_key(|resource| storage.store().get_used_capacity(Some(*resource)))
+        {
+            let amount = storage
+                .store()
+                .get_used_capacity(Some(resource))
+                .min(terminal.store().get_free_capacity(None).try_into().unwrap_or_default());
+            if amount > 0 {
+                events.push(RoomEvent::Request(Request::new(
+                    RequestKind::Carry(CarryData::new(
+                        storage.raw_id(),
+                        terminal.raw_id(),
+                        resource,
+                        amount,
+                    )),
+                    Assignment::Single(None),
+                )));
+            }
+        }
+        if removals.drain_terminal {
+            events.extend(self.drain_terminal(plan));
+        }
+        let ids = removals
+            .remove
+            .iter()
+            .filter_map(|index| structures.get(*index))
+            .map(|structure| structure.as_structure().id())
+            .collect();
+        events.push(RoomEvent::Migrate(ids, removals.misplaced));
+        events
+    }
+
+    // the misplaced terminal empties into the planned storage, or into another base
+    fn drain_terminal(&self, plan: &RoomPlan) -> Option<RoomEvent> {
+        let terminal = self.base.terminal.as_ref()?;
+        let resource = terminal
+            .store()
+            .store_types()
+            .into_iter()
+            .max_by_key(|resource| terminal.store().get_used_capacity(Some(*resource)))?;
+        let used = terminal.store().get_used_capacity(Some(resource));
+
+        if let Some(storage) =
+            self.base.storage.as_ref().filter(|storage| plan.storage() == Some(storage.pos().xy()))
+        {
+            let amount =
+                used.min(storage.store().get_free_capacity(None).try_into().unwrap_or_default());
+            return (amount > 0).then(|| {
+                RoomEvent::Request(Request::new(
+                    RequestKind::Carry(CarryData::new(
+                        terminal.raw_id(),
+                        storage.raw_id(),
+                        resource,
+                        amount,
+                    )),
+                    Assignment::Single(None),
+                ))
+            });
+        }
+
+        let destination = game::rooms()
+            .values()
+            .filter(|room| {
+                room.name() != self.name()
+                    && room.terminal().is_some()
+                    && room.controller().is_some_and(|controller| controller.my())
+            })
+            .map(|room| room.name())
+            .min_by_key(|room| game::map::get_room_linear_distance(self.name(), *room, false))?;
+        // the energy sent pays the transaction cost too
+        let amount = if resource == ResourceType::Energy { used / 2 } else { used };
+        Some(RoomEvent::Request(Request::new(
+            RequestKind::Transfer(TransferData::new(
+                resource,
+                amount,
+                destination,
+                Some(format!("migration from: {}", self.name())),
+            )),
+            Assignment::None,
+        )))
     }
 
     fn constructions_check(&self) -> Option<RoomEvent> {
         if let Some(plan) = &self.state.plan {
             match self.plan_farm(plan, &self.state.farms) {
-                Ok(plans) => Some(RoomEvent::EditPlans(plans)),
+                Ok(event) => Some(event),
                 Err(err) => match err {
                     RoomPlannerError::AlreadyCreated => {
                         let buildings: HashMap<RoomXY, StructureType> =
@@ -477,25 +787,33 @@ impl<'s> Shelter<'s> {
                             None
                         }
                     }
-                    e => {
-                        error!("{} creation plan error: {}", self.name(), e);
-                        None
-                    }
+                    e => Some(RoomEvent::Journal(JournalEvent::new(
+                        Category::Planner,
+                        Severity::Warning,
+                        Some(self.name()),
+                        format!("{} creation plan error: {}", self.name(), e),
+                    ))),
                 },
             }
         } else if !is_cpu_on_low() {
             match self.base.generate_plan(None) {
                 Ok(plan) => Some(RoomEvent::Plan(plan)),
-                Err(err) => {
-                    error!("{}", err);
-                    None
-                }
+                Err(err) => Some(RoomEvent::Journal(JournalEvent::new(
+                    Category::Planner,
+                    Severity::Critical,
+                    Some(self.name()),
+                    format!("{} plan generation error: {}", self.name(), err),
+                ))),
             }
         } else {
             None
         }
     }
 
+    fn journal(&self, category: Category, severity: Severity, message: String) -> ColonyEvent {
+        ColonyEvent::Journal(JournalEvent::new(category, severity, Some(self.name()), message))
+    }
+
     //mineral_miners is periodic by default cause don't respawn
     fn get_periodic_roles(&self, room_name: RoomName) -> Vec<Role> {
         let mut roles: Vec<Role> = self
@@ -552,8 +870,23 @@ impl<'s> Shelter<'s> {
         self.base.closest_empty_structure(to)
     }
 
+    /// The weakest perimeter rampart, the ones hostiles are at first
     pub fn lowest_perimetr_hits(&self) -> Option<&StructureRampart> {
-        self.base.ramparts.perimeter().sorted_by_key(screeps::HasHits::hits).next()
+        let threatened = self.base.threatened_ramparts();
+        self.base
+            .ramparts
+            .perimeter()
+            .min_by_key(|rampart| (!threatened.contains(&rampart.pos().xy()), rampart.hits()))
+    }
+
+    fn fortification(&self) -> Fortification {
+        let stored = self
+            .base
+            .storage
+            .as_ref()
+            .map_or(0, |storage| storage.store().get_used_capacity(Some(ResourceType::Energy)));
+        let income = self.all_sources().map(Source::energy_capacity).sum::<u32>() / ENERGY_REGEN_TIME;
+        Fortification::new(self.controller().level(), stored, income)
     }
 
     pub fn empty_sender(&self) -> Option<&StructureLink> {
@@ -570,25 +903,161 @@ impl<'s> Shelter<'s> {
             .filter(|link| link.store().get_used_capacity(Some(ResourceType::Energy)) > 0)
     }
 
+    fn observation_target(&self) -> Option<RoomName> {
+        self.requests().find_map(|request| match &request.kind {
+            RequestKind::Siege(d) if d.needs_vision() => Some(d.target),
+            _ => None,
+        })
+    }
+
+    pub fn squad_formation(&self, squad_id: &str) -> Formation {
+        self.requests()
+            .find_map(|request| request.assignment.formation(squad_id))
+            .unwrap_or_default()
+    }
+
+    pub fn reserved(&self, id: RawObjectId) -> u32 {
+        self.state.reserved(id)
+    }
+
+    pub fn urgency(&self, courier: Courier) -> Option<&Urgency> {
+        self.urgency.get(&courier)
+    }
+
+    /// Gives up a route: the energy promised to the structures and the requests go back
+    pub fn release_route(&mut self, stops: &[Stop], doer: String) {
+        for stop in stops {
+            match &stop.request {
+                Some(request) => self.reset_request(request, doer.clone()),
+                None if stop.action == StopAction::Deliver => {
+                    self.state.release(stop.id, stop.amount.unwrap_or_default());
+                }
+                None => {}
+            }
+        }
+    }
+
+    pub fn plan_route(&mut self, creep: &Creep, courier: Courier) -> Option<Vec<Stop>> {
+        let route = logistics::plan_route(self, creep, courier);
+
+        for request in route.claimed {
+            if let Some(mut request) = self.take_request(&request) {
+                request.join(Some(creep.name()), None);
+                self.add_request(request);
+            }
+        }
+        for (request, left) in route.split {
+            if let Some(mut request) = self.take_request(&request) {
+                if let RequestKind::Carry(d) = &mut request.kind {
+                    d.amount = left;
+                }
+                request.assignment = Assignment::Single(None);
+                request.meta.update(Status::Created);
+                self.add_request(request);
+            }
+        }
+        for (id, amount) in route.reserved {
+            self.state.reserve(id, amount);
+        }
+
+        debug!("{} {} planned route: {:?}", self.name(), creep.name(), route.stops);
+        (!route.stops.is_empty()).then_some(route.stops)
+    }
+
+    /// The most important and then the oldest requests first, the ones added this tick last
     pub fn requests(&self) -> impl Iterator<Item = &Request> {
-        self.state.requests.iter()
+        self.state.request_order.iter().filter_map(|request| self.state.requests.get(request))
     }
 
     pub fn resolve_request(&mut self, request: Request, doer: String) {
         let removed = self.state.requests.remove(&request);
+        self.forget_order(&request);
         debug!("{} resolved request: {} {:?}", doer, removed, request);
     }
 
+    // resets or aborts a stuck request, the stale doer drops the task it still runs
+    fn watch(
+        &self,
+        request: &mut Request,
+        creeps: &mut HashMap<String, CreepMemory>,
+        events: &mut Vec<RoomEvent>,
+    ) {
+        let status = request.meta.status.clone();
+        let doer = match &request.assignment {
+            Assignment::Single(doer) => doer.clone(),
+            _ => None,
+        };
+        let Some(reason) = watchdog::inspect(request) else {
+            return;
+        };
+
+        if let Assignment::Single(None) = request.assignment
+            && let Some(memory) = doer.and_then(|doer| creeps.get_mut(&doer))
+        {
+            memory.task = None;
+        }
+        events.push(RoomEvent::Journal(
+            JournalEvent::new(
+                Category::Request,
+                Severity::Info,
+                Some(self.name()),
+                format!("{} {}", self.name(), reason),
+            )
+            .with_key(format!("{} {:?}", request.kind.name(), status)),
+        ));
+    }
+
+    /// Puts the request back for another doer
+    pub fn reset_request(&mut self, request: &Request, doer: String) {
+        if let Some(mut request) = self.take_request(request) {
+            request.assignment = Assignment::Single(None);
+            request.meta.update(Status::Created);
+            debug!("{} reset request: {:?}", doer, request);
+            self.add_request(request);
+        }
+    }
+
+    /// Whether the doer is the only one working on a request
+    pub fn serving(&self, doer: &str) -> bool {
+        self.requests().any(|request| {
+            matches!(request.assignment, Assignment::Single(_)) && request.assigned_to(doer)
+        })
+    }
+
+    /// Takes the doer out of every request it joined
+    pub fn release_doer(&mut self, doer: &str) {
+        let joined: Vec<Request> =
+            self.requests().filter(|request| request.assigned_to(doer)).cloned().collect();
+        for request in joined {
+            if let Some(mut request) = self.take_request(&request) {
+                request.assignment.remove_doer(doer);
+                debug!("{} released request: {:?}", doer, request);
+                self.add_request(request);
+            }
+        }
+    }
+
+    fn forget_order(&mut self, request: &Request) {
+        if let Some(index) = self.state.request_order.iter().position(|r| r == request) {
+            self.state.request_order.remove(index);
+        }
+    }
+
     pub fn replace_request(&mut self, request: Request) {
         let replaced = self.state.requests.replace(request);
         debug!("replaced request: {:?}", replaced);
     }
 
     pub fn add_request(&mut self, request: Request) -> bool {
+        if self.state.requests.contains(&request) {
+            return false;
+        }
+        self.state.request_order.push(request.clone());
         self.state.requests.insert(request)
     }
 
     pub fn take_request(&mut self, request: &Request) -> Option<Request> {
+        self.forget_order(request);
         self.state.requests.take(request)
     }
 
@@ -608,6 +1077,10 @@ impl<'s> Shelter<'s> {
         &self.base.controller
     }
 
+    pub fn edge_link(&self) -> Option<&StructureLink> {
+        self.base.links.edge()
+    }
+
     pub fn pc_workplace(&self) -> Option<Position> {
         self.state
             .plan
@@ -640,10 +1113,6 @@ impl<'s> Shelter<'s> {
         (self.base.labs.inputs(), self.base.labs.outputs())
     }
 
-    pub fn lab_for_boost(&self, resources: [ResourceType; 2]) -> Option<ObjectId<StructureLab>> {
-        resources.iter().find_map(|res| self.base.labs.boost_lab(*res))
-    }
-
     pub(crate) fn get_trades(&self) -> impl Iterator<Item = &TradeData> {
         self.state.trades.iter()
     }
@@ -696,23 +1165,6 @@ impl<'s> Shelter<'s> {
         })
     }
 
-    pub fn get_available_boost(
-        &self,
-        creep: &Creep,
-        all_boosts: HashMap<Part, [ResourceType; 2]>,
-    ) -> Option<(ObjectId<StructureLab>, Part)> {
-        creep
-            .body()
-            .iter()
-            .filter(|bodypart| bodypart.boost().is_none())
-            .map(screeps::BodyPart::part)
-            .unique()
-            .filter_map(|part| all_boosts.get(&part).map(|resoucres| (part, resoucres)))
-            .find_map(|resources_for_part| {
-                self.lab_for_boost(*resources_for_part.1).map(|id| (id, resources_for_part.0))
-            })
-    }
-
     pub fn unload<T>(&self, obj: &T, allowed: &[ResourceType]) -> Option<RoomEvent>
     where
         T: HasStore + HasId + HasPosition,
@@ -4,14 +4,21 @@ use std::iter::Iterator;
 
 use log::info;
 use ordered_float::OrderedFloat;
-use screeps::{OrderType, PowerType, ResourceType, RoomName, game};
+use screeps::{OrderType, PowerType, RawObjectId, ResourceType, RoomName, RoomXY, game};
 use serde::{Deserialize, Serialize};
 
+use crate::colony::upgrade::UpgradePlan;
+use crate::rooms::state::boosts::BoostOrder;
 use crate::rooms::state::constructions::RoomPlan;
+use crate::rooms::state::constructions::migration::Migration;
 use crate::rooms::state::requests::Request;
 use crate::units::creeps::CreepMemory;
 use crate::units::roles::Role;
+use crate::utils::constants::{
+    DEPOSIT_YIELD_WINDOW, FORTIFY_THREAT_TICKS, LOGISTICS_RESERVATION_TICKS,
+};
 
+pub mod boosts;
 pub mod constructions;
 pub mod requests;
 
@@ -21,6 +28,9 @@ pub struct RoomState {
     pub spawns: Vec<Role>,
     #[serde(default)]
     pub requests: HashSet<Request>,
+    /// the requests by priority and age, sorted once a tick and extended with the new ones
+    #[serde(skip)]
+    pub request_order: Vec<Request>,
     #[serde(default)]
     pub plan: Option<RoomPlan>,
     #[serde(default = "HashMap::new")]
@@ -41,6 +51,24 @@ pub struct RoomState {
     pub powers: HashSet<PowerType>,
     #[serde(default = "HashMap::new")]
     pub boosts: HashMap<BoostReason, u32>,
+    /// harvested deposit resources by 1000 ticks buckets
+    #[serde(default = "HashMap::new")]
+    pub deposit_yields: HashMap<ResourceType, Vec<(u32, u32)>>,
+    /// energy on the way to a structure: amount and expiration tick
+    #[serde(skip)]
+    pub reservations: HashMap<RawObjectId, (u32, u32)>,
+    /// upgrade energy allocated by the colony
+    #[serde(default)]
+    pub upgrade: Option<UpgradePlan>,
+    /// moving the built structures onto the plan
+    #[serde(default)]
+    pub migration: Option<Migration>,
+    /// ramparts hostiles came in range of and the last tick they did
+    #[serde(default)]
+    pub threats: Vec<(RoomXY, u32)>,
+    /// minerals reserved for the creeps to be boosted
+    #[serde(default)]
+    pub boost_orders: Vec<BoostOrder>,
 }
 
 impl RoomState {
@@ -52,13 +80,9 @@ impl RoomState {
         }
     }
 
-    pub fn set_farm_plan(&mut self, name: RoomName, mut plan: RoomPlan) {
-        self.farms.entry(name).and_modify(|info| {
-            if let Some(existed) = info.plan.take() {
-                plan.add_cells(existed.planned_cells().into_iter());
-            }
-            info.plan = Some(plan);
-        });
+    /// The road network plans the farm whole
+    pub fn set_farm_plan(&mut self, name: RoomName, plan: RoomPlan) {
+        self.farms.entry(name).and_modify(|info| info.plan = Some(plan));
     }
 
     pub fn add_to_spawn(&mut self, role: Role, times: usize) {
@@ -99,6 +123,81 @@ impl RoomState {
     pub fn update_expired_boosts(&mut self) {
         self.boosts.retain(|_, timeout| game::time() < *timeout);
     }
+
+    pub fn update_boost_orders(&mut self) {
+        let now = game::time();
+        self.boost_orders.retain_mut(|order| {
+            let Some(creep) = game::creeps().get(order.creep.clone()) else {
+                return false;
+            };
+            let boosts: Vec<ResourceType> =
+                creep.body().iter().filter_map(screeps::BodyPart::boost).collect();
+            order.received(&boosts);
+            !order.resources.is_empty() && !order.is_expired(now)
+        });
+    }
+
+    pub fn boost_reserved(&self, resource: ResourceType) -> u32 {
+        self.boost_orders.iter().map(|order| order.reserved(resource)).sum()
+    }
+
+    pub fn record_yield(&mut self, resource: ResourceType, amount: u32) {
+        let bucket = game::time() / 1000 * 1000;
+        let yields = self.deposit_yields.entry(resource).or_default();
+        yields.retain(|(tick, _)| tick + DEPOSIT_YIELD_WINDOW > bucket);
+        match yields.last_mut() {
+            Some((tick, total)) if *tick == bucket => *total += amount,
+            _ => yields.push((bucket, amount)),
+        }
+    }
+
+    pub fn record_threats(&mut self, ramparts: Vec<RoomXY>, now: u32) {
+        self.threats.retain(|(xy, tick)| {
+            tick + FORTIFY_THREAT_TICKS > now && !ramparts.contains(xy)
+        });
+        self.threats.extend(ramparts.into_iter().map(|xy| (xy, now)));
+    }
+
+    pub fn reserve(&mut self, id: RawObjectId, amount: u32) {
+        let expire = game::time() + LOGISTICS_RESERVATION_TICKS;
+        self.reservations
+            .entry(id)
+            .and_modify(|(reserved, timeout)| {
+                if *timeout < game::time() {
+                    *reserved = 0;
+                }
+                *reserved += amount;
+                *timeout = expire;
+            })
+            .or_insert((amount, expire));
+    }
+
+    pub fn release(&mut self, id: RawObjectId, amount: u32) {
+        if let Some((reserved, _)) = self.reservations.get_mut(&id) {
+            *reserved = reserved.saturating_sub(amount);
+        }
+    }
+
+    pub fn reserved(&self, id: RawObjectId) -> u32 {
+        self.reservations
+            .get(&id)
+            .filter(|(_, timeout)| game::time() <= *timeout)
+            .map_or(0, |(amount, _)| *amount)
+    }
+
+    pub fn deposit_income(&self) -> HashMap<ResourceType, u32> {
+        self.deposit_yields
+            .iter()
+            .map(|(resource, yields)| {
+                let recent = yields
+                    .iter()
+                    .filter(|(tick, _)| tick + DEPOSIT_YIELD_WINDOW > game::time())
+                    .map(|(_, amount)| amount)
+                    .sum();
+                (*resource, recent)
+            })
+            .collect()
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -106,6 +205,9 @@ pub struct FarmInfo {
     #[serde(default)]
     active: bool,
     plan: Option<RoomPlan>,
+    /// repair energy a tick of the roads the farm hauls over
+    #[serde(default)]
+    upkeep: f64,
 }
 
 impl FarmInfo {
@@ -120,6 +222,14 @@ impl FarmInfo {
     pub fn is_active(&self) -> bool {
         self.active
     }
+
+    pub const fn upkeep(&self) -> f64 {
+        self.upkeep
+    }
+
+    pub const fn set_upkeep(&mut self, upkeep: f64) {
+        self.upkeep = upkeep;
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Hash)]
@@ -133,12 +243,13 @@ pub enum BoostReason {
     Defend,
     Pvp,
     Carry,
+    Powerbank,
 }
 
 impl BoostReason {
     pub fn value(&self) -> Vec<ResourceType> {
         match *self {
-            BoostReason::Invasion => {
+            BoostReason::Invasion | BoostReason::Powerbank => {
                 vec![ResourceType::CatalyzedUtriumAcid, ResourceType::CatalyzedLemergiumAlkalide]
             }
             BoostReason::Carry => vec![ResourceType::CatalyzedZynthiumAlkalide],
@@ -178,11 +289,14 @@ pub struct TradeData {
     pub price: OrderedFloat<f64>,
     // pub former_price: Option<OrderedFloat<f64>>,
     pub amount: u32,
+    /// our own market order is up, the terminal does not deal
+    #[serde(default)]
+    pub posted: bool,
 }
 
 impl TradeData {
     pub fn new(order_type: OrderType, resource: ResourceType) -> Self {
-        Self { order_type, resource, price: OrderedFloat::default(), amount: 0 }
+        Self { order_type, resource, price: OrderedFloat::default(), amount: 0, posted: false }
     }
 
     pub const fn with_price_and_amount(
@@ -191,7 +305,7 @@ impl TradeData {
         price: OrderedFloat<f64>,
         amount: u32,
     ) -> Self {
-        Self { order_type, resource, price, amount }
+        Self { order_type, resource, price, amount, posted: false }
     }
 }
 
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use screeps::{LAB_BOOST_MINERAL, ResourceType};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::constants::BOOST_ORDER_TICKS;
+
+/// Minerals put aside for a creep from the tick it starts spawning until it is boosted
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BoostOrder {
+    pub creep: String,
+    /// the resources still to be taken and their amount
+    pub resources: Vec<(ResourceType, u32)>,
+    pub created: u32,
+}
+
+impl BoostOrder {
+    pub const fn new(creep: String, resources: Vec<(ResourceType, u32)>, created: u32) -> Self {
+        Self { creep, resources, created }
+    }
+
+    pub fn reserved(&self, resource: ResourceType) -> u32 {
+        self.resources.iter().filter(|(res, _)| *res == resource).map(|(_, amount)| amount).sum()
+    }
+
+    /// Forgets the boosts the creep wears already
+    pub fn received(&mut self, boosts: &[ResourceType]) {
+        self.resources.retain(|(res, _)| !boosts.contains(res));
+    }
+
+    pub const fn is_expired(&self, now: u32) -> bool {
+        self.created + BOOST_ORDER_TICKS < now
+    }
+}
+
+/// The boosts for the parts of a body: the tier the stock covers, else the fallback, else as many
+/// parts as the larger stock boosts
+pub fn plan(
+    wanted: &[(u32, [ResourceType; 2])],
+    mut available: HashMap<ResourceType, u32>,
+) -> Vec<(ResourceType, u32)> {
+    let mut result: Vec<(ResourceType, u32)> = Vec::new();
+    for (parts, [primary, fallback]) in wanted {
+        let need = parts * LAB_BOOST_MINERAL;
+        let stock = |res: &ResourceType| available.get(res).copied().unwrap_or_default();
+        let resource = if stock(primary) >= need || stock(primary) >= stock(fallback) {
+            *primary
+        } else {
+            *fallback
+        };
+        let amount = need.min(stock(&resource) / LAB_BOOST_MINERAL * LAB_BOOST_MINERAL);
+        if amount == 0 {
+            continue;
+        }
+
+        available.entry(resource).and_modify(|left| *left -= amount);
+        match result.iter_mut().find(|(res, _)| *res == resource) {
+            Some((_, reserved)) => *reserved += amount,
+            None => result.push((resource, amount)),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use screeps::ResourceType;
+
+    use crate::rooms::state::boosts::plan;
+
+    #[test]
+    fn boost_plan_test() {
+        let heal = [ResourceType::CatalyzedLemergiumAlkalide, ResourceType::LemergiumAlkalide];
+        let attack = [ResourceType::CatalyzedUtriumAcid, ResourceType::UtriumAcid];
+        let available = HashMap::from([
+            (ResourceType::CatalyzedLemergiumAlkalide, 500),
+            (ResourceType::LemergiumAlkalide, 3000),
+            (ResourceType::UtriumAcid, 100),
+        ]);
+
+        // the best tier falls short, the fallback covers every part
+        assert_eq!(plan(&[(20, heal)], available.clone()), vec![(heal[1], 600)]);
+        // the first order takes the stock the second one counted on
+        assert_eq!(
+            plan(&[(10, heal), (10, heal)], available.clone()),
+            vec![(heal[0], 300), (heal[1], 300)]
+        );
+        // not enough of any tier, boost what the stock covers
+        assert_eq!(plan(&[(10, attack)], available), vec![(attack[1], 90)]);
+    }
+}
@@ -11,10 +11,16 @@ use screeps::{ROOM_SIZE, Source};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+pub mod exchange;
 mod farm;
+pub mod migration;
+pub mod network;
 mod owned;
+pub mod visualizer;
 mod xy_util;
 
+pub use owned::{BaseSite, PlanScore, plan_site};
+
 type OuterRectangle = (u8, u8, u8, u8);
 type Walls = [[bool; ROOM_SIZE as usize]; ROOM_SIZE as usize];
 type Sat = [[u16; ROOM_SIZE as usize]; ROOM_SIZE as usize];
@@ -67,6 +73,12 @@ pub enum RoomPlannerError {
     GridCreationFailed,
     #[error("farm is temporarly suspended")]
     FarmSuspended,
+    #[error("{0} can't stand at {1}:{2}")]
+    ImportConflict(StructureType, u8, u8),
+    #[error("more {0} than the controller allows")]
+    StructureLimitExceeded(StructureType),
+    #[error("{0} can't be planned")]
+    UnsupportedStructure(StructureType),
     #[error("room plan is already created")]
     AlreadyCreated, /* #[error("plan generation already finished")]
                      * PlanGenerationFinished, */
@@ -163,6 +175,10 @@ impl RoomPlan {
         })
     }
 
+    pub fn cells(&self) -> impl Iterator<Item = &PlannedCell> {
+        self.planned_cells.iter()
+    }
+
     pub fn planned_cells(self) -> HashSet<PlannedCell> {
         self.planned_cells
     }
@@ -394,12 +410,13 @@ pub enum LabStatus {
     Output,
 }
 
-#[derive(Serialize, Deserialize, Copy, Clone, Debug)]
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
 pub enum LinkType {
     Sender,
     Receiver,
     Ctrl,
     Source,
+    Edge,
 }
 
 // Information about structure present in a cell
@@ -459,6 +476,11 @@ pub fn build_wall_bitmap<T: TerrainSource>(src: &T) -> Walls {
     m
 }
 
+/// The lowest controller level that allows one more structure after `built` ones
+pub fn lowest_level(structure: StructureType, built: usize) -> Option<u8> {
+    (1..=8).find(|rcl| structure.controller_structures(u32::from(*rcl)) as usize > built)
+}
+
 #[inline]
 const fn is_wall(walls: &Walls, p: RoomXY) -> bool {
     walls[p.y.u8() as usize][p.x.u8() as usize]
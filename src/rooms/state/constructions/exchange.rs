@@ -0,0 +1,186 @@
+use std::collections::{HashMap, HashSet};
+
+use screeps::local::RoomXY;
+use screeps::{ROOM_SIZE, StructureType, Terrain};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    LabStatus, LinkType, PlannedCell, RoomPart, RoomPlan, RoomPlannerError, RoomStructure,
+    TerrainSource, lowest_level,
+};
+use crate::utils::constants::EDGE_LINK_RANGE;
+
+// labs in range of every other lab take the reagents
+const INPUT_LABS: usize = 2;
+const LAB_REACH: u8 = 2;
+const CTRL_LINK_RANGE: u8 = 3;
+
+/// Room layout in the community building-planner format:
+/// `{"rcl": 8, "buildings": {"spawn": {"pos": [{"x": 25, "y": 25}]}}}`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BuildingPlan {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shard: Option<String>,
+    pub rcl: u8,
+    pub buildings: HashMap<StructureType, Positions>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Positions {
+    pub pos: Vec<Position>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub x: u8,
+    pub y: u8,
+}
+
+/// The structures standing at the level, every level when `rcl` is 8
+pub fn export(plan: &RoomPlan, name: Option<String>, rcl: u8) -> BuildingPlan {
+    let mut buildings: HashMap<StructureType, Positions> = HashMap::new();
+    for cell in plan
+        .cells()
+        .filter(|cell| cell.b_lvl <= rcl && cell.r_lvl.is_none_or(|remove_lvl| remove_lvl > rcl))
+    {
+        if let Ok(structure) = StructureType::try_from(cell.structure) {
+            buildings
+                .entry(structure)
+                .or_default()
+                .pos
+                .push(Position { x: cell.xy.x.u8(), y: cell.xy.y.u8() });
+        }
+    }
+    for positions in buildings.values_mut() {
+        positions.pos.sort_unstable_by_key(|pos| (pos.y, pos.x));
+    }
+    BuildingPlan { name, shard: None, rcl, buildings }
+}
+
+/// Checks the layout against the terrain and the structure limits and gives every structure
+/// the lowest level it can be built at, the ones closer to the storage first
+pub fn import<T: TerrainSource>(
+    document: &BuildingPlan,
+    terrain: &T,
+    ctrl: Option<RoomXY>,
+) -> Result<RoomPlan, RoomPlannerError> {
+    let mut placed: HashMap<StructureType, Vec<RoomXY>> = HashMap::new();
+    let mut occupied = HashSet::new();
+    for (structure, positions) in &document.buildings {
+        for pos in &positions.pos {
+            let xy = RoomXY::checked_new(pos.x, pos.y)
+                .map_err(|_| RoomPlannerError::ImportConflict(*structure, pos.x, pos.y))?;
+            let on_wall = terrain.terrain_at(pos.x, pos.y) == Terrain::Wall
+                && !matches!(structure, StructureType::Road | StructureType::Extractor);
+            let on_edge =
+                pos.x == 0 || pos.y == 0 || pos.x == ROOM_SIZE - 1 || pos.y == ROOM_SIZE - 1;
+            let overlaps = !matches!(structure, StructureType::Road | StructureType::Rampart)
+                && !occupied.insert(xy);
+            if on_wall || on_edge || overlaps {
+                return Err(RoomPlannerError::ImportConflict(*structure, pos.x, pos.y));
+            }
+            placed.entry(*structure).or_default().push(xy);
+        }
+    }
+
+    let anchor = [StructureType::Storage, StructureType::Spawn]
+        .iter()
+        .find_map(|structure| placed.get(structure).and_then(|xys| xys.first()).copied())
+        .unwrap_or_else(|| unsafe { RoomXY::unchecked_new(ROOM_SIZE / 2, ROOM_SIZE / 2) });
+    let labs = placed.get(&StructureType::Lab).cloned().unwrap_or_default();
+    let mut inputs = 0;
+
+    let mut cells = HashSet::new();
+    for (structure, mut xys) in placed {
+        xys.sort_unstable_by_key(|xy| (xy.get_range_to(anchor), xy.y, xy.x));
+        for (i, xy) in xys.into_iter().enumerate() {
+            let lvl = lowest_level(structure, i)
+                .ok_or(RoomPlannerError::StructureLimitExceeded(structure))?;
+            let room_structure = match structure {
+                StructureType::Spawn => RoomStructure::Spawn,
+                StructureType::Extension => RoomStructure::Extension,
+                StructureType::Road => RoomStructure::Road(usize::from(xy.get_range_to(anchor))),
+                StructureType::Wall => RoomStructure::Wall,
+                StructureType::Rampart => RoomStructure::Rampart(!occupied.contains(&xy)),
+                StructureType::Link => RoomStructure::Link(if i == 0 {
+                    LinkType::Sender
+                } else if ctrl.is_some_and(|ctrl| ctrl.get_range_to(xy) <= CTRL_LINK_RANGE) {
+                    LinkType::Ctrl
+                } else if xy.get_range_to(anchor) <= LAB_REACH {
+                    LinkType::Receiver
+                } else if xy.x.u8().min(xy.y.u8()) <= EDGE_LINK_RANGE
+                    || xy.x.u8().max(xy.y.u8()) >= ROOM_SIZE - 1 - EDGE_LINK_RANGE
+                {
+                    LinkType::Edge
+                } else {
+                    LinkType::Source
+                }),
+                StructureType::Storage => RoomStructure::Storage,
+                StructureType::Tower => RoomStructure::Tower,
+                StructureType::Observer => RoomStructure::Observer,
+                StructureType::PowerSpawn => RoomStructure::PowerSpawn,
+                StructureType::Extractor => RoomStructure::Extractor,
+                StructureType::Lab => {
+                    let input = inputs < INPUT_LABS
+                        && labs.iter().all(|lab| lab.get_range_to(xy) <= LAB_REACH);
+                    inputs += usize::from(input);
+                    RoomStructure::Lab(if input { LabStatus::Input } else { LabStatus::Output })
+                }
+                StructureType::Terminal => RoomStructure::Terminal,
+                StructureType::Container => RoomStructure::Container(RoomPart::Red),
+                StructureType::Nuker => RoomStructure::Nuker,
+                StructureType::Factory => RoomStructure::Factory,
+                _ => return Err(RoomPlannerError::UnsupportedStructure(structure)),
+            };
+            cells.insert(PlannedCell::new(xy, room_structure, lvl, None));
+        }
+    }
+    Ok(RoomPlan::new(cells))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use screeps::{StructureType, Terrain};
+
+    use crate::rooms::state::constructions::RoomPlannerError;
+    use crate::rooms::state::constructions::exchange::{
+        BuildingPlan, Position, Positions, export, import,
+    };
+    use crate::rooms::state::constructions::tests::WALLS;
+
+    #[test]
+    fn plan_exchange_test() {
+        let terrain = |x: u8, y: u8| {
+            if WALLS[usize::from(y)][usize::from(x)] { Terrain::Wall } else { Terrain::Plain }
+        };
+        let positions = |xys: &[(u8, u8)]| Positions {
+            pos: xys.iter().map(|(x, y)| Position { x: *x, y: *y }).collect(),
+        };
+        let mut document = BuildingPlan {
+            rcl: 8,
+            buildings: HashMap::from([
+                (StructureType::Storage, positions(&[(35, 15)])),
+                (StructureType::Spawn, positions(&[(36, 17), (34, 17)])),
+                (StructureType::Road, positions(&[(35, 16), (35, 17), (35, 18)])),
+            ]),
+            ..BuildingPlan::default()
+        };
+
+        let plan = import(&document, &terrain, None).unwrap();
+        let spawns = export(&plan, None, 6).buildings.remove(&StructureType::Spawn).unwrap();
+        assert_eq!(spawns.pos.len(), 1);
+        let exported = export(&plan, None, 8);
+        assert_eq!(exported.buildings[&StructureType::Road].pos.len(), 3);
+        assert_eq!(exported.buildings[&StructureType::Spawn].pos.len(), 2);
+
+        document.buildings.insert(StructureType::Storage, positions(&[(35, 15), (36, 15)]));
+        assert_eq!(
+            import(&document, &terrain, None).err(),
+            Some(RoomPlannerError::StructureLimitExceeded(StructureType::Storage))
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+use std::fmt::{self, Display};
+
+use screeps::StructureType;
+use screeps::local::RoomXY;
+use serde::{Deserialize, Serialize};
+
+use super::RoomPlan;
+use crate::utils::constants::{
+    MIGRATION_BATCH, MIGRATION_MIN_SPAWNS, MIGRATION_MIN_TOWERS, MIGRATION_STORE_LEFT,
+};
+
+/// Progress of moving a base built before its plan onto the plan
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Migration {
+    pub started: u32,
+    /// misplaced structures at the last step
+    pub misplaced: usize,
+    pub removed: usize,
+}
+
+impl Migration {
+    pub const fn new(started: u32) -> Self {
+        Self { started, misplaced: 0, removed: 0 }
+    }
+}
+
+impl Display for Migration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "started: {}, removed: {}, misplaced: {}",
+            self.started, self.removed, self.misplaced
+        )
+    }
+}
+
+/// What the next migration step may do with the built structures
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Removals {
+    /// indexes of the structures to remove now
+    pub remove: Vec<usize>,
+    /// built structures the plan has no place for
+    pub misplaced: usize,
+    /// the misplaced storage keeps resources to be moved out first
+    pub drain_storage: bool,
+    /// the misplaced terminal keeps resources to be moved out first
+    pub drain_terminal: bool,
+}
+
+/// Picks the misplaced structures that can go now: the minimum spawns, towers and half of the
+/// extensions stay unless they hold the place of the planned ones at the structure limit, the
+/// storage and the terminal go empty, a critical structure goes one a step
+pub fn removals(
+    built: &[(RoomXY, StructureType)],
+    plan: &RoomPlan,
+    lvl: u8,
+    storage_used: u32,
+    terminal_used: u32,
+) -> Removals {
+    let planned: HashSet<(RoomXY, StructureType)> = plan
+        .cells()
+        .filter(|cell| cell.r_lvl.is_none_or(|remove_lvl| remove_lvl > lvl))
+        .filter_map(|cell| StructureType::try_from(cell.structure).ok().map(|st| (cell.xy, st)))
+        .collect();
+    let count = |structure: StructureType| built.iter().filter(|(_, st)| *st == structure).count();
+    let keep = |structure: StructureType| match structure {
+        StructureType::Spawn => MIGRATION_MIN_SPAWNS,
+        StructureType::Tower => MIGRATION_MIN_TOWERS,
+        StructureType::Extension => {
+            (StructureType::Extension.controller_structures(u32::from(lvl)) / 2) as usize
+        }
+        _ => 0,
+    };
+    // the planned structure can't be built before a misplaced one goes
+    let blocked = |structure: StructureType| {
+        count(structure) >= structure.controller_structures(u32::from(lvl)) as usize
+            && plan.cells().any(|cell| {
+                cell.b_lvl <= lvl
+                    && cell.r_lvl.is_none_or(|remove_lvl| remove_lvl > lvl)
+                    && StructureType::try_from(cell.structure).ok() == Some(structure)
+                    && !built.contains(&(cell.xy, structure))
+            })
+    };
+
+    let mut result = Removals::default();
+    let mut left: Vec<(StructureType, usize)> = Vec::new();
+    for (index, (xy, structure)) in built.iter().enumerate() {
+        if planned.contains(&(*xy, *structure)) {
+            continue;
+        }
+        result.misplaced += 1;
+        if result.remove.len() >= MIGRATION_BATCH {
+            continue;
+        }
+
+        let critical = matches!(
+            structure,
+            StructureType::Spawn
+                | StructureType::Tower
+                | StructureType::Storage
+                | StructureType::Terminal
+                | StructureType::Rampart
+        );
+        let removed = left.iter().find(|(st, _)| st == structure).map_or(0, |(_, n)| *n);
+        if critical && removed > 0 {
+            continue;
+        }
+        if count(*structure) - removed <= keep(*structure) && !blocked(*structure) {
+            continue;
+        }
+        // the perimeter holds until the planned ramparts stand
+        if *structure == StructureType::Rampart
+            && planned.iter().any(|(xy, st)| {
+                *st == StructureType::Rampart && !built.contains(&(*xy, StructureType::Rampart))
+            })
+        {
+            continue;
+        }
+        if *structure == StructureType::Storage && storage_used > MIGRATION_STORE_LEFT {
+            result.drain_storage = true;
+            continue;
+        }
+        if *structure == StructureType::Terminal && terminal_used > MIGRATION_STORE_LEFT {
+            result.drain_terminal = true;
+            continue;
+        }
+
+        match left.iter_mut().find(|(st, _)| st == structure) {
+            Some((_, n)) => *n += 1,
+            None => left.push((*structure, 1)),
+        }
+        result.remove.push(index);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use screeps::{RoomXY, StructureType};
+
+    use crate::rooms::state::constructions::migration::removals;
+    use crate::rooms::state::constructions::{PlannedCell, RoomPlan, RoomStructure};
+
+    #[test]
+    fn migration_removals_test() {
+        let xy = |x, y| RoomXY::checked_new(x, y).unwrap();
+        let plan = RoomPlan::new(HashSet::from([
+            PlannedCell::new(xy(20, 20), RoomStructure::Spawn, 1, None),
+            PlannedCell::new(xy(22, 20), RoomStructure::Storage, 4, None),
+        ]));
+        let built = [
+            (xy(10, 10), StructureType::Spawn),
+            (xy(11, 10), StructureType::Road),
+            (xy(12, 10), StructureType::Storage),
+            (xy(13, 10), StructureType::Terminal),
+        ];
+
+        // the only spawn stays, the full storage and terminal are drained first
+        let result = removals(&built, &plan, 7, 100_000, 20_000);
+        assert_eq!(result.remove, vec![1]);
+        assert_eq!(result.misplaced, 4);
+        assert!(result.drain_storage);
+        assert!(result.drain_terminal);
+
+        // the empty terminal can go
+        let result = removals(&built, &plan, 7, 100_000, 0);
+        assert_eq!(result.remove, vec![1, 3]);
+        assert!(!result.drain_terminal);
+
+        // a second spawn can't be built, the only one makes room for the planned one
+        let result = removals(&built, &plan, 6, 100_000, 0);
+        assert_eq!(result.remove, vec![0, 1, 3]);
+
+        // the planned spawn stands, the old one and the empty storage can go
+        let built = [
+            (xy(10, 10), StructureType::Spawn),
+            (xy(20, 20), StructureType::Spawn),
+            (xy(12, 10), StructureType::Storage),
+        ];
+        let result = removals(&built, &plan, 7, 0, 0);
+        assert_eq!(result.remove, vec![0, 2]);
+        assert!(!result.drain_storage);
+    }
+}
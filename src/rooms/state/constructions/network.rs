@@ -0,0 +1,380 @@
+use std::collections::{HashMap, HashSet};
+use std::iter::{once, successors};
+
+use itertools::Itertools;
+use log::debug;
+use screeps::pathfinder::SearchGoal;
+use screeps::{
+    CARRY_CAPACITY, CONSTRUCTION_COST_ROAD_SWAMP_RATIO, CONSTRUCTION_COST_ROAD_WALL_RATIO,
+    ENERGY_REGEN_TIME, Position, REPAIR_COST, ROAD_DECAY_AMOUNT, ROAD_DECAY_TIME, ROAD_WEAROUT,
+    RoomName, RoomXY, SOURCE_ENERGY_CAPACITY, Terrain, game,
+};
+
+use super::xy_util::exit_distance;
+use super::{PlannedCell, RoomPart, RoomPlan, RoomPlannerError, RoomStructure};
+use crate::commons::is_cpu_on_low;
+use crate::movement::{callback::construction_multi_rooms, find_many};
+use crate::utils::constants::{EDGE_LINK_HUB_RANGE, EDGE_LINK_RANGE};
+
+// a hauler has a move part for two carry parts and walks the road loaded and back empty
+const WEAR_PER_CARRY: f64 = 3.;
+const MINERAL_TRAFFIC: f64 = 2.;
+
+/// A place the haulers take from and the energy a tick they carry from it to the hub
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Target {
+    pub pos: Position,
+    pub farm: RoomName,
+    pub traffic: f64,
+}
+
+impl Target {
+    pub fn source(pos: Position) -> Self {
+        let traffic = f64::from(SOURCE_ENERGY_CAPACITY) / f64::from(ENERGY_REGEN_TIME);
+        Self { pos, farm: pos.room_name(), traffic }
+    }
+
+    pub fn mineral(pos: Position) -> Self {
+        Self { pos, farm: pos.room_name(), traffic: MINERAL_TRAFFIC }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Road {
+    /// the next tile towards the hub, none next to the hub
+    next: Option<Position>,
+    /// energy a tick carried over the tile by farm
+    traffic: HashMap<RoomName, f64>,
+}
+
+impl Road {
+    fn total(&self) -> f64 {
+        self.traffic.values().sum()
+    }
+}
+
+/// One road tree from every farm to the hub, the hauling routes merge where they meet
+#[derive(Debug, Clone, Default)]
+pub struct RoadNetwork {
+    roads: HashMap<Position, Road>,
+    containers: Vec<Position>,
+}
+
+impl RoadNetwork {
+    /// Routes the busiest targets first, so the quieter ones join their roads. A route starts
+    /// with the container and ends next to the hub or on a road of the network
+    pub fn build(
+        targets: &[Target],
+        mut route: impl FnMut(Position, &Self) -> Result<Vec<Position>, RoomPlannerError>,
+    ) -> Result<Self, RoomPlannerError> {
+        let mut network = Self::default();
+        for target in targets.iter().sorted_by(|a, b| b.traffic.total_cmp(&a.traffic)) {
+            let path = route(target.pos, &network)?;
+            network.join(*target, &path)?;
+        }
+        Ok(network)
+    }
+
+    fn join(&mut self, target: Target, path: &[Position]) -> Result<(), RoomPlannerError> {
+        let (container, path) = path.split_first().ok_or(RoomPlannerError::RoadPlanFailure)?;
+        self.containers.push(*container);
+
+        let new: Vec<Position> =
+            path.iter().take_while(|pos| !self.roads.contains_key(pos)).copied().collect();
+        let joint = path.get(new.len()).copied();
+        for (i, pos) in new.iter().enumerate() {
+            let next = new.get(i + 1).copied().or(joint);
+            self.roads.insert(*pos, Road { next, traffic: HashMap::new() });
+        }
+
+        let mut step = new.first().copied().or(joint);
+        while let Some(pos) = step {
+            let road = self.roads.get_mut(&pos).ok_or(RoomPlannerError::RoadConnectionFailure)?;
+            *road.traffic.entry(target.farm).or_default() += target.traffic;
+            step = road.next;
+        }
+        Ok(())
+    }
+
+    /// Drops the roads too quiet to pay for their repairs. The traffic only grows towards the
+    /// hub, so the pruned tiles are the ends of the branches
+    pub fn prune(&mut self, min_traffic: f64) {
+        self.roads.retain(|_, road| road.total() >= min_traffic);
+    }
+
+    /// Repair energy a tick by farm: the decay of the tile and the wear of the haulers, the
+    /// shared tiles are split by the traffic
+    pub fn upkeep(&self, mut terrain: impl FnMut(Position) -> Terrain) -> HashMap<RoomName, f64> {
+        let mut result = HashMap::new();
+        for (pos, road) in &self.roads {
+            let ratio = match terrain(*pos) {
+                Terrain::Plain => 1,
+                Terrain::Swamp => CONSTRUCTION_COST_ROAD_SWAMP_RATIO,
+                Terrain::Wall => CONSTRUCTION_COST_ROAD_WALL_RATIO,
+            };
+            let decay = f64::from(ROAD_DECAY_AMOUNT * ratio) / f64::from(ROAD_DECAY_TIME);
+            let total = road.total();
+            let wear = total / f64::from(CARRY_CAPACITY) * WEAR_PER_CARRY * f64::from(ROAD_WEAROUT);
+            let energy = (decay + wear) * f64::from(REPAIR_COST);
+            for (farm, traffic) in &road.traffic {
+                *result.entry(*farm).or_default() += energy * traffic / total;
+            }
+        }
+        result
+    }
+
+    /// The repair energy with the terrain of the game rooms
+    pub fn game_upkeep(&self) -> HashMap<RoomName, f64> {
+        let mut terrains = HashMap::new();
+        self.upkeep(|pos| {
+            terrains
+                .entry(pos.room_name())
+                .or_insert_with(|| game::map::get_room_terrain(pos.room_name()))
+                .as_ref()
+                .map_or(Terrain::Plain, |terrain| terrain.get(pos.x().u8(), pos.y().u8()))
+        })
+    }
+
+    // tiles to the hub along the network
+    fn distance(&self, pos: Position) -> usize {
+        successors(Some(pos), |pos| self.roads.get(pos).and_then(|road| road.next)).count()
+    }
+
+    /// A free tile for the edge link next to the busiest road where the farm routes enter the
+    /// room of the hub, close to the room edge and away from the hub
+    pub fn edge_link(&self, hub: Position, mut free: impl FnMut(RoomXY) -> bool) -> Option<RoomXY> {
+        let home = hub.room_name();
+        let entry = self
+            .roads
+            .iter()
+            .filter(|(pos, _)| pos.room_name() != home)
+            .filter_map(|(_, road)| road.next.filter(|next| next.room_name() == home))
+            .filter_map(|entry| self.roads.get(&entry).map(|road| (entry, road.total())))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entry, _)| entry)?;
+        let road = successors(Some(entry), |pos| self.roads.get(pos).and_then(|road| road.next))
+            .take_while(|pos| pos.room_name() == home)
+            .find(|pos| exit_distance(pos.xy()) >= 2)
+            .filter(|pos| pos.get_range_to(hub) > EDGE_LINK_HUB_RANGE)?;
+
+        road.xy()
+            .neighbors()
+            .into_iter()
+            .filter(|xy| {
+                (2..=EDGE_LINK_RANGE).contains(&exit_distance(*xy))
+                    && !self.roads.contains_key(&Position::new(xy.x, xy.y, home))
+                    && free(*xy)
+            })
+            .min_by_key(|xy| (exit_distance(*xy), xy.y, xy.x))
+    }
+
+    /// Road and container cells by room. The routed farms get their whole plan from the network,
+    /// the other rooms keep theirs with the tiles it doesn't have yet
+    pub fn plans(
+        &self,
+        existed: &HashMap<RoomName, &RoomPlan>,
+        routed: &HashSet<RoomName>,
+    ) -> HashMap<RoomName, RoomPlan> {
+        let taken: HashSet<Position> = existed
+            .iter()
+            .filter(|(name, _)| !routed.contains(name))
+            .flat_map(|(name, plan)| {
+                plan.cells().map(|cell| Position::new(cell.xy.x, cell.xy.y, *name))
+            })
+            .collect();
+
+        let roads =
+            self.roads.keys().map(|pos| (*pos, RoomStructure::Road(self.distance(*pos)))).chain(
+                self.containers.iter().map(|pos| (*pos, RoomStructure::Container(RoomPart::Red))),
+            );
+
+        let mut plans: HashMap<RoomName, RoomPlan> =
+            routed.iter().map(|name| (*name, RoomPlan::default())).collect();
+        for (pos, structure) in roads.filter(|(pos, _)| !taken.contains(pos)) {
+            plans
+                .entry(pos.room_name())
+                .or_insert_with(|| {
+                    existed
+                        .get(&pos.room_name())
+                        .map_or_else(RoomPlan::default, |plan| (*plan).clone())
+                })
+                .add_cell(PlannedCell::new(pos.xy(), structure, 0, None));
+        }
+        plans
+    }
+}
+
+// the container a plan has next to the target, the route of a planned farm starts there
+fn planned_container(target: Position, existed: &HashMap<RoomName, &RoomPlan>) -> Option<Position> {
+    existed
+        .get(&target.room_name())?
+        .containers()
+        .into_iter()
+        .map(|xy| Position::new(xy.x, xy.y, target.room_name()))
+        .filter(|pos| pos.is_near_to(target))
+        .min_by_key(|pos| (pos.y(), pos.x()))
+}
+
+/// Plans the network with the pathfinder, the roads already planned are cheap to walk so the
+/// routes merge with them
+pub fn plan(
+    hub: Position,
+    targets: &[Target],
+    existed: &HashMap<RoomName, &RoomPlan>,
+) -> Result<RoadNetwork, RoomPlannerError> {
+    let structures: HashMap<RoomName, Vec<RoomXY>> =
+        existed.iter().map(|(name, plan)| (*name, plan.unwalkable_structures())).collect();
+    let planned: HashSet<Position> =
+        existed.iter().flat_map(|(name, plan)| plan.costed_roads(*name).into_keys()).collect();
+
+    RoadNetwork::build(targets, |from, network| {
+        if is_cpu_on_low() {
+            return Err(RoomPlannerError::LowCPU);
+        }
+
+        let mut blocked = structures.clone();
+        for container in &network.containers {
+            blocked.entry(container.room_name()).or_default().push(container.xy());
+        }
+        let roads: HashSet<Position> =
+            planned.iter().chain(network.roads.keys()).copied().collect();
+        let goals = once(SearchGoal::new(hub, 1))
+            .chain(network.roads.keys().map(|pos| SearchGoal::new(*pos, 0)));
+
+        let start = planned_container(from, existed);
+        let search =
+            find_many(start.unwrap_or(from), goals, construction_multi_rooms(&blocked, &roads));
+        if search.incomplete() || search.path().is_empty() {
+            debug!("road from {from} to the network is incomplete");
+            return Err(RoomPlannerError::RoadPlanFailure);
+        }
+        Ok(start.into_iter().chain(search.path()).filter(|pos| !pos.is_room_edge()).collect())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use screeps::{Position, RoomCoordinate, RoomName, Terrain};
+
+    use crate::rooms::state::constructions::network::{RoadNetwork, Target, planned_container};
+    use crate::rooms::state::constructions::{PlannedCell, RoomPart, RoomPlan, RoomStructure};
+
+    #[test]
+    fn road_network_test() {
+        let room = |name: &str| name.parse::<RoomName>().unwrap();
+        let pos = |x: u8, y: u8| {
+            Position::new(
+                RoomCoordinate::new(x).unwrap(),
+                RoomCoordinate::new(y).unwrap(),
+                room("W1N1"),
+            )
+        };
+        let line = |xys: &[(u8, u8)]| xys.iter().map(|(x, y)| pos(*x, *y)).collect::<Vec<_>>();
+        // the hub is at (10, 10), the routes start with the container
+        let routes: HashMap<Position, Vec<Position>> = HashMap::from([
+            (pos(30, 10), (11..=29).rev().map(|x| pos(x, 10)).collect()),
+            (pos(30, 14), line(&[(29, 13), (28, 12), (27, 11), (26, 10), (25, 10)])),
+            (pos(30, 17), line(&[(29, 16), (28, 15), (28, 14), (28, 13), (28, 12), (27, 11)])),
+        ]);
+        let targets = [
+            Target { farm: room("W2N1"), ..Target::source(pos(30, 10)) },
+            Target { farm: room("W3N1"), ..Target::source(pos(30, 14)) },
+            Target { farm: room("W3N1"), ..Target::mineral(pos(30, 17)) },
+        ];
+
+        let mut network = RoadNetwork::build(&targets, |from, network| {
+            let mut path = routes[&from].clone();
+            // the pathfinder stops on the first road of the network
+            if let Some(joint) = path.iter().skip(1).position(|pos| network.roads.contains_key(pos))
+            {
+                path.truncate(joint + 2);
+            }
+            Ok(path)
+        })
+        .unwrap();
+
+        assert_eq!(network.roads.len(), 18 + 2 + 3);
+        assert_eq!(network.distance(pos(28, 10)), 18);
+        assert_eq!(network.distance(pos(28, 15)), 4 + 1 + 16);
+        assert!((network.roads[&pos(11, 10)].total() - 22.).abs() < 1e-9);
+
+        // the mineral branch is too quiet for a road
+        network.prune(5.);
+        assert_eq!(network.roads.len(), 18 + 2);
+        let upkeep = network.upkeep(|_| Terrain::Plain);
+        // the mineral haulers still pay their share of the main road
+        assert!(upkeep[&room("W3N1")] > upkeep[&room("W2N1")]);
+        assert!(upkeep[&room("W2N1")] > 0.);
+    }
+
+    #[test]
+    fn edge_link_test() {
+        let pos = |x: u8, y: u8, name: &str| {
+            Position::new(
+                RoomCoordinate::new(x).unwrap(),
+                RoomCoordinate::new(y).unwrap(),
+                name.parse::<RoomName>().unwrap(),
+            )
+        };
+        let hub = pos(25, 10, "W1N1");
+        let container = pos(40, 10, "W2N1");
+        // the route crosses from the farm in the west, the room edge tiles are no roads
+        let path: Vec<Position> = [container]
+            .into_iter()
+            .chain((41..=48).map(|x| pos(x, 10, "W2N1")))
+            .chain((1..=24).map(|x| pos(x, 10, "W1N1")))
+            .collect();
+        let network =
+            RoadNetwork::build(&[Target::source(container)], |_, _| Ok(path.clone())).unwrap();
+
+        // next to the first road off the exit tiles, the free tile closest to the edge
+        assert_eq!(network.edge_link(hub, |_| true), Some(pos(2, 9, "W1N1").xy()));
+        assert_eq!(network.edge_link(hub, |xy| xy.y.u8() != 9), Some(pos(2, 11, "W1N1").xy()));
+        // the hub is close enough to the edge
+        assert_eq!(network.edge_link(pos(10, 10, "W1N1"), |_| true), None);
+    }
+
+    #[test]
+    fn planned_farm_test() {
+        let room = |name: &str| name.parse::<RoomName>().unwrap();
+        let pos = |x: u8, y: u8, name: &str| {
+            Position::new(
+                RoomCoordinate::new(x).unwrap(),
+                RoomCoordinate::new(y).unwrap(),
+                room(name),
+            )
+        };
+        let cell = |pos: Position, structure| PlannedCell::new(pos.xy(), structure, 0, None);
+        let home =
+            RoomPlan::new(HashSet::from([cell(pos(20, 10, "W1N1"), RoomStructure::Road(1))]));
+        // the farm was planned before, one of its roads went out of use since
+        let farm = RoomPlan::new(HashSet::from([
+            cell(pos(29, 10, "W2N1"), RoomStructure::Container(RoomPart::Red)),
+            cell(pos(28, 10, "W2N1"), RoomStructure::Road(4)),
+            cell(pos(28, 20, "W2N1"), RoomStructure::Road(5)),
+        ]));
+        let existed = HashMap::from([(room("W1N1"), &home), (room("W2N1"), &farm)]);
+
+        let source = pos(30, 10, "W2N1");
+        let container = planned_container(source, &existed);
+        assert_eq!(container, Some(pos(29, 10, "W2N1")));
+        let path: Vec<Position> = container
+            .into_iter()
+            .chain([pos(28, 10, "W2N1"), pos(20, 10, "W1N1"), pos(19, 10, "W1N1")])
+            .collect();
+        let network =
+            RoadNetwork::build(&[Target::source(source)], |_, _| Ok(path.clone())).unwrap();
+        let plans = network.plans(&existed, &HashSet::from([room("W2N1")]));
+
+        // the farm keeps its container and drops the road out of use
+        assert_eq!(plans[&room("W2N1")].containers(), HashSet::from([pos(29, 10, "W2N1").xy()]));
+        assert_eq!(plans[&room("W2N1")].roads(), HashSet::from([pos(28, 10, "W2N1").xy()]));
+        // the home plan only grows
+        assert_eq!(
+            plans[&room("W1N1")].roads(),
+            HashSet::from([pos(20, 10, "W1N1").xy(), pos(19, 10, "W1N1").xy()])
+        );
+    }
+}
@@ -4,12 +4,15 @@ use std::iter::once;
 
 use itertools::Itertools;
 use screeps::{
-    Direction, HasPosition, OutOfBoundsError, Position, RoomCoordinate, RoomPosition, RoomXY, Step,
+    Direction, HasPosition, OutOfBoundsError, Position, RoomCoordinate, RoomName, RoomPosition,
+    RoomXY, Step, game,
 };
+use serde::{Deserialize, Serialize};
 
 use self::central::central_square;
 use self::polygon::{smallest_perimeter, walk_border};
 use self::roads::{Dash, RoadNet, Turn, Variable, best_net};
+pub use self::score::PlanScore;
 use self::spawns::spawn_space;
 use super::xy_util::{outside_rect, square_sides};
 use super::{
@@ -26,62 +29,39 @@ mod observer;
 mod polygon;
 mod ramparts;
 mod roads;
+mod score;
 mod sources;
 mod spawns;
+mod stamps;
 mod towers;
 
 type Route = (RoomXY, Vec<Step>);
 
+/// Positions of the objects a base is planned around
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BaseSite {
+    pub ctrl: RoomXY,
+    pub sources: Vec<RoomXY>,
+    pub mineral: RoomXY,
+}
+
 impl Claimed {
     pub fn generate_plan(
         &self,
         rect: Option<OuterRectangle>,
     ) -> Result<RoomPlan, RoomPlannerError> {
-        let sources: Vec<RoomXY> = self.sources.iter().map(|source| source.pos().xy()).collect();
-
-        let ctrl = self.controller.pos().xy();
+        let site = BaseSite {
+            ctrl: self.controller.pos().xy(),
+            sources: self.sources.iter().map(|source| source.pos().xy()).collect(),
+            mineral: self.mineral.pos().xy(),
+        };
 
         let initial_spawn = self.spawns.first().map(|spawn| spawn.pos().xy());
 
-        let mineral = self.mineral.pos().xy();
-
         let terrain = self.room.get_terrain();
         let walls = build_wall_bitmap(&|a, b| terrain.get(a, b));
 
-        let perimeter = match rect {
-            Some(r) => Perimeter::new(r, &walls),
-            None => smallest_perimeter(initial_spawn, &sources, &walls)?,
-        };
-
-        let grid = room_grid(&perimeter, &walls)?;
-
-        let RoadNet { config, roads, mut squares } =
-            best_net(perimeter.rectangle(), initial_spawn, &grid)?;
-
-        // todo guide cell could be an exit from farm to a base
-        // the idea is to turn the base in direction to the guide cell!
-        let guide = guide_cell(ctrl, &sources, perimeter.rectangle())?;
-
-        let central = central_square(guide, initial_spawn, &roads, &mut squares, &walls)?;
-        let spawns = spawn_space(&central, initial_spawn, &squares, &walls)?;
-
-        let mut plan = RoomPlan::new(central.plan()?);
-        let storage = plan
-            .storage()
-            .map(|xy| Position::new(xy.x, xy.y, self.get_name()))
-            .ok_or(RoomPlannerError::StorageNotFound)?;
-
-        spawns::plan(&spawns, &mut plan);
-        ramparts::plan(&perimeter, &mut plan);
-        towers::plan(&perimeter, &grid, &mut plan);
-        config.plan(perimeter.rectangle(), &grid, &mut plan);
-        sources::plan(storage, &sources, &grid, &mut plan)?;
-        controller::plan(storage, ctrl, &grid, &mut plan)?;
-        mineral::plan(storage, mineral, &grid, &mut plan)?;
-        extensions::plan(storage.xy(), &grid, &mut plan);
-        observer::plan(storage.xy(), &grid, &mut plan);
-
-        Ok(plan)
+        plan_base(self.get_name(), &site, initial_spawn, rect, &walls)
     }
 
     // pub fn generate_plan(&self) -> Result<RoomPlan, RoomPlannerError> {
@@ -125,6 +105,91 @@ impl Claimed {
     // }
 }
 
+/// Plans a base from the terrain and the object positions only, so a room can be ranked
+/// before it is claimed
+pub fn plan_site(
+    room_name: RoomName,
+    site: &BaseSite,
+) -> Result<(RoomPlan, PlanScore), RoomPlannerError> {
+    let terrain =
+        game::map::get_room_terrain(room_name).ok_or(RoomPlannerError::UnreachableRoom)?;
+    let walls = build_wall_bitmap(&|a, b| terrain.get(a, b));
+
+    let plan = plan_base(room_name, site, None, None, &walls)?;
+    let score = score::score(&plan, site, &walls);
+    Ok((plan, score))
+}
+
+/// The central square base, or the stamps when the room is too cramped for the square
+fn plan_base(
+    room_name: RoomName,
+    site: &BaseSite,
+    initial_spawn: Option<RoomXY>,
+    rect: Option<OuterRectangle>,
+    walls: &Walls,
+) -> Result<RoomPlan, RoomPlannerError> {
+    plan_square(room_name, site, initial_spawn, rect, walls).or_else(|err| match err {
+        RoomPlannerError::CentralSquareNotFound
+        | RoomPlannerError::CentralSquarePlacementError
+        | RoomPlannerError::SpawnPlaceNotFound
+        | RoomPlannerError::GridCreationFailed
+        | RoomPlannerError::PerimeterCreationFailed
+        | RoomPlannerError::RoadPlanFailure
+        | RoomPlannerError::StructurePlacementFailure => {
+            stamps::plan(room_name, site, initial_spawn, walls)
+        }
+        err => Err(err),
+    })
+}
+
+fn plan_square(
+    room_name: RoomName,
+    site: &BaseSite,
+    initial_spawn: Option<RoomXY>,
+    rect: Option<OuterRectangle>,
+    walls: &Walls,
+) -> Result<RoomPlan, RoomPlannerError> {
+    let BaseSite { ctrl, sources, mineral } = site;
+
+    let perimeter = match rect {
+        Some(r) => Perimeter::new(r, walls),
+        None => smallest_perimeter(initial_spawn, sources, walls)?,
+    };
+
+    let grid = room_grid(&perimeter, walls)?;
+
+    let RoadNet { config, roads, mut squares } =
+        best_net(perimeter.rectangle(), initial_spawn, &grid)?;
+
+    // todo guide cell could be an exit from farm to a base
+    // the idea is to turn the base in direction to the guide cell!
+    let guide = guide_cell(*ctrl, sources, perimeter.rectangle())?;
+
+    let central = central_square(guide, initial_spawn, &roads, &mut squares, walls)?;
+    let spawns = spawn_space(&central, initial_spawn, &squares, walls)?;
+
+    let mut plan = RoomPlan::new(central.plan()?);
+    let storage = plan
+        .storage()
+        .map(|xy| Position::new(xy.x, xy.y, room_name))
+        .ok_or(RoomPlannerError::StorageNotFound)?;
+
+    spawns::plan(&spawns, &mut plan);
+    let cut = ramparts::min_cut(&ramparts::protected_cells(&grid, *ctrl, sources), walls)?;
+    ramparts::plan(&cut, &mut plan);
+    // the towers and the roads follow the cut, not the rectangle it grew from
+    let cut_grid = cut.grid(walls);
+    towers::plan(&cut, &cut_grid, &mut plan);
+    config.plan(perimeter.rectangle(), &cut_grid, &mut plan);
+    sources::plan(storage, sources, &grid, &mut plan)?;
+    controller::plan(storage, *ctrl, &grid, &mut plan)?;
+    mineral::plan(storage, *mineral, &grid, &mut plan)?;
+    extensions::plan(storage.xy(), &grid, &mut plan);
+    observer::plan(storage.xy(), &grid, &mut plan);
+
+    Ok(plan)
+}
+
 fn room_grid(
     perimeter: &Perimeter,
     walls: &Walls,
@@ -304,10 +369,6 @@ impl Perimeter {
         &self.ramparts
     }
 
-    fn near_rampart(&self, xy: RoomXY) -> bool {
-        self.ramparts.iter().any(|rampart| rampart.get_range_to(xy) == 1)
-    }
-
     fn full_path(&self) -> impl Iterator<Item = &RoomXY> {
         self.walls.iter().chain(self.ramparts.iter())
     }
@@ -408,6 +469,8 @@ impl Square {
 mod tests {
     use screeps::RoomXY;
 
+    use crate::rooms::state::constructions::owned::ramparts::{min_cut, protected_cells};
+    use crate::rooms::state::constructions::owned::room_grid;
     use crate::rooms::state::constructions::{owned::smallest_perimeter, tests::WALLS};
 
     #[test]
@@ -419,6 +482,23 @@ mod tests {
         assert_eq!(perimeter.rectangle(), (27, 8, 45, 26));
     }
 
+    #[test]
+    fn rampart_cut_test() {
+        let sources = sources();
+        let ctrl = RoomXY::checked_new(20, 20).unwrap();
+
+        let perimeter = smallest_perimeter(None, &sources, &WALLS).unwrap();
+        let grid = room_grid(&perimeter, &WALLS).unwrap();
+        let cut = min_cut(&protected_cells(&grid, ctrl, &sources), &WALLS).unwrap();
+
+        assert!(cut.ramparts().len() <= perimeter.ramparts().len());
+        assert!(sources.iter().all(|source| source.neighbors().iter().all(|xy| {
+            WALLS[usize::from(xy.y.u8())][usize::from(xy.x.u8())]
+                || cut.is_inside(*xy)
+                || cut.ramparts().contains(xy)
+        })));
+    }
+
     pub fn sources() -> Vec<RoomXY> {
         unsafe { vec![RoomXY::unchecked_new(8, 23), RoomXY::unchecked_new(30, 15)] }
     }
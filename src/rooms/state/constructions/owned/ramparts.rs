@@ -1,11 +1,295 @@
-use super::Perimeter;
-use crate::rooms::state::constructions::{PlannedCell, RoomPlan, RoomStructure};
+use std::collections::{HashMap, HashSet, VecDeque};
 
-pub fn plan(perimeter: &Perimeter, room_plan: &mut RoomPlan) {
+use screeps::ROOM_SIZE;
+use screeps::local::RoomXY;
+
+use crate::rooms::state::constructions::{
+    PlannedCell, RoomPart, RoomPlan, RoomPlannerError, RoomStructure, Walls, is_wall,
+};
+
+// rampart cost by the range to the exit zone, hostiles outside the room shoot the closest ones
+const EXIT_COSTS: [u32; 3] = [0, 10, 4];
+const RAMPART_COST: u32 = 1;
+const INFINITE: u32 = u32::MAX / 4;
+
+const ROOM: usize = ROOM_SIZE as usize;
+const NODES: usize = ROOM * ROOM * 2 + 2;
+const SOURCE: usize = NODES - 2;
+const SINK: usize = NODES - 1;
+
+/// The minimal set of ramparts that separates the protected cells from the exits
+#[derive(Debug, Clone, Default)]
+pub struct RampartCut {
+    ramparts: Vec<RoomXY>,
+    inside: HashSet<RoomXY>,
+}
+
+impl RampartCut {
+    pub fn ramparts(&self) -> &[RoomXY] {
+        &self.ramparts
+    }
+
+    pub fn is_inside(&self, xy: RoomXY) -> bool {
+        self.inside.contains(&xy)
+    }
+
+    /// Room parts by the range to the cut, as the perimeter grid has them
+    pub fn grid(&self, walls: &Walls) -> HashMap<RoomXY, RoomPart> {
+        let mut grid = HashMap::new();
+        for y in 0..ROOM_SIZE {
+            for x in 0..ROOM_SIZE {
+                let xy = unsafe { RoomXY::unchecked_new(x, y) };
+                let part = if is_wall(walls, xy) {
+                    RoomPart::Wall
+                } else if xy.is_room_edge() {
+                    RoomPart::Exit
+                } else if self.ramparts.contains(&xy) {
+                    RoomPart::Protected
+                } else if self.is_inside(xy) {
+                    match self.ramparts.iter().map(|rampart| rampart.get_range_to(xy)).min() {
+                        Some(1) => RoomPart::Orange,
+                        Some(2) => RoomPart::Yellow,
+                        _ => RoomPart::Green,
+                    }
+                } else {
+                    RoomPart::Red
+                };
+                grid.insert(xy, part);
+            }
+        }
+        grid
+    }
+}
+
+pub fn plan(cut: &RampartCut, room_plan: &mut RoomPlan) {
     room_plan.add_cells(
-        perimeter
-            .ramparts()
+        cut.ramparts()
             .iter()
             .map(|xy| PlannedCell::new(*xy, RoomStructure::Rampart(true), 4, None)),
     );
 }
+
+/// The base stamp inside the perimeter, the controller and the sources with their neighbours
+pub(super) fn protected_cells(
+    grid: &HashMap<RoomXY, RoomPart>,
+    ctrl: RoomXY,
+    sources: &[RoomXY],
+) -> Vec<RoomXY> {
+    grid.iter()
+        .filter(|(_, part)| part.is_internal() && !matches!(part, RoomPart::Protected))
+        .map(|(xy, _)| *xy)
+        .chain(sources.iter().chain([&ctrl]).flat_map(|xy| xy.neighbors()))
+        .collect()
+}
+
+/// Min-cut over the room tiles: every walkable tile is a node with the rampart cost as the
+/// capacity, the protected cells are tied to the source and the exit zone to the sink
+pub(super) fn min_cut(protected: &[RoomXY], walls: &Walls) -> Result<RampartCut, RoomPlannerError> {
+    let exit_range = exit_ranges(walls);
+    let mut network = Network::new();
+
+    for y in 0..ROOM_SIZE {
+        for x in 0..ROOM_SIZE {
+            let xy = unsafe { RoomXY::unchecked_new(x, y) };
+            if is_wall(walls, xy) {
+                continue;
+            }
+            let node = index(xy);
+            let cost = match exit_range[node] {
+                // the exit zone can't hold structures
+                Some(0) => {
+                    network.edge(node * 2 + 1, SINK, INFINITE);
+                    INFINITE
+                }
+                Some(range) => RAMPART_COST + EXIT_COSTS.get(range).copied().unwrap_or_default(),
+                None => RAMPART_COST,
+            };
+            network.edge(node * 2, node * 2 + 1, cost);
+            for neighbor in xy.neighbors().into_iter().filter(|n| !is_wall(walls, *n)) {
+                network.edge(node * 2 + 1, index(neighbor) * 2, INFINITE);
+            }
+        }
+    }
+
+    // a cell in the exit zone can't be covered, it stays outside
+    for xy in
+        protected.iter().filter(|xy| !is_wall(walls, **xy) && exit_range[index(**xy)] != Some(0))
+    {
+        network.edge(SOURCE, index(*xy) * 2, INFINITE);
+    }
+
+    if network.max_flow() >= INFINITE {
+        return Err(RoomPlannerError::RampartPlacementFailure);
+    }
+
+    let reachable = network.reachable();
+    let mut cut = RampartCut::default();
+    for y in 0..ROOM_SIZE {
+        for x in 0..ROOM_SIZE {
+            let xy = unsafe { RoomXY::unchecked_new(x, y) };
+            let node = index(xy);
+            if reachable[node * 2] && !reachable[node * 2 + 1] {
+                cut.ramparts.push(xy);
+            } else if reachable[node * 2] {
+                cut.inside.insert(xy);
+            }
+        }
+    }
+    Ok(cut)
+}
+
+fn index(xy: RoomXY) -> usize {
+    usize::from(xy.y.u8()) * ROOM + usize::from(xy.x.u8())
+}
+
+// range to the exit zone: exit tiles and the tiles next to them, None further than the costs
+fn exit_ranges(walls: &Walls) -> Vec<Option<usize>> {
+    let mut ranges = vec![None; ROOM * ROOM];
+    let mut queue = VecDeque::new();
+    for y in 0..ROOM_SIZE {
+        for x in 0..ROOM_SIZE {
+            let xy = unsafe { RoomXY::unchecked_new(x, y) };
+            if xy.is_room_edge() && !is_wall(walls, xy) {
+                for cell in xy.neighbors().into_iter().chain([xy]) {
+                    if !is_wall(walls, cell) && ranges[index(cell)].is_none() {
+                        ranges[index(cell)] = Some(0);
+                        queue.push_back(cell);
+                    }
+                }
+            }
+        }
+    }
+
+    while let Some(xy) = queue.pop_front() {
+        let range = ranges[index(xy)].unwrap_or_default() + 1;
+        if range >= EXIT_COSTS.len() {
+            continue;
+        }
+        for neighbor in xy.neighbors() {
+            if !is_wall(walls, neighbor) && ranges[index(neighbor)].is_none() {
+                ranges[index(neighbor)] = Some(range);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    ranges
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    to: usize,
+    capacity: u32,
+}
+
+// Dinic max flow, edges go in pairs with the reverse one right after
+struct Network {
+    edges: Vec<Edge>,
+    adjacent: Vec<Vec<usize>>,
+    level: Vec<Option<u32>>,
+    next: Vec<usize>,
+}
+
+impl Network {
+    fn new() -> Self {
+        Self {
+            edges: Vec::new(),
+            adjacent: vec![Vec::new(); NODES],
+            level: vec![None; NODES],
+            next: vec![0; NODES],
+        }
+    }
+
+    fn edge(&mut self, from: usize, to: usize, capacity: u32) {
+        self.adjacent[from].push(self.edges.len());
+        self.edges.push(Edge { to, capacity });
+        self.adjacent[to].push(self.edges.len());
+        self.edges.push(Edge { to: from, capacity: 0 });
+    }
+
+    fn max_flow(&mut self) -> u32 {
+        let mut flow = 0;
+        while self.levels() {
+            self.next.fill(0);
+            loop {
+                let pushed = self.push();
+                if pushed == 0 {
+                    break;
+                }
+                flow += pushed;
+                if flow >= INFINITE {
+                    return flow;
+                }
+            }
+        }
+        flow
+    }
+
+    fn levels(&mut self) -> bool {
+        self.level.fill(None);
+        self.level[SOURCE] = Some(0);
+        let mut queue = VecDeque::from([SOURCE]);
+        while let Some(node) = queue.pop_front() {
+            let level = self.level[node].unwrap_or_default();
+            for &edge in &self.adjacent[node] {
+                let Edge { to, capacity } = self.edges[edge];
+                if capacity > 0 && self.level[to].is_none() {
+                    self.level[to] = Some(level + 1);
+                    queue.push_back(to);
+                }
+            }
+        }
+        self.level[SINK].is_some()
+    }
+
+    // walks the level graph from the source without recursion, the path keeps the edges taken
+    fn push(&mut self) -> u32 {
+        let mut path: Vec<usize> = Vec::new();
+        let mut node = SOURCE;
+        loop {
+            if node == SINK {
+                let pushed =
+                    path.iter().map(|&edge| self.edges[edge].capacity).fold(INFINITE, u32::min);
+                for &edge in &path {
+                    self.edges[edge].capacity -= pushed;
+                    self.edges[edge ^ 1].capacity += pushed;
+                }
+                return pushed;
+            }
+            if let Some(&edge) = self.adjacent[node].get(self.next[node]) {
+                let Edge { to, capacity } = self.edges[edge];
+                let deeper =
+                    self.level[to].zip(self.level[node]).is_some_and(|(to, from)| to == from + 1);
+                if capacity > 0 && deeper {
+                    path.push(edge);
+                    node = to;
+                } else {
+                    self.next[node] += 1;
+                }
+            } else {
+                // a dead end, step back and skip the edge that led here
+                let Some(edge) = path.pop() else {
+                    return 0;
+                };
+                node = self.edges[edge ^ 1].to;
+                self.next[node] += 1;
+            }
+        }
+    }
+
+    // nodes on the source side of the residual network
+    fn reachable(&self) -> Vec<bool> {
+        let mut seen = vec![false; NODES];
+        seen[SOURCE] = true;
+        let mut queue = VecDeque::from([SOURCE]);
+        while let Some(node) = queue.pop_front() {
+            for &edge in &self.adjacent[node] {
+                let Edge { to, capacity } = self.edges[edge];
+                if capacity > 0 && !seen[to] {
+                    seen[to] = true;
+                    queue.push_back(to);
+                }
+            }
+        }
+        seen
+    }
+}
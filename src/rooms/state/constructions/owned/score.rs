@@ -0,0 +1,97 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt::{self, Display};
+
+use screeps::ROOM_SIZE;
+use screeps::local::RoomXY;
+use serde::{Deserialize, Serialize};
+
+use super::BaseSite;
+use crate::commons::tower_attack_power;
+use crate::rooms::state::constructions::{RoomPlan, Walls, is_wall};
+
+// what a rampart and a road tile cost against a point of the weakest tower damage
+const RAMPART_WEIGHT: i64 = 40;
+const ROAD_WEIGHT: i64 = 10;
+const UPGRADE_RANGE: u8 = 3;
+
+/// How good a base plan is: shorter defence and roads, stronger towers
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlanScore {
+    /// perimeter ramparts
+    pub perimeter: usize,
+    /// walking distance from the storage to the sources and the controller
+    pub roads: usize,
+    /// tower damage at the weakest perimeter rampart
+    pub coverage: u32,
+}
+
+impl PlanScore {
+    pub fn value(&self) -> i64 {
+        i64::from(self.coverage)
+            - RAMPART_WEIGHT * i64::try_from(self.perimeter).unwrap_or(i64::MAX / 2)
+            - ROAD_WEIGHT * i64::try_from(self.roads).unwrap_or(i64::MAX / 2)
+    }
+}
+
+impl Display for PlanScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "value: {}, perimeter: {}, roads: {}, coverage: {}",
+            self.value(),
+            self.perimeter,
+            self.roads,
+            self.coverage
+        )
+    }
+}
+
+pub fn score(plan: &RoomPlan, site: &BaseSite, walls: &Walls) -> PlanScore {
+    let perimeter = plan.perimeter();
+    let towers: Vec<RoomXY> = plan.get_towers().map(|cell| cell.xy).collect();
+    let coverage = perimeter
+        .iter()
+        .map(|rampart| {
+            towers.iter().map(|tower| tower_attack_power(tower.get_range_to(*rampart).into())).sum()
+        })
+        .min()
+        .unwrap_or_default();
+
+    let roads = plan.storage().map_or(0, |storage| {
+        let blocked: HashSet<RoomXY> = plan.unwalkable_structures().into_iter().collect();
+        let distances = distances(storage, &blocked, walls);
+        site.sources
+            .iter()
+            .map(|source| (*source, 1))
+            .chain([(site.ctrl, UPGRADE_RANGE)])
+            .filter_map(|(target, range)| {
+                distances
+                    .iter()
+                    .filter(|(xy, _)| xy.get_range_to(target) <= range)
+                    .map(|(_, distance)| *distance)
+                    .min()
+            })
+            .sum()
+    });
+
+    PlanScore { perimeter: perimeter.len(), roads, coverage }
+}
+
+// walking distance from the storage to every reachable tile
+fn distances(from: RoomXY, blocked: &HashSet<RoomXY>, walls: &Walls) -> Vec<(RoomXY, usize)> {
+    let mut seen = [[false; ROOM_SIZE as usize]; ROOM_SIZE as usize];
+    seen[usize::from(from.y.u8())][usize::from(from.x.u8())] = true;
+    let mut queue = VecDeque::from([(from, 0)]);
+    let mut result = Vec::new();
+    while let Some((xy, distance)) = queue.pop_front() {
+        result.push((xy, distance));
+        for neighbor in xy.neighbors() {
+            let cell = &mut seen[usize::from(neighbor.y.u8())][usize::from(neighbor.x.u8())];
+            if !*cell && !is_wall(walls, neighbor) && !blocked.contains(&neighbor) {
+                *cell = true;
+                queue.push_back((neighbor, distance + 1));
+            }
+        }
+    }
+    result
+}
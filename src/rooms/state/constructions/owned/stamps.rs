@@ -0,0 +1,506 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use itertools::Itertools;
+use screeps::local::RoomXY;
+use screeps::{Position, ROOM_SIZE, RoomName, StructureType};
+
+use super::{BaseSite, controller, mineral, ramparts, sources};
+use crate::rooms::state::constructions::{
+    LabStatus, LinkType, PlannedCell, RoomPlan, RoomPlannerError, RoomStructure, Walls, is_wall,
+    lowest_level,
+};
+
+const ROOM: usize = ROOM_SIZE as usize;
+// stamps keep off the exits, so the ramparts fit between them
+const EDGE_MARGIN: u8 = 3;
+const EXTENSIONS: usize = 60;
+
+const ROAD: RoomStructure = RoomStructure::Road(0);
+const EXT: RoomStructure = RoomStructure::Extension;
+const TOWER: RoomStructure = RoomStructure::Tower;
+const INPUT: RoomStructure = RoomStructure::Lab(LabStatus::Input);
+const OUTPUT: RoomStructure = RoomStructure::Lab(LabStatus::Output);
+
+#[rustfmt::skip]
+const BUNKER: [(i8, i8, RoomStructure); 25] = [
+    (-2, -2, ROAD), (-1, -2, ROAD), (0, -2, ROAD), (1, -2, ROAD), (2, -2, ROAD),
+    (-2, -1, ROAD), (-1, -1, RoomStructure::Storage), (0, -1, RoomStructure::Terminal),
+    (1, -1, RoomStructure::Factory), (2, -1, ROAD),
+    (-2, 0, ROAD), (-1, 0, RoomStructure::Link(LinkType::Sender)), (0, 0, RoomStructure::Empty),
+    (1, 0, RoomStructure::PowerSpawn), (2, 0, ROAD),
+    (-2, 1, ROAD), (-1, 1, RoomStructure::Spawn), (0, 1, ROAD), (1, 1, RoomStructure::Spawn),
+    (2, 1, ROAD),
+    (-2, 2, ROAD), (-1, 2, ROAD), (0, 2, ROAD), (1, 2, ROAD), (2, 2, ROAD),
+];
+
+#[rustfmt::skip]
+const FLOWER: [(i8, i8, RoomStructure); 9] = [
+    (-1, -1, EXT), (0, -1, EXT), (1, -1, EXT),
+    (-1, 0, EXT), (0, 0, ROAD), (1, 0, EXT),
+    (-1, 1, EXT), (0, 1, ROAD), (1, 1, EXT),
+];
+
+#[rustfmt::skip]
+const LABS: [(i8, i8, RoomStructure); 14] = [
+    (-1, -1, ROAD), (0, -1, OUTPUT), (1, -1, OUTPUT),
+    (-1, 0, OUTPUT), (0, 0, ROAD), (1, 0, INPUT), (2, 0, OUTPUT),
+    (-1, 1, OUTPUT), (0, 1, INPUT), (1, 1, ROAD), (2, 1, OUTPUT),
+    (0, 2, OUTPUT), (1, 2, OUTPUT), (2, 2, ROAD),
+];
+
+#[rustfmt::skip]
+const EXTENSION_FIELD: [(i8, i8, RoomStructure); 13] = [
+    (0, -2, ROAD),
+    (-1, -1, ROAD), (0, -1, EXT), (1, -1, ROAD),
+    (-2, 0, ROAD), (-1, 0, EXT), (0, 0, EXT), (1, 0, EXT), (2, 0, ROAD),
+    (-1, 1, ROAD), (0, 1, EXT), (1, 1, ROAD),
+    (0, 2, ROAD),
+];
+
+#[rustfmt::skip]
+const TOWERS: [(i8, i8, RoomStructure); 9] = [
+    (-1, -1, TOWER), (0, -1, TOWER), (1, -1, TOWER),
+    (-1, 0, ROAD), (0, 0, ROAD), (1, 0, ROAD),
+    (-1, 1, TOWER), (0, 1, TOWER), (1, 1, TOWER),
+];
+
+/// Fixed pieces of a base, the offsets are from the stamp center
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stamp {
+    /// storage, terminal, factory, the sender link, the power spawn and two spawns around the
+    /// manager cell
+    Bunker,
+    /// seven extensions around the cell a filler stands on
+    Flower,
+    /// ten labs in range of the two inputs
+    Labs,
+    /// five extensions in a road diamond
+    ExtensionField,
+    /// six towers along a road
+    Towers,
+}
+
+impl Stamp {
+    const fn cells(self) -> &'static [(i8, i8, RoomStructure)] {
+        match self {
+            Stamp::Bunker => &BUNKER,
+            Stamp::Flower => &FLOWER,
+            Stamp::Labs => &LABS,
+            Stamp::ExtensionField => &EXTENSION_FIELD,
+            Stamp::Towers => &TOWERS,
+        }
+    }
+
+    fn radius(self) -> u8 {
+        self.cells()
+            .iter()
+            .map(|(dx, dy, _)| dx.unsigned_abs().max(dy.unsigned_abs()))
+            .max()
+            .unwrap_or_default()
+    }
+
+    // the cells turned by `rotation` quarters around the center
+    fn placed(self, center: RoomXY, rotation: u8) -> Option<Vec<(RoomXY, RoomStructure)>> {
+        self.cells()
+            .iter()
+            .map(|(dx, dy, structure)| {
+                let offset = (0..rotation % 4).fold((*dx, *dy), |(dx, dy), _| (-dy, dx));
+                center.checked_add(offset).map(|xy| (xy, *structure))
+            })
+            .collect()
+    }
+}
+
+/// Base cells laid out with stamps, every cell belongs to the group it was placed with
+#[derive(Debug, Default)]
+pub(super) struct Layout {
+    core: Option<RoomXY>,
+    cells: HashMap<RoomXY, (RoomStructure, usize)>,
+    groups: usize,
+}
+
+impl Layout {
+    fn fits(&self, cells: &[(RoomXY, RoomStructure)], walls: &Walls) -> bool {
+        cells.iter().all(|(xy, structure)| {
+            !is_wall(walls, *xy)
+                && self.cells.get(xy).is_none_or(|(existed, _)| {
+                    matches!(existed, RoomStructure::Road(_))
+                        && matches!(structure, RoomStructure::Road(_))
+                })
+        })
+    }
+
+    fn add(&mut self, cells: impl IntoIterator<Item = (RoomXY, RoomStructure)>) -> usize {
+        let group = self.groups;
+        self.groups += 1;
+        for (xy, structure) in cells {
+            self.cells.entry(xy).or_insert((structure, group));
+        }
+        group
+    }
+
+    fn roads(&self) -> HashSet<RoomXY> {
+        self.cells
+            .iter()
+            .filter(|(_, (structure, _))| matches!(structure, RoomStructure::Road(_)))
+            .map(|(xy, _)| *xy)
+            .collect()
+    }
+
+    fn count(&self, structure: RoomStructure) -> usize {
+        self.cells.values().filter(|(existed, _)| *existed == structure).count()
+    }
+
+    /// Puts the stamp on the best scored place the roads reach, with one free tile around when
+    /// there is room for it
+    fn place(
+        &mut self,
+        stamp: Stamp,
+        space: &Space,
+        walls: &Walls,
+        score: impl Fn(RoomXY) -> u32,
+    ) -> Option<RoomXY> {
+        for margin in [1, 0] {
+            let candidates = (EDGE_MARGIN..ROOM_SIZE - EDGE_MARGIN)
+                .cartesian_product(EDGE_MARGIN..ROOM_SIZE - EDGE_MARGIN)
+                .map(|(x, y)| unsafe { RoomXY::unchecked_new(x, y) })
+                .filter(|xy| space.at(*xy) > stamp.radius() + margin)
+                .cartesian_product(0..4)
+                .sorted_by_key(|(xy, rotation)| (score(*xy), *rotation));
+
+            for (center, rotation) in candidates {
+                if let Some(cells) = stamp.placed(center, rotation)
+                    && self.fits(&cells, walls)
+                    && self.connect(&cells, walls)
+                {
+                    return Some(center);
+                }
+            }
+        }
+        None
+    }
+
+    // the core in a rotation that keeps the spawn already built
+    fn place_core(&mut self, spawn: RoomXY, walls: &Walls) -> Option<RoomXY> {
+        let slots = BUNKER.iter().filter(|(_, _, structure)| *structure == RoomStructure::Spawn);
+        for ((dx, dy, _), rotation) in slots.cartesian_product(0..4) {
+            let (dx, dy) = (0..rotation).fold((*dx, *dy), |(dx, dy), _| (-dy, dx));
+            if let Some(center) = spawn.checked_add((-dx, -dy))
+                && let Some(cells) = Stamp::Bunker.placed(center, rotation)
+                && cells.iter().all(|(xy, _)| in_bounds(*xy))
+                && self.fits(&cells, walls)
+            {
+                self.add(cells);
+                return Some(center);
+            }
+        }
+        None
+    }
+
+    // adds the stamp with the roads from the core to it, the core is connected by itself
+    fn connect(&mut self, cells: &[(RoomXY, RoomStructure)], walls: &Walls) -> bool {
+        let stamp_roads: HashSet<RoomXY> = cells
+            .iter()
+            .filter(|(_, structure)| matches!(structure, RoomStructure::Road(_)))
+            .map(|(xy, _)| *xy)
+            .collect();
+        let roads = self.roads();
+        if roads.is_empty() || !stamp_roads.is_disjoint(&roads) {
+            self.add(cells.iter().copied());
+            return true;
+        }
+
+        let blocked: HashSet<RoomXY> = cells
+            .iter()
+            .filter(|(_, structure)| !matches!(structure, RoomStructure::Road(_)))
+            .map(|(xy, _)| *xy)
+            .collect();
+        let Some(path) = self.path(&stamp_roads, &roads, &blocked, walls) else {
+            return false;
+        };
+        let group = self.add(cells.iter().copied());
+        for xy in path {
+            self.cells.entry(xy).or_insert((ROAD, group));
+        }
+        true
+    }
+
+    // the shortest walk from the stamp roads to the base roads around the structures
+    fn path(
+        &self,
+        from: &HashSet<RoomXY>,
+        to: &HashSet<RoomXY>,
+        blocked: &HashSet<RoomXY>,
+        walls: &Walls,
+    ) -> Option<Vec<RoomXY>> {
+        let mut previous: HashMap<RoomXY, Option<RoomXY>> =
+            from.iter().map(|xy| (*xy, None)).collect();
+        let mut queue: VecDeque<RoomXY> = from.iter().copied().collect();
+        while let Some(xy) = queue.pop_front() {
+            for neighbor in xy.neighbors() {
+                if previous.contains_key(&neighbor) {
+                    continue;
+                }
+                if to.contains(&neighbor) {
+                    let mut path = Vec::new();
+                    let mut step = Some(xy);
+                    while let Some(cell) = step {
+                        path.push(cell);
+                        step = previous.get(&cell).copied().flatten();
+                    }
+                    return Some(path);
+                }
+                let free = in_bounds(neighbor)
+                    && !is_wall(walls, neighbor)
+                    && !blocked.contains(&neighbor)
+                    && !self.cells.contains_key(&neighbor);
+                if free {
+                    previous.insert(neighbor, Some(xy));
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        None
+    }
+
+    // a free cell next to a road, the closest to the core
+    fn place_single(&mut self, structure: RoomStructure, walls: &Walls) -> Option<RoomXY> {
+        let core = self.core?;
+        let roads = self.roads();
+        let xy = roads
+            .iter()
+            .flat_map(|road| road.neighbors())
+            .filter(|xy| in_bounds(*xy) && !is_wall(walls, *xy) && !self.cells.contains_key(xy))
+            .min_by_key(|xy| (xy.get_range_to(core), xy.y, xy.x))?;
+        self.add([(xy, structure)]);
+        Some(xy)
+    }
+
+    /// Gives every structure the lowest level its count allows, the closer to the core the
+    /// earlier, and the roads the level of their first structure
+    pub(super) fn planned_cells(&self, spawn: Option<RoomXY>) -> HashSet<PlannedCell> {
+        let core = self.core.unwrap_or_else(|| unsafe { RoomXY::unchecked_new(25, 25) });
+        let mut levels: HashMap<RoomXY, u8> = HashMap::new();
+        let by_type = self
+            .cells
+            .iter()
+            .filter_map(|(xy, (structure, _))| {
+                StructureType::try_from(*structure)
+                    .ok()
+                    .filter(|st| *st != StructureType::Road)
+                    .map(|st| (st, *xy, *structure))
+            })
+            .into_group_map_by(|(st, _, _)| *st);
+        for (structure_type, cells) in by_type {
+            let ordered = cells.into_iter().sorted_by_key(|(_, xy, structure)| {
+                (
+                    Some(*xy) != spawn,
+                    !matches!(structure, RoomStructure::Lab(LabStatus::Input)),
+                    xy.get_range_to(core),
+                    xy.y,
+                    xy.x,
+                )
+            });
+            for (i, (_, xy, _)) in ordered.enumerate() {
+                if let Some(lvl) = lowest_level(structure_type, i) {
+                    levels.insert(xy, lvl);
+                }
+            }
+        }
+
+        let mut group_levels: HashMap<usize, u8> = HashMap::new();
+        for (xy, (_, group)) in &self.cells {
+            if let Some(lvl) = levels.get(xy) {
+                let entry = group_levels.entry(*group).or_insert(*lvl);
+                *entry = (*entry).min(*lvl);
+            }
+        }
+
+        self.cells
+            .iter()
+            .filter_map(|(xy, (structure, group))| {
+                let lvl = match structure {
+                    RoomStructure::Road(_) => group_levels.get(group).copied().unwrap_or(1),
+                    RoomStructure::Empty => 8,
+                    _ => *levels.get(xy)?,
+                };
+                Some(PlannedCell::new(*xy, *structure, lvl, None))
+            })
+            .collect()
+    }
+
+    fn cells(&self) -> impl Iterator<Item = (&RoomXY, &RoomStructure)> {
+        self.cells.iter().map(|(xy, (structure, _))| (xy, structure))
+    }
+}
+
+/// Chebyshev distance to the closest wall or the exit margin
+struct Space([[u8; ROOM]; ROOM]);
+
+impl Space {
+    fn new(walls: &Walls) -> Self {
+        let mut space = [[u8::MAX; ROOM]; ROOM];
+        let mut queue = VecDeque::new();
+        for y in 0..ROOM_SIZE {
+            for x in 0..ROOM_SIZE {
+                let xy = unsafe { RoomXY::unchecked_new(x, y) };
+                if is_wall(walls, xy) || !in_bounds(xy) {
+                    space[usize::from(y)][usize::from(x)] = 0;
+                    queue.push_back(xy);
+                }
+            }
+        }
+        while let Some(xy) = queue.pop_front() {
+            let next = space[usize::from(xy.y.u8())][usize::from(xy.x.u8())] + 1;
+            for neighbor in xy.neighbors() {
+                let cell = &mut space[usize::from(neighbor.y.u8())][usize::from(neighbor.x.u8())];
+                if *cell > next {
+                    *cell = next;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        Self(space)
+    }
+
+    fn at(&self, xy: RoomXY) -> u8 {
+        self.0[usize::from(xy.y.u8())][usize::from(xy.x.u8())]
+    }
+}
+
+fn in_bounds(xy: RoomXY) -> bool {
+    let range = EDGE_MARGIN..ROOM_SIZE - EDGE_MARGIN;
+    range.contains(&xy.x.u8()) && range.contains(&xy.y.u8())
+}
+
+/// The core next to the controller and the sources, the labs and the towers next to the core,
+/// flowers and then extension fields until there are enough extensions, single structures in
+/// the cramped places
+pub(super) fn layout(
+    site: &BaseSite,
+    spawn: Option<RoomXY>,
+    walls: &Walls,
+) -> Result<Layout, RoomPlannerError> {
+    let space = Space::new(walls);
+    let mut layout = Layout::default();
+
+    let core_score = |xy: RoomXY| {
+        2 * u32::from(xy.get_range_to(site.ctrl))
+            + site.sources.iter().map(|source| u32::from(xy.get_range_to(*source))).sum::<u32>()
+    };
+    let core = spawn
+        .and_then(|spawn| layout.place_core(spawn, walls))
+        .or_else(|| layout.place(Stamp::Bunker, &space, walls, core_score))
+        .ok_or(RoomPlannerError::CentralSquareNotFound)?;
+    layout.core = Some(core);
+
+    let near_core = |xy: RoomXY| u32::from(xy.get_range_to(core));
+    for stamp in [Stamp::Labs, Stamp::Towers] {
+        if layout.place(stamp, &space, walls, near_core).is_none() {
+            let structure = if stamp == Stamp::Labs { OUTPUT } else { TOWER };
+            let limit = if stamp == Stamp::Labs { 10 } else { 6 };
+            while layout.count(structure) < limit {
+                layout
+                    .place_single(structure, walls)
+                    .ok_or(RoomPlannerError::StructurePlacementFailure)?;
+            }
+        }
+    }
+
+    for (stamp, size) in [(Stamp::Flower, 7), (Stamp::ExtensionField, 5)] {
+        while layout.count(EXT) + size <= EXTENSIONS
+            && layout.place(stamp, &space, walls, near_core).is_some()
+        {}
+    }
+    for structure in [RoomStructure::Spawn, RoomStructure::Nuker, RoomStructure::Observer] {
+        layout.place_single(structure, walls).ok_or(RoomPlannerError::StructurePlacementFailure)?;
+    }
+    while layout.count(EXT) < EXTENSIONS {
+        layout.place_single(EXT, walls).ok_or(RoomPlannerError::StructurePlacementFailure)?;
+    }
+    Ok(layout)
+}
+
+/// The stamp layout with the perimeter ramparts and the roads to the sources, the controller
+/// and the mineral
+pub(super) fn plan(
+    room_name: RoomName,
+    site: &BaseSite,
+    spawn: Option<RoomXY>,
+    walls: &Walls,
+) -> Result<RoomPlan, RoomPlannerError> {
+    let layout = layout(site, spawn, walls)?;
+
+    let protected: Vec<RoomXY> = layout
+        .cells()
+        .map(|(xy, _)| *xy)
+        .chain(site.sources.iter().chain([&site.ctrl]).flat_map(|xy| xy.neighbors()))
+        .collect();
+    let cut = ramparts::min_cut(&protected, walls)?;
+    let grid = cut.grid(walls);
+
+    let mut plan = RoomPlan::new(layout.planned_cells(spawn));
+    let storage = plan
+        .storage()
+        .map(|xy| Position::new(xy.x, xy.y, room_name))
+        .ok_or(RoomPlannerError::StorageNotFound)?;
+
+    ramparts::plan(&cut, &mut plan);
+    sources::plan(storage, &site.sources, &grid, &mut plan)?;
+    controller::plan(storage, site.ctrl, &grid, &mut plan)?;
+    mineral::plan(storage, site.mineral, &grid, &mut plan)?;
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use screeps::{RoomXY, StructureType};
+
+    use crate::rooms::state::constructions::owned::BaseSite;
+    use crate::rooms::state::constructions::owned::stamps::{EXTENSIONS, layout};
+    use crate::rooms::state::constructions::owned::tests::sources;
+    use crate::rooms::state::constructions::tests::WALLS;
+    use crate::rooms::state::constructions::{RoomPlan, RoomStructure, is_wall};
+
+    #[test]
+    fn stamp_layout_test() {
+        let ctrl = RoomXY::checked_new(20, 20).unwrap();
+        let site = BaseSite { ctrl, sources: sources(), mineral: ctrl };
+
+        let layout = layout(&site, None, &WALLS).unwrap();
+        let plan = RoomPlan::new(layout.planned_cells(None));
+        let count = |structure: StructureType| {
+            plan.cells()
+                .filter(|cell| StructureType::try_from(cell.structure) == Ok(structure))
+                .count()
+        };
+        assert_eq!(count(StructureType::Extension), EXTENSIONS);
+        assert_eq!(count(StructureType::Lab), 10);
+        assert_eq!(count(StructureType::Tower), 6);
+        assert_eq!(count(StructureType::Spawn), 3);
+        assert!(plan.storage().is_some());
+        assert!(plan.cells().all(|cell| !is_wall(&WALLS, cell.xy)));
+        assert!(plan.cells().all(|cell| (1..=8).contains(&cell.b_lvl)));
+
+        // every road is reachable from the core over the other roads
+        let roads: HashSet<RoomXY> = plan
+            .cells()
+            .filter(|cell| matches!(cell.structure, RoomStructure::Road(_)))
+            .map(|cell| cell.xy)
+            .collect();
+        let start =
+            *roads.iter().min_by_key(|xy| xy.get_range_to(plan.storage().unwrap())).unwrap();
+        let mut seen = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(xy) = stack.pop() {
+            for neighbor in xy.neighbors() {
+                if roads.contains(&neighbor) && seen.insert(neighbor) {
+                    stack.push(neighbor);
+                }
+            }
+        }
+        assert_eq!(seen.len(), roads.len());
+    }
+}
@@ -3,16 +3,17 @@ use std::collections::HashMap;
 use std::iter;
 
 use itertools::Itertools;
-use screeps::{RoomCoordinate, RoomXY};
+use screeps::RoomXY;
 
-use crate::rooms::state::constructions::owned::Perimeter;
+use crate::rooms::state::constructions::owned::ramparts::RampartCut;
 use crate::rooms::state::constructions::{PlannedCell, RoomPart, RoomPlan, RoomStructure};
 
-pub fn plan(perimeter: &Perimeter, grid: &HashMap<RoomXY, RoomPart>, room_plan: &mut RoomPlan) {
-    let reds = red_parts_near_ramparts(perimeter, grid);
+pub fn plan(cut: &RampartCut, grid: &HashMap<RoomXY, RoomPart>, room_plan: &mut RoomPlan) {
+    let reds = red_parts_near_ramparts(cut, grid);
+    let occupied = room_plan.occupied();
     let candidates: Vec<(RoomXY, usize)> = grid
         .iter()
-        .filter_map(|(xy, part)| if part.is_yellow() { Some(*xy) } else { None })
+        .filter_map(|(xy, part)| (part.is_yellow() && !occupied.contains(xy)).then_some(*xy))
         .map(|yellow| {
             let under_attack_count =
                 reds.iter().filter(|red| red.get_range_to(yellow) == 3).count();
@@ -106,20 +107,16 @@ fn select_best_spread(
     Box::new(best_idxs.into_iter().map(move |idx| candidates[idx].0))
 }
 
-fn red_parts_near_ramparts(perimeter: &Perimeter, grid: &HashMap<RoomXY, RoomPart>) -> Vec<RoomXY> {
-    let mut attack_zones = Vec::new();
-
-    let (x0, y0, x1, y1) = perimeter.rectangle();
-    for y in y0 - 1..=y1 + 1 {
-        for x in x0 - 1..=x1 + 1 {
-            let xy = unsafe {
-                RoomXY::new(RoomCoordinate::unchecked_new(x), RoomCoordinate::unchecked_new(y))
-            };
-            if grid.get(&xy).is_some_and(|part| part.is_red()) && perimeter.near_rampart(xy) {
-                attack_zones.push(xy);
-            }
-        }
-    }
-
-    attack_zones
+// cells outside the cut where hostiles attack the ramparts from
+fn red_parts_near_ramparts(cut: &RampartCut, grid: &HashMap<RoomXY, RoomPart>) -> Vec<RoomXY> {
+    cut.ramparts()
+        .iter()
+        .flat_map(|rampart| rampart.neighbors())
+        .filter(|xy| {
+            !cut.is_inside(*xy)
+                && !cut.ramparts().contains(xy)
+                && grid.get(xy).is_some_and(|part| !part.is_wall())
+        })
+        .unique()
+        .collect()
 }
@@ -0,0 +1,200 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use screeps::local::RoomXY;
+use screeps::visual::{CircleStyle, RectStyle, RoomVisual, TextStyle};
+use screeps::{
+    HasPosition, ROOM_SIZE, RoomName, StructureObject, StructureType, Terrain, find, game,
+};
+
+use super::{PlannedCell, RoomPart, RoomPlan, RoomStructure};
+
+/// What the operator asked to draw and until when
+#[derive(Debug, Clone, Copy)]
+pub struct PlanVisual {
+    /// draw the plan of this farm instead of the base plan
+    pub farm: Option<RoomName>,
+    /// cells standing at this level, all of them when not set
+    pub lvl: Option<u8>,
+    pub zones: bool,
+    pub until: u32,
+}
+
+impl PlanVisual {
+    pub const fn new(farm: Option<RoomName>, lvl: Option<u8>, zones: bool, until: u32) -> Self {
+        Self { farm, lvl, zones, until }
+    }
+
+    /// Draws the plan cells, the zones and the difference with the built structures
+    pub fn draw(&self, room_name: RoomName, plan: &RoomPlan) {
+        let visual = RoomVisual::new(Some(room_name));
+        let cells: Vec<&PlannedCell> = plan
+            .cells()
+            .filter(|cell| {
+                self.lvl.is_none_or(|lvl| {
+                    cell.b_lvl <= lvl && cell.r_lvl.is_none_or(|remove_lvl| remove_lvl > lvl)
+                })
+            })
+            .collect();
+
+        if self.zones {
+            for (xy, part) in zones(room_name, plan) {
+                let color = match part {
+                    RoomPart::Green => "#00ff00",
+                    RoomPart::Yellow => "#ffff00",
+                    RoomPart::Orange => "#ff8800",
+                    RoomPart::Wall => "#000000",
+                    RoomPart::Protected => "#0088ff",
+                    _ => "#ff0000",
+                };
+                visual.rect(
+                    f32::from(xy.x.u8()) - 0.5,
+                    f32::from(xy.y.u8()) - 0.5,
+                    1.,
+                    1.,
+                    Some(RectStyle::default().fill(color).opacity(0.1)),
+                );
+            }
+        }
+
+        for cell in &cells {
+            draw_cell(&visual, cell);
+        }
+
+        // the built structures are known for the visible rooms only
+        let Some(built) = built(room_name) else {
+            return;
+        };
+        let planned: HashSet<(RoomXY, StructureType)> = cells
+            .iter()
+            .filter_map(|cell| {
+                StructureType::try_from(cell.structure).ok().map(|structure| (cell.xy, structure))
+            })
+            .collect();
+        for (xy, _) in planned.difference(&built) {
+            mark(&visual, *xy, "#ff0000");
+        }
+        for (xy, _) in built.difference(&planned) {
+            mark(&visual, *xy, "#ff00ff");
+        }
+    }
+}
+
+fn draw_cell(visual: &RoomVisual, cell: &PlannedCell) {
+    let (x, y) = (f32::from(cell.xy.x.u8()), f32::from(cell.xy.y.u8()));
+    let glyph = match cell.structure {
+        RoomStructure::Road(_) => {
+            visual.circle(x, y, Some(CircleStyle::default().radius(0.15).fill("#aaaaaa")));
+            return;
+        }
+        RoomStructure::Rampart(perimeter) => {
+            let color = if perimeter { "#00aa00" } else { "#006600" };
+            visual.rect(
+                x - 0.45,
+                y - 0.45,
+                0.9,
+                0.9,
+                Some(RectStyle::default().fill(color).opacity(0.3)),
+            );
+            return;
+        }
+        RoomStructure::Empty => "·",
+        RoomStructure::Spawn => "S",
+        RoomStructure::Extension => "e",
+        RoomStructure::Wall => "W",
+        RoomStructure::Link(_) => "L",
+        RoomStructure::Storage => "St",
+        RoomStructure::Tower => "T",
+        RoomStructure::Observer => "O",
+        RoomStructure::PowerSpawn => "P",
+        RoomStructure::Extractor => "X",
+        RoomStructure::Lab(_) => "Lb",
+        RoomStructure::Terminal => "Tm",
+        RoomStructure::Container(_) => "C",
+        RoomStructure::Nuker => "N",
+        RoomStructure::Factory => "F",
+    };
+    visual.text(
+        x,
+        y + 0.2,
+        glyph.to_string(),
+        Some(TextStyle::default().color("#ffffff").font(0.5)),
+    );
+    visual.text(
+        x + 0.3,
+        y - 0.25,
+        cell.b_lvl.to_string(),
+        Some(TextStyle::default().color("#88ccff").font(0.3)),
+    );
+}
+
+// a frame around a cell which plan and buildings differ
+fn mark(visual: &RoomVisual, xy: RoomXY, color: &str) {
+    visual.rect(
+        f32::from(xy.x.u8()) - 0.5,
+        f32::from(xy.y.u8()) - 0.5,
+        1.,
+        1.,
+        Some(RectStyle::default().fill("transparent").stroke(color).stroke_width(0.08)),
+    );
+}
+
+fn built(room_name: RoomName) -> Option<HashSet<(RoomXY, StructureType)>> {
+    let room = game::rooms().get(room_name)?;
+    Some(
+        room.find(find::STRUCTURES, None)
+            .into_iter()
+            .filter(|structure| !matches!(structure, StructureObject::StructureController(_)))
+            .map(|structure| (structure.pos().xy(), structure.structure_type()))
+            .collect(),
+    )
+}
+
+// zones the planner uses: outside the perimeter ramparts is red, inside by the range to them
+fn zones(room_name: RoomName, plan: &RoomPlan) -> HashMap<RoomXY, RoomPart> {
+    let terrain = game::map::get_room_terrain(room_name);
+    let is_wall = |xy: RoomXY| {
+        terrain.as_ref().is_some_and(|terrain| terrain.get(xy.x.u8(), xy.y.u8()) == Terrain::Wall)
+    };
+    let perimeter = plan.perimeter();
+
+    let mut zones = HashMap::new();
+    let mut queue = VecDeque::new();
+    for y in 0..ROOM_SIZE {
+        for x in 0..ROOM_SIZE {
+            let xy = unsafe { RoomXY::unchecked_new(x, y) };
+            if is_wall(xy) {
+                zones.insert(xy, RoomPart::Wall);
+            } else if xy.is_room_edge() {
+                zones.insert(xy, RoomPart::Exit);
+                queue.push_back(xy);
+            }
+        }
+    }
+
+    while let Some(xy) = queue.pop_front() {
+        for neighbor in xy.neighbors() {
+            if !zones.contains_key(&neighbor) && !perimeter.contains(&neighbor) {
+                zones.insert(neighbor, RoomPart::Red);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+
+    for y in 0..ROOM_SIZE {
+        for x in 0..ROOM_SIZE {
+            let xy = unsafe { RoomXY::unchecked_new(x, y) };
+            if zones.contains_key(&xy) {
+                continue;
+            }
+            let range = perimeter.iter().map(|rampart| rampart.get_range_to(xy)).min();
+            let part = match range {
+                Some(0) => RoomPart::Protected,
+                Some(1) => RoomPart::Orange,
+                Some(2) => RoomPart::Yellow,
+                _ => RoomPart::Green,
+            };
+            zones.insert(xy, part);
+        }
+    }
+    zones
+}
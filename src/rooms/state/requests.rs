@@ -5,7 +5,7 @@ use std::hash::{Hash, Hasher};
 pub use data::{
     BookData, BuildData, CaravanData, CarryData, ClaimData, CrashData, DefendData, DepositData,
     DestroyData, DismantleData, FactoryData, FarmData, LRWData, LabData, PickupData, PowerbankData,
-    ProtectData, PullData, RepairData, SMData, TransferData, WithdrawData,
+    ProtectData, PullData, RepairData, SMData, SiegeData, TransferData, WithdrawData,
 };
 use log::error;
 use screeps::Part;
@@ -34,6 +34,7 @@ use self::data::protect::protect_handler;
 use self::data::pull::pull_handler;
 use self::data::repair::repair_handler;
 use self::data::safe_mode::sm_handler;
+use self::data::siege::siege_handler;
 use self::data::transfer::transfer_handler;
 use self::data::withdraw::withdraw_handler;
 use self::meta::{Meta, Status};
@@ -41,10 +42,13 @@ use crate::rooms::RoomEvent;
 use crate::rooms::shelter::Shelter;
 use crate::units::creeps::CreepMemory;
 use crate::units::tasks::Task;
+use crate::utils::profiler;
 
 pub mod assignment;
 mod data;
 pub mod meta;
+pub mod priority;
+pub mod watchdog;
 
 //todo defend request for all defenders
 //todo repair perimeter for all house keepers
@@ -79,6 +83,10 @@ impl Request {
         self.meta.created_at
     }
 
+    pub const fn priority(&self) -> u8 {
+        self.meta.priority
+    }
+
     pub fn handle(
         &mut self,
         home: &Shelter,
@@ -86,7 +94,7 @@ impl Request {
     ) -> SmallVec<[RoomEvent; 3]> {
         let (meta, assignment, kind) = (&mut self.meta, &mut self.assignment, &mut self.kind);
 
-        match kind {
+        profiler::measure(("request", kind.name()), || match kind {
             RequestKind::Destroy(_) => destroy_handler(),
             RequestKind::Protect(d) => protect_handler(d, meta, home.name()),
             RequestKind::Defend(d) => defend_handler(d, meta, assignment, home, creeps),
@@ -94,7 +102,7 @@ impl Request {
             RequestKind::Factory(d) => factory_handler(d, meta, home),
             RequestKind::Lab(d) => lab_handler(d, meta, home),
             RequestKind::Powerbank(d) => powerbank_handler(d, meta, assignment, home),
-            RequestKind::Deposit(d) => deposit_handler(d, meta, assignment, home.name()),
+            RequestKind::Deposit(d) => deposit_handler(d, meta, assignment, home),
             RequestKind::Caravan(d) => caravan_handler(d, meta, home.name()),
             RequestKind::Build(_) => build_handler(meta, assignment),
             RequestKind::Repair(_) => repair_handler(meta, assignment),
@@ -109,7 +117,8 @@ impl Request {
             RequestKind::Carry(_) => carry_handler(meta, assignment),
             RequestKind::LongRangeWithdraw(_) => lrw_handler(meta, assignment, home.name()),
             RequestKind::Farm(d) => begin_farm_handler(d, meta, assignment, home),
-        }
+            RequestKind::Siege(d) => siege_handler(d, meta, assignment, home),
+        })
     }
 
     pub fn join(&mut self, doer: Option<String>, squad_id: Option<&str>) {
@@ -160,6 +169,7 @@ impl Hash for Request {
                 d.resource.hash(state);
             }
             RequestKind::Farm(d) => d.room_name.hash(state),
+            RequestKind::Siege(d) => d.target.hash(state),
         }
     }
 }
@@ -265,6 +275,10 @@ impl PartialEq for Request {
                 RequestKind::Farm(o) => d.room_name == o.room_name,
                 _ => false,
             },
+            RequestKind::Siege(d) => match &other.kind {
+                RequestKind::Siege(o) => d.target == o.target,
+                _ => false,
+            },
         }
     }
 }
@@ -300,6 +314,37 @@ pub enum RequestKind {
     Lab(LabData),
     Transfer(TransferData),
     Farm(FarmData),
+    Siege(SiegeData),
+}
+
+impl RequestKind {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            RequestKind::Pickup(_) => "pickup",
+            RequestKind::Withdraw(_) => "withdraw",
+            RequestKind::Carry(_) => "carry",
+            RequestKind::LongRangeWithdraw(_) => "long_range_withdraw",
+            RequestKind::SafeMode(_) => "safe_mode",
+            RequestKind::Caravan(_) => "caravan",
+            RequestKind::Repair(_) => "repair",
+            RequestKind::Dismantle(_) => "dismantle",
+            RequestKind::Build(_) => "build",
+            RequestKind::Claim(_) => "claim",
+            RequestKind::Book(_) => "book",
+            RequestKind::Pull(_) => "pull",
+            RequestKind::Defend(_) => "defend",
+            RequestKind::Protect(_) => "protect",
+            RequestKind::Destroy(_) => "destroy",
+            RequestKind::Crash(_) => "crash",
+            RequestKind::Powerbank(_) => "powerbank",
+            RequestKind::Deposit(_) => "deposit",
+            RequestKind::Factory(_) => "factory",
+            RequestKind::Lab(_) => "lab",
+            RequestKind::Transfer(_) => "transfer",
+            RequestKind::Farm(_) => "farm",
+            RequestKind::Siege(_) => "siege",
+        }
+    }
 }
 
 impl Display for RequestKind {
@@ -327,6 +372,7 @@ impl Display for RequestKind {
             RequestKind::Lab(d) => write!(f, "Lab({d:?})"),
             RequestKind::Transfer(d) => write!(f, "Transfer({d:?})"),
             RequestKind::Farm(d) => write!(f, "Begin Farm({d:?})"),
+            RequestKind::Siege(d) => write!(f, "Siege({d:?})"),
         }
     }
 }
@@ -420,11 +466,11 @@ impl TryFrom<Task> for Request {
                 Assignment::Single(None),
             )),
             Task::DepositHarvest(pos, id) => Ok(Request::new(
-                RequestKind::Deposit(DepositData::new(id, pos, 1)),
+                RequestKind::Deposit(DepositData::new(id, pos, 1, 0)),
                 Assignment::Squads(Vec::new()),
             )),
             Task::PowerbankAttack(pos, id, _) => Ok(Request::new(
-                RequestKind::Powerbank(PowerbankData::new(id, pos, 1)),
+                RequestKind::Powerbank(PowerbankData::new(id, pos, 1, 0)),
                 Assignment::Squads(Vec::new()),
             )),
             Task::Crash(id, pos) => Ok(Request::new(
@@ -4,6 +4,7 @@ use screeps::game;
 use serde::{Deserialize, Serialize};
 
 use super::RequestError;
+use crate::movement::formation::Formation;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum Assignment {
@@ -37,6 +38,15 @@ impl Assignment {
         }
     }
 
+    pub fn formation(&self, squad_id: &str) -> Option<Formation> {
+        match self {
+            Assignment::Squads(squads) => {
+                squads.iter().find(|squad| squad.id == *squad_id).map(|squad| squad.formation)
+            }
+            _ => None,
+        }
+    }
+
     pub fn has_any_members(&self) -> bool {
         match self {
             Assignment::None => false,
@@ -61,6 +71,17 @@ impl Assignment {
         }
     }
 
+    /// Removes the doers who are not in the game anymore and returns them
+    pub fn remove_dead(&mut self) -> Vec<String> {
+        let is_dead = |doer: &String| game::creeps().get(doer.clone()).is_none();
+        match self {
+            // squads are replaced by their request handlers
+            Assignment::None | Assignment::Squads(_) => Vec::new(),
+            Assignment::Single(slot) => slot.take_if(|doer| is_dead(doer)).into_iter().collect(),
+            Assignment::Multi(set) => set.extract_if(is_dead).collect(),
+        }
+    }
+
     pub fn drop(&mut self, doer: String, squad_id: Option<&str>) -> Result<(), RequestError> {
         match self {
             Assignment::None => Ok(()),
@@ -221,6 +242,8 @@ impl Display for Assignment {
 pub struct Squad {
     pub id: String,
     pub members: HashSet<String>,
+    #[serde(default)]
+    pub formation: Formation,
 }
 
 impl Squad {
@@ -228,6 +251,11 @@ impl Squad {
         where T: Display
     {
         let squad_id = format!("{}_{}", target_id, index);
-        Self { id: squad_id, members: HashSet::new() }
+        Self { id: squad_id, members: HashSet::new(), formation: Formation::default() }
+    }
+
+    pub const fn with_formation(mut self, formation: Formation) -> Self {
+        self.formation = formation;
+        self
     }
 }
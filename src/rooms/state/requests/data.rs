@@ -18,6 +18,7 @@ pub(crate) mod protect;
 pub(crate) mod pull;
 pub(crate) mod repair;
 pub(crate) mod safe_mode;
+pub(crate) mod siege;
 pub(crate) mod transfer;
 pub(crate) mod withdraw;
 
@@ -41,5 +42,6 @@ pub use protect::ProtectData;
 pub use pull::PullData;
 pub use repair::RepairData;
 pub use safe_mode::SMData;
+pub use siege::SiegeData;
 pub use transfer::TransferData;
 pub use withdraw::WithdrawData;
@@ -1,48 +1,133 @@
-use std::cmp::min;
-use log::warn;
-use screeps::{Deposit, HasPosition, ObjectId, Part, Position, RoomName, find, game};
+use log::{info, warn};
+use screeps::constants::{
+    CARRY_CAPACITY, CREEP_LIFE_TIME, CREEP_SPAWN_TIME, DEPOSIT_EXHAUST_MULTIPLY,
+    DEPOSIT_EXHAUST_POW, HARVEST_DEPOSIT_POWER, MAX_CREEP_SIZE,
+};
+use screeps::{Deposit, HasPosition, ObjectId, Part, Position, ResourceType, find, game};
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 
 use crate::rooms::RoomEvent;
+use crate::rooms::shelter::Shelter;
 use crate::rooms::state::requests::assignment::Squad;
 use crate::rooms::state::requests::{Assignment, Meta, Status};
-use crate::units::roles::Role;
 use crate::units::roles::teams::dep_hauler::DepositHauler;
 use crate::units::roles::teams::dep_miner::DepositMiner;
+use crate::units::roles::Role;
 use crate::utils::commons::has_part;
-use crate::utils::constants::DEPOSIT_REQUEST_THRESHOLD;
+use crate::utils::constants::{
+    DEPOSIT_MAX_HAULERS, DEPOSIT_MAX_MINERS, DEPOSIT_MIN_GENERATION_YIELD, DEPOSIT_TICKS_PER_ROOM,
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DepositData {
     pub id: ObjectId<Deposit>,
     pub pos: Position,
     pub empty_cells: usize,
+    /// rooms from home
+    #[serde(default)]
+    pub distance: u32,
+    #[serde(default)]
+    pub deposit_type: Option<ResourceType>,
+    #[serde(default)]
+    pub last_cooldown: Option<u32>,
 }
 
 impl DepositData {
-    pub const fn new(id: ObjectId<Deposit>, pos: Position, empty_cells: usize) -> Self {
-        Self { id, pos, empty_cells }
+    pub const fn new(
+        id: ObjectId<Deposit>,
+        pos: Position,
+        empty_cells: usize,
+        distance: u32,
+    ) -> Self {
+        Self { id, pos, empty_cells, distance, deposit_type: None, last_cooldown: None }
+    }
+
+    // ticks from the spawn request to the deposit
+    const fn travel_ticks(&self) -> u32 {
+        self.distance * DEPOSIT_TICKS_PER_ROOM + CREEP_SPAWN_TIME * MAX_CREEP_SIZE
+    }
+
+    fn miners(&self) -> u32 {
+        u32::try_from(self.empty_cells.min(DEPOSIT_MAX_MINERS)).unwrap_or_default()
+    }
+
+    // records the harvested amount since the last observation
+    fn observe(&mut self, deposit: &Deposit, events: &mut SmallVec<[RoomEvent; 3]>) {
+        let cooldown = deposit.last_cooldown();
+        if let Some(last_cooldown) = self.last_cooldown {
+            let harvested =
+                total_harvested(cooldown).saturating_sub(total_harvested(last_cooldown));
+            if harvested > 0 {
+                events.push(RoomEvent::DepositYield(deposit.deposit_type(), harvested));
+            }
+        }
+        self.deposit_type = Some(deposit.deposit_type());
+        self.last_cooldown = Some(cooldown);
+    }
+
+    // how much the next miners generation harvests while the cooldown grows
+    fn forecast(&self, work_parts: u32) -> u32 {
+        let work_ticks = CREEP_LIFE_TIME.saturating_sub(self.travel_ticks());
+        let per_harvest = self.miners() * work_parts * HARVEST_DEPOSIT_POWER;
+        let mut harvested = total_harvested(self.last_cooldown.unwrap_or_default());
+
+        let (mut ticks, mut gained) = (0, 0);
+        while ticks < work_ticks && per_harvest > 0 {
+            harvested += per_harvest;
+            gained += per_harvest;
+            ticks += cooldown(harvested) + 1;
+        }
+        gained
+    }
+
+    // enough haulers to be back before the miners fill the next load
+    fn haulers(&self, gained: u32, capacity: u32) -> u32 {
+        let work_ticks = CREEP_LIFE_TIME.saturating_sub(self.travel_ticks()).max(1);
+        let round_trip = 2 * self.distance.max(1) * DEPOSIT_TICKS_PER_ROOM;
+        (round_trip * gained).div_ceil(work_ticks * capacity.max(1)).clamp(1, DEPOSIT_MAX_HAULERS)
     }
 
     fn spawn_squad(
         &self,
         meta: &mut Meta,
         assignment: &mut Assignment,
-        home_name: RoomName,
-        events: &mut SmallVec<[RoomEvent; 3]>)
-    {
+        home: &Shelter,
+        events: &mut SmallVec<[RoomEvent; 3]>,
+    ) {
+        let energy = home.room().energy_capacity_available();
+        let dep_miner = Role::DepositMiner(DepositMiner::new(None, None));
+        let dep_hauler = Role::DepositHauler(DepositHauler::new(None, None));
+
+        let gained = self.forecast(dep_miner.parts_number(Part::Work, energy));
+        if gained < DEPOSIT_MIN_GENERATION_YIELD {
+            info!(
+                "{} deposit {}: the next generation harvests only {}, stop",
+                home.name(),
+                self.id,
+                gained
+            );
+            meta.update(if meta.status == Status::Created {
+                Status::Resolved
+            } else {
+                Status::Carry
+            });
+            return;
+        }
+
         if let Assignment::Squads(squads) = assignment {
             let squad = Squad::new(self.id, squads.len() + 1);
+            let capacity = dep_hauler.parts_number(Part::Carry, energy) * CARRY_CAPACITY;
+            let haulers = self.haulers(gained, capacity);
 
             let dep_miner =
-                Role::DepositMiner(DepositMiner::new(Some(squad.id.clone()), Some(home_name)));
+                Role::DepositMiner(DepositMiner::new(Some(squad.id.clone()), Some(home.name())));
             let dep_hauler =
-                Role::DepositHauler(DepositHauler::new(Some(squad.id.clone()), Some(home_name)));
+                Role::DepositHauler(DepositHauler::new(Some(squad.id.clone()), Some(home.name())));
 
             squads.push(squad);
-            events.push(RoomEvent::Spawn(dep_miner, min(3, self.empty_cells)));
-            events.push(RoomEvent::Spawn(dep_hauler, 1));
+            events.push(RoomEvent::Spawn(dep_miner, self.miners() as usize));
+            events.push(RoomEvent::Spawn(dep_hauler, haulers as usize));
 
             meta.update(Status::InProgress);
         } else {
@@ -55,29 +140,28 @@ pub(in crate::rooms::state::requests) fn deposit_handler(
     data: &mut DepositData,
     meta: &mut Meta,
     assignment: &mut Assignment,
-    home_name: RoomName,
+    home: &Shelter,
 ) -> SmallVec<[RoomEvent; 3]> {
     let mut events: SmallVec<[RoomEvent; 3]> = SmallVec::new();
+
+    if let Some(deposit) = data.id.resolve() {
+        data.observe(&deposit, &mut events);
+    }
+
     match meta.status {
         Status::Created => {
-            data.spawn_squad(meta, assignment, home_name, &mut events);
+            data.spawn_squad(meta, assignment, home, &mut events);
         }
-        Status::InProgress => {
-            if let Some(deposit) = data.id.resolve()
-                && deposit.last_cooldown() >= DEPOSIT_REQUEST_THRESHOLD
-            {
-                meta.update(Status::Carry);
-            } else if game::time() > meta.updated_at + 1350 {
-                let fast_spawn = game::rooms().get(data.pos.room_name()).is_some_and(|room| {
-                    room.find(find::HOSTILE_CREEPS, None).iter().any(|hostile| {
-                        has_part(&[Part::Work], hostile, false)
-                            && hostile.pos().in_range_to(data.pos, 5)
-                    })
-                });
-
-                if fast_spawn || game::time() > meta.updated_at + 1400 {
-                    data.spawn_squad(meta, assignment, home_name, &mut events);
-                }
+        Status::InProgress if game::time() > meta.updated_at + 1350 => {
+            let fast_spawn = game::rooms().get(data.pos.room_name()).is_some_and(|room| {
+                room.find(find::HOSTILE_CREEPS, None).iter().any(|hostile| {
+                    has_part(&[Part::Work], hostile, false)
+                        && hostile.pos().in_range_to(data.pos, 5)
+                })
+            });
+
+            if fast_spawn || game::time() > meta.updated_at + 1400 {
+                data.spawn_squad(meta, assignment, home, &mut events);
             }
         }
         Status::Carry if meta.updated_at < game::time() - 2000 => {
@@ -87,3 +171,14 @@ pub(in crate::rooms::state::requests) fn deposit_handler(
     }
     events
 }
+
+// cooldown = ceil(DEPOSIT_EXHAUST_MULTIPLY * total_harvested ^ DEPOSIT_EXHAUST_POW)
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss, clippy::cast_precision_loss)]
+fn cooldown(harvested: u32) -> u32 {
+    (DEPOSIT_EXHAUST_MULTIPLY * (harvested as f32).powf(DEPOSIT_EXHAUST_POW)).ceil() as u32
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss, clippy::cast_precision_loss)]
+fn total_harvested(cooldown: u32) -> u32 {
+    (cooldown as f32 / DEPOSIT_EXHAUST_MULTIPLY).powf(DEPOSIT_EXHAUST_POW.recip()) as u32
+}
@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 
 use crate::commons::is_walkable;
+use crate::movement::formation::Formation;
 use crate::rooms::RoomEvent;
 use crate::rooms::shelter::Shelter;
 use crate::rooms::state::BoostReason;
@@ -39,7 +40,7 @@ pub(in crate::rooms::state::requests) fn dismantle_handler(
         Status::Created => {
             match assignment {
                 Assignment::Squads(squads) => {
-                    let squad = Squad::new(data.id, squads.len() + 1);
+                    let squad = Squad::new(data.id, squads.len() + 1).with_formation(Formation::Duo);
 
                     let dismantler = Role::CombatDismantler(ComDismantler::new(Some(squad.id.clone()), Some(home.name())));
                     let healer = Role::CombatHealer(ComHealer::new(Some(squad.id.clone()), Some(home.name())));
@@ -1,18 +1,31 @@
-use log::warn;
-use screeps::{HasHits, ObjectId, Part, Position, ResourceType, RoomName, StructurePowerBank, game};
+use log::{info, warn};
+use screeps::constants::{
+    ATTACK_POWER, CREEP_LIFE_TIME, CREEP_SPAWN_TIME, LAB_BOOST_MINERAL, MAX_CREEP_SIZE,
+};
+use screeps::{
+    Boost, Creep, HasHits, HasPosition, ObjectId, Part, Position, ResourceType, Room, RoomName,
+    StructurePowerBank, find, game,
+};
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 
+use crate::movement::formation::Formation;
 use crate::rooms::RoomEvent;
 use crate::rooms::shelter::Shelter;
+use crate::rooms::state::BoostReason;
 use crate::rooms::state::requests::assignment::Squad;
 use crate::rooms::state::requests::{Assignment, Meta, Status};
-use crate::units::roles::Role;
 use crate::units::roles::teams::pb_a::PBAttacker;
 use crate::units::roles::teams::pb_c::PBCarrier;
 use crate::units::roles::teams::pb_h::PBHealer;
-use crate::utils::commons::find_hostiles_nearby;
-use crate::utils::constants::MAX_POWER_CAPACITY;
+use crate::units::roles::{Kind, Role};
+use crate::utils::commons::{find_hostiles_nearby, find_walkable_positions_near_by};
+use crate::utils::constants::{
+    MAX_POWER_CAPACITY, PB_BOOST_POWER_THRESHOLD, PB_MAX_ENERGY_PER_POWER, PB_MAX_SQUADS,
+    PB_TICKS_PER_ROOM,
+};
+
+const POWER_BANK_HITS: u32 = 2_000_000;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PowerbankData {
@@ -21,33 +34,230 @@ pub struct PowerbankData {
     pub amount: u32,
     #[serde(default)]
     pub postponed_farm: bool,
+    /// rooms from home
+    #[serde(default)]
+    pub distance: u32,
+    #[serde(default)]
+    pub estimate: Option<PowerbankEstimate>,
+    #[serde(default)]
+    pub rival: Option<PowerbankRival>,
+    /// last seen bank hits with the game tick
+    #[serde(default)]
+    pub observed: Option<(u32, u32)>,
+    #[serde(default)]
+    pub decay_at: Option<u32>,
+    /// damage per tick from all attackers, measured between observations
+    #[serde(default)]
+    pub dps: u32,
+    /// spawn energy spent on the bank
+    #[serde(default)]
+    pub spent: u32,
+    /// power left in the ruin and on the ground after the bank is broken
+    #[serde(default)]
+    pub remaining: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerbankEstimate {
+    pub squads: u32,
+    pub boosted: bool,
+    /// planned damage per tick of all squads
+    pub dps: u32,
+    pub break_ticks: u32,
+    pub cost: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PowerbankRival {
+    pub owner: String,
+    pub dps: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stance {
+    /// the rival is stronger, let it break the bank and pick up the loot
+    Share,
+    /// we are stronger, keep reinforcing
+    Contest,
+    /// the rival breaks the bank before we arrive
+    GiveUp,
 }
 
 impl PowerbankData {
-    pub const fn new(id: ObjectId<StructurePowerBank>, pos: Position, amount: u32) -> Self {
-        Self { id, pos, amount, postponed_farm: false }
+    pub const fn new(
+        id: ObjectId<StructurePowerBank>,
+        pos: Position,
+        amount: u32,
+        distance: u32,
+    ) -> Self {
+        Self {
+            id,
+            pos,
+            amount,
+            postponed_farm: false,
+            distance,
+            estimate: None,
+            rival: None,
+            observed: None,
+            decay_at: None,
+            dps: 0,
+            spent: 0,
+            remaining: None,
+        }
     }
 
-    pub const fn postponed(id: ObjectId<StructurePowerBank>, pos: Position, amount: u32) -> Self {
-        Self { id, pos, amount, postponed_farm: true }
+    pub const fn postponed(
+        id: ObjectId<StructurePowerBank>,
+        pos: Position,
+        amount: u32,
+        distance: u32,
+    ) -> Self {
+        let mut data = Self::new(id, pos, amount, distance);
+        data.postponed_farm = true;
+        data
+    }
+
+    pub fn boosted(&self) -> bool {
+        self.estimate.is_some_and(|estimate| estimate.boosted)
+    }
+
+    fn hits(&self) -> u32 {
+        self.observed.map_or(POWER_BANK_HITS, |(_, hits)| hits)
+    }
+
+    fn ticks_to_decay(&self) -> u32 {
+        self.decay_at.map_or(0, |decay_at| decay_at.saturating_sub(game::time()))
+    }
+
+    // ticks from the spawn request to the bank
+    const fn travel_ticks(&self) -> u32 {
+        self.distance * PB_TICKS_PER_ROOM + CREEP_SPAWN_TIME * MAX_CREEP_SIZE
+    }
+
+    fn observe(&mut self, power_bank: &StructurePowerBank) {
+        let now = game::time();
+        if let Some((tick, hits)) = self.observed
+            && now > tick
+        {
+            self.dps = hits.saturating_sub(power_bank.hits()) / (now - tick);
+        }
+        self.observed = Some((now, power_bank.hits()));
+        self.decay_at = Some(now + power_bank.ticks_to_decay());
+        self.rival = power_bank.room().and_then(|room| find_rival(&room, power_bank));
+    }
+
+    fn stance(&self, invested: bool) -> Option<Stance> {
+        let rival = self.rival.as_ref()?;
+        let our_dps = self.estimate.map_or(0, |estimate| estimate.dps);
+
+        if !invested && rival.dps * self.ticks_to_decay() >= self.hits() {
+            Some(Stance::GiveUp)
+        } else if rival.dps > our_dps {
+            Some(Stance::Share)
+        } else {
+            Some(Stance::Contest)
+        }
+    }
+
+    // the cheapest composition breaking the bank before decay, None if the bank isn't worth it
+    fn estimate(&self, home: &Shelter) -> Option<PowerbankEstimate> {
+        let energy = home.room().energy_capacity_available();
+        let pb_a = Role::PBAttacker(PBAttacker::new(None, None));
+        let pb_h = Role::PBHealer(PBHealer::new(None, None));
+        let pb_c = Role::PBCarrier(PBCarrier::new(None, None));
+
+        let attack_parts = pb_a.parts_number(Part::Attack, energy);
+        let squad_cost = body_cost(&pb_a, energy) + body_cost(&pb_h, energy);
+        let carry_cost = body_cost(&pb_c, energy) * carriers(self.amount);
+        let travel = self.travel_ticks();
+        let work_ticks = CREEP_LIFE_TIME.checked_sub(travel)?;
+        // every attacker needs a free tile near the bank
+        let slots = u32::try_from(find_walkable_positions_near_by(self.pos, true).len())
+            .unwrap_or_default()
+            .min(PB_MAX_SQUADS);
+        let boost_stock = home.storage().map_or(0, |storage| {
+            storage.store().get_used_capacity(Some(ResourceType::CatalyzedUtriumAcid))
+        });
+
+        [false, true]
+            .into_iter()
+            .filter(|boosted| !boosted || self.amount >= PB_BOOST_POWER_THRESHOLD)
+            .flat_map(|boosted| (1..=slots).map(move |squads| (boosted, squads)))
+            .filter(|(boosted, squads)| {
+                !boosted || boost_stock >= attack_parts * squads * LAB_BOOST_MINERAL
+            })
+            .map(|(boosted, squads)| {
+                let ratio =
+                    if boosted { attack_ratio(ResourceType::CatalyzedUtriumAcid) } else { 1 };
+                let dps = squads * attack_parts * ATTACK_POWER * ratio;
+                let break_ticks = self.hits().div_ceil(dps.max(1));
+                let generations = break_ticks.div_ceil(work_ticks.max(1));
+                let cost = squads * generations * squad_cost + carry_cost;
+                PowerbankEstimate { squads, boosted, dps, break_ticks, cost }
+            })
+            .find(|estimate| {
+                // carriers arrive right on the break and need some time to pick up
+                travel + estimate.break_ticks + PB_TICKS_PER_ROOM <= self.ticks_to_decay()
+                    && estimate.cost <= self.amount * PB_MAX_ENERGY_PER_POWER
+            })
+    }
+
+    fn carriers_due(&self) -> bool {
+        self.hits().checked_div(self.dps).map_or_else(
+            || (self.hits() < 600_000 && self.rival.is_some()) || self.hits() < 400_000,
+            |ticks| ticks <= self.travel_ticks(),
+        )
+    }
+
+    fn commit(
+        &mut self,
+        meta: &mut Meta,
+        assignment: &mut Assignment,
+        home: &Shelter,
+        events: &mut SmallVec<[RoomEvent; 3]>,
+    ) {
+        if self.stance(false) == Some(Stance::GiveUp) {
+            info!("{} power bank {}: give up to {:?}", home.name(), self.id, self.rival);
+            meta.update(Status::Aborted);
+            return;
+        }
+
+        self.estimate = self.estimate(home);
+        if let Some(estimate) = self.estimate {
+            info!(
+                "{} power bank {}: {} power, plan: {:?}",
+                home.name(),
+                self.id,
+                self.amount,
+                estimate
+            );
+            if estimate.boosted {
+                events.push(RoomEvent::AddBoost(BoostReason::Powerbank, CREEP_LIFE_TIME));
+            }
+            for _ in 0..estimate.squads {
+                self.spawn_attack_squad(meta, assignment, home, events);
+            }
+        } else {
+            info!("{} power bank {}: {} power isn't worth it", home.name(), self.id, self.amount);
+            meta.update(Status::Aborted);
+        }
     }
 
     fn spawn_attack_squad(
-        &self,
+        &mut self,
         meta: &mut Meta,
         assignment: &mut Assignment,
-        home_name: RoomName,
-        events: &mut SmallVec<[RoomEvent; 3]>)
-    {
+        home: &Shelter,
+        events: &mut SmallVec<[RoomEvent; 3]>,
+    ) {
         if let Assignment::Squads(squads) = assignment {
-            let squad = Squad::new(self.id, squads.len() + 1);
+            let squad = Squad::new(self.id, squads.len() + 1).with_formation(Formation::Duo);
+
+            let pb_a = Role::PBAttacker(PBAttacker::new(Some(squad.id.clone()), Some(home.name())));
+            let pb_h = Role::PBHealer(PBHealer::new(Some(squad.id.clone()), Some(home.name())));
 
-            let pb_a = Role::PBAttacker(PBAttacker::new(
-                Some(squad.id.clone()),
-                Some(home_name),
-            ));
-            let pb_h =
-                Role::PBHealer(PBHealer::new(Some(squad.id.clone()), Some(home_name)));
+            let energy = home.room().energy_capacity_available();
+            self.spent += body_cost(&pb_a, energy) + body_cost(&pb_h, energy);
 
             squads.push(squad);
             events.push(RoomEvent::Spawn(pb_h, 1));
@@ -60,23 +270,22 @@ impl PowerbankData {
     }
 
     fn spawn_carry_squad(
-        &self,
+        &mut self,
         meta: &mut Meta,
         assignment: &mut Assignment,
-        home_name: RoomName,
-        events: &mut SmallVec<[RoomEvent; 3]>)
-    {
+        home: &Shelter,
+        events: &mut SmallVec<[RoomEvent; 3]>,
+    ) {
         if let Assignment::Squads(squads) = assignment {
             let squad = Squad::new(self.id, squads.len() + 1);
 
-            let pb_c =
-                Role::PBCarrier(PBCarrier::new(Some(squad.id.clone()), Some(home_name)));
+            let pb_c = Role::PBCarrier(PBCarrier::new(Some(squad.id.clone()), Some(home.name())));
+
+            let carriers = carriers(self.amount);
+            self.spent += body_cost(&pb_c, home.room().energy_capacity_available()) * carriers;
 
             squads.push(squad);
-            events.push(RoomEvent::Spawn(
-                pb_c,
-                ((self.amount + 800) / 1600) as usize, //(data.amount as f32 / 1600_f32).round() as usize,
-            ));
+            events.push(RoomEvent::Spawn(pb_c, carriers as usize));
 
             meta.update(Status::Carry);
         } else {
@@ -84,61 +293,136 @@ impl PowerbankData {
         }
     }
 
+    fn report(&self, home_name: RoomName) {
+        let collected = self.amount.saturating_sub(self.remaining.unwrap_or(self.amount));
+        info!(
+            "{} power bank {}: collected {} of {} power for {} energy",
+            home_name, self.id, collected, self.amount, self.spent
+        );
+    }
 }
 
 pub(in crate::rooms::state::requests) fn powerbank_handler(
-    data: &PowerbankData,
+    data: &mut PowerbankData,
     meta: &mut Meta,
     assignment: &mut Assignment,
     home: &Shelter,
 ) -> SmallVec<[RoomEvent; 3]> {
     let mut events: SmallVec<[RoomEvent; 3]> = SmallVec::new();
 
-    if meta.created_at + 5200 > game::time() {
-        match meta.status {
-            Status::Created
-                if home.storage().is_some_and(|storage| {
-                    storage.store().get_used_capacity(Some(ResourceType::Power))
-                        > MAX_POWER_CAPACITY
-                }) =>
-            {
-                meta.update(Status::Aborted);
-            }
-            Status::Created if data.postponed_farm => {
-                if let Some(power_bank) = data.id.resolve() {
-                    if power_bank.hits() == power_bank.hits_max()
-                        && power_bank.ticks_to_decay() < 4300
-                    {
-                        data.spawn_attack_squad(meta, assignment, home.name(), &mut events);
-                    } else if power_bank.hits() < power_bank.hits_max() {
-                        meta.update(Status::Aborted);
-                    }
+    if meta.created_at + 5200 <= game::time() {
+        data.report(home.name());
+        meta.update(Status::Resolved);
+        return events;
+    }
+
+    let power_bank = data.id.resolve();
+    if let Some(power_bank) = power_bank.as_ref() {
+        data.observe(power_bank);
+    } else if let Some(room) = game::rooms().get(data.pos.room_name()) {
+        data.remaining = Some(power_left(&room, data.pos));
+    }
+    if data.decay_at.is_none() {
+        // the bank was reported with more than 4000 ticks to decay
+        data.decay_at = Some(meta.created_at + 4000);
+    }
+
+    let invested = assignment.has_alive_members();
+    match meta.status {
+        Status::Created
+            if home.storage().is_some_and(|storage| {
+                storage.store().get_used_capacity(Some(ResourceType::Power)) > MAX_POWER_CAPACITY
+            }) =>
+        {
+            meta.update(Status::Aborted);
+        }
+        Status::Created if data.postponed_farm => {
+            if let Some(power_bank) = power_bank {
+                if power_bank.hits() == power_bank.hits_max() && power_bank.ticks_to_decay() < 4300
+                {
+                    data.commit(meta, assignment, home, &mut events);
+                } else if power_bank.hits() < power_bank.hits_max() {
+                    meta.update(Status::Aborted);
                 }
             }
-            Status::Created => {
-                data.spawn_attack_squad(meta, assignment, home.name(), &mut events);
-            }
-            Status::InProgress if meta.updated_at + 1350 < game::time() => {
-                data.spawn_attack_squad(meta, assignment, home.name(), &mut events);
-            }
-            Status::InProgress => {
-                if let Some(power_bank) = data.id.resolve() {
-                    //todo calculate distance
-                    let pb_room = power_bank.room().expect("expect powerbank is in a room");
-                    let another_attacker =
-                        find_hostiles_nearby(&pb_room, vec![Part::Attack], &power_bank).count();
-
-                    if (power_bank.hits() < 600_000 && another_attacker > 0)
-                        || power_bank.hits() < 400_000
-                    {
-                        data.spawn_carry_squad(meta, assignment, home.name(), &mut events);
-                    }
-                }
+        }
+        Status::Created => {
+            data.commit(meta, assignment, home, &mut events);
+        }
+        Status::InProgress if data.remaining == Some(0) => {
+            info!("{} power bank {}: looted by somebody else", home.name(), data.id);
+            meta.update(Status::Resolved);
+        }
+        Status::InProgress if data.remaining.is_some_and(|amount| amount > 0) => {
+            // broken by somebody else
+            data.spawn_carry_squad(meta, assignment, home, &mut events);
+        }
+        Status::InProgress if data.stance(invested) == Some(Stance::GiveUp) => {
+            info!("{} power bank {}: give up to {:?}", home.name(), data.id, data.rival);
+            meta.update(Status::Aborted);
+        }
+        Status::InProgress if power_bank.is_some() && data.carriers_due() => {
+            data.spawn_carry_squad(meta, assignment, home, &mut events);
+        }
+        Status::InProgress
+            if meta.updated_at + 1350 < game::time()
+                && data.stance(invested) != Some(Stance::Share) =>
+        {
+            for _ in 0..data.estimate.map_or(1, |estimate| estimate.squads) {
+                data.spawn_attack_squad(meta, assignment, home, &mut events);
             }
-            _ => {}
         }
-    } else {
-        meta.update(Status::Resolved);
+        Status::Carry if data.remaining == Some(0) => {
+            data.report(home.name());
+            meta.update(Status::Resolved);
+        }
+        _ => {}
     }
     events
 }
+
+fn find_rival(room: &Room, power_bank: &StructurePowerBank) -> Option<PowerbankRival> {
+    let attackers: Vec<Creep> =
+        find_hostiles_nearby(room, vec![Part::Attack], power_bank).collect();
+    let owner = attackers.first()?.owner().username();
+    let dps = attackers
+        .iter()
+        .flat_map(Creep::body)
+        .filter(|bodypart| bodypart.part() == Part::Attack && bodypart.hits() > 0)
+        .map(|bodypart| ATTACK_POWER * bodypart.boost().map_or(1, attack_ratio))
+        .sum();
+    Some(PowerbankRival { owner, dps })
+}
+
+fn power_left(room: &Room, pos: Position) -> u32 {
+    let in_ruins: u32 = room
+        .find(find::RUINS, None)
+        .iter()
+        .filter(|ruin| ruin.pos().is_near_to(pos))
+        .map(|ruin| ruin.store().get_used_capacity(Some(ResourceType::Power)))
+        .sum();
+    let dropped: u32 = room
+        .find(find::DROPPED_RESOURCES, None)
+        .iter()
+        .filter(|resource| {
+            resource.resource_type() == ResourceType::Power && resource.pos().in_range_to(pos, 3)
+        })
+        .map(screeps::Resource::amount)
+        .sum();
+    in_ruins + dropped
+}
+
+const fn carriers(amount: u32) -> u32 {
+    (amount + 800) / 1600
+}
+
+fn body_cost(role: &Role, energy: u32) -> u32 {
+    role.body(energy).iter().map(|part| part.cost()).sum()
+}
+
+fn attack_ratio(resource: ResourceType) -> u32 {
+    match resource.boost() {
+        Some(Boost::Attack(ratio)) => ratio,
+        _ => 1,
+    }
+}
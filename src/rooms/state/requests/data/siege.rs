@@ -0,0 +1,485 @@
+use std::collections::{HashMap, HashSet};
+
+use log::{info, warn};
+use screeps::pathfinder::MultiRoomCostResult;
+use screeps::{
+    Boost, CostMatrix, Creep, DISMANTLE_POWER, ExitDirection, HEAL_POWER, HasHits, HasId,
+    HasPosition, LocalCostMatrix, ObjectId, OwnedStructureProperties, Part, Position, ResourceType,
+    Room, RoomCoordinate, RoomName, Structure, StructureObject, StructureType, TOWER_ENERGY_COST,
+    TOWER_POWER_ATTACK, Terrain, find, game,
+};
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+
+use crate::movement::find_path;
+use crate::movement::formation::Formation;
+use crate::rooms::RoomEvent;
+use crate::rooms::shelter::Shelter;
+use crate::rooms::state::BoostReason;
+use crate::rooms::state::requests::assignment::Squad;
+use crate::rooms::state::requests::{Assignment, Meta, Status};
+use crate::units::roles::Role;
+use crate::units::roles::services::scout::Scout;
+use crate::units::roles::teams::com_d::ComDismantler;
+use crate::units::roles::teams::com_h::ComHealer;
+use crate::units::tasks::Task;
+use crate::utils::constants::{
+    SIEGE_BREACH_TICKS, SIEGE_HITS_PER_COST, SIEGE_MAX_DISMANTLERS, SIEGE_MAX_HEALERS,
+    SIEGE_MAX_SQUADS, SIEGE_RETREAT_PERCENT, SIEGE_STAGING_RANGE,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SiegeStage {
+    /// waiting for the target room vision
+    #[default]
+    Scouting,
+    Assembling,
+    Breaching,
+    Retreating,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SiegeIntel {
+    pub owner: Option<String>,
+    /// towers with enough energy to shoot
+    pub towers: u32,
+    pub safe_mode: Option<u32>,
+    pub scanned_at: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct SiegeComposition {
+    pub dismantlers: u32,
+    pub healers: u32,
+    pub boosted: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SiegeTarget {
+    pub id: ObjectId<Structure>,
+    /// the tile to dismantle from, equals to the target position for the structures inside
+    pub workplace: Position,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SiegePlan {
+    pub staging: Position,
+    pub breach: Option<Position>,
+    /// barriers on the breach path first, then towers and spawns
+    pub targets: Vec<SiegeTarget>,
+    pub retreat: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SiegeData {
+    pub target: RoomName,
+    #[serde(default)]
+    pub stage: SiegeStage,
+    #[serde(default)]
+    pub intel: Option<SiegeIntel>,
+    #[serde(default)]
+    pub composition: Option<SiegeComposition>,
+    #[serde(default)]
+    pub plan: Option<SiegePlan>,
+}
+
+impl SiegeData {
+    pub const fn new(target: RoomName) -> Self {
+        Self { target, stage: SiegeStage::Scouting, intel: None, composition: None, plan: None }
+    }
+
+    pub fn needs_vision(&self) -> bool {
+        self.plan.is_none() || self.intel.as_ref().is_some_and(|intel| intel.safe_mode.is_some())
+    }
+
+    pub fn dismantler_task(&self, members: HashSet<String>) -> Task {
+        self.plan
+            .as_ref()
+            .and_then(|plan| match self.stage {
+                SiegeStage::Breaching => plan
+                    .targets
+                    .first()
+                    .map(|target| Task::Breach(target.id, target.workplace, members, plan.retreat)),
+                _ => Some(Task::Assemble(plan.staging)),
+            })
+            .unwrap_or_default()
+    }
+
+    fn scan(&mut self, room: &Room) {
+        let controller = room.controller();
+        let towers = room
+            .find(find::HOSTILE_STRUCTURES, None)
+            .into_iter()
+            .filter(|structure| match structure {
+                StructureObject::StructureTower(tower) => {
+                    tower.store().get_used_capacity(Some(ResourceType::Energy)) >= TOWER_ENERGY_COST
+                }
+                _ => false,
+            })
+            .count();
+
+        self.intel = Some(SiegeIntel {
+            owner: controller
+                .as_ref()
+                .and_then(OwnedStructureProperties::owner)
+                .map(|owner| owner.username()),
+            towers: u32::try_from(towers).unwrap_or_default(),
+            safe_mode: controller.as_ref().and_then(screeps::StructureController::safe_mode),
+            scanned_at: game::time(),
+        });
+
+        if let Some(plan) = self.plan.as_mut() {
+            plan.targets.retain(|target| target.id.resolve().is_some());
+        }
+    }
+
+    fn spawn_squad(
+        &self,
+        composition: SiegeComposition,
+        meta: &mut Meta,
+        assignment: &mut Assignment,
+        home_name: RoomName,
+        events: &mut SmallVec<[RoomEvent; 3]>,
+    ) {
+        if let Assignment::Squads(squads) = assignment {
+            let formation = match composition.dismantlers + composition.healers {
+                2 => Formation::Duo,
+                4 => Formation::Quad,
+                _ => Formation::Loose,
+            };
+            let squad = Squad::new(self.target, squads.len() + 1).with_formation(formation);
+
+            let dismantler = Role::CombatDismantler(
+                ComDismantler::new(Some(squad.id.clone()), Some(home_name))
+                    .with_boosts(composition.boosted),
+            );
+            let healer = Role::CombatHealer(
+                ComHealer::new(Some(squad.id.clone()), Some(home_name))
+                    .with_boosts(composition.boosted),
+            );
+
+            squads.push(squad);
+            events.push(RoomEvent::Spawn(dismantler, composition.dismantlers as usize));
+            events.push(RoomEvent::Spawn(healer, composition.healers as usize));
+            if composition.boosted {
+                events.push(RoomEvent::AddBoost(BoostReason::Pvp, 1500));
+                events.push(RoomEvent::AddBoost(BoostReason::Dismantle, 1500));
+            }
+
+            meta.update(Status::Spawning);
+        } else {
+            warn!("creation new squad error: {:?}", self);
+        }
+    }
+}
+
+pub(in crate::rooms::state::requests) fn siege_handler(
+    data: &mut SiegeData,
+    meta: &mut Meta,
+    assignment: &mut Assignment,
+    home: &Shelter,
+) -> SmallVec<[RoomEvent; 3]> {
+    let mut events: SmallVec<[RoomEvent; 3]> = SmallVec::new();
+
+    let room = game::rooms().get(data.target);
+    if let Some(room) = room.as_ref() {
+        data.scan(room);
+    }
+
+    if data.plan.as_ref().is_some_and(|plan| plan.targets.is_empty()) {
+        info!("{} siege of {} is completed", home.name(), data.target);
+        meta.update(Status::Resolved);
+        return events;
+    }
+
+    match meta.status {
+        Status::Created | Status::OnHold
+            if data.intel.as_ref().is_some_and(|intel| intel.safe_mode.is_some()) =>
+        {
+            meta.update(Status::OnHold);
+        }
+        Status::Created | Status::OnHold => {
+            if data.plan.is_none()
+                && let Some(room) = room.as_ref()
+            {
+                data.plan = build_plan(home.name(), room);
+                if data.plan.is_none() {
+                    warn!("{} siege of {}: no breach path found", home.name(), data.target);
+                    meta.update(Status::Aborted);
+                    return events;
+                }
+            }
+
+            let (Some(plan), Some(intel)) = (data.plan.as_ref(), data.intel.as_ref()) else {
+                if meta.updated_at + 10 < game::time() && game::time().is_multiple_of(100) {
+                    // no observer in range, the room is still invisible
+                    let center = Position::new(
+                        RoomCoordinate::new(25).expect("valid coordinate"),
+                        RoomCoordinate::new(25).expect("valid coordinate"),
+                        data.target,
+                    );
+                    events.push(RoomEvent::MayBeSpawn(Role::Scout(Scout::new(
+                        Some(home.name()),
+                        Some(center),
+                    ))));
+                }
+                return events;
+            };
+
+            let squads = match assignment {
+                Assignment::Squads(squads) => squads.len(),
+                _ => 0,
+            };
+            if squads >= SIEGE_MAX_SQUADS {
+                warn!("{} siege of {}: {} squads lost, abort", home.name(), data.target, squads);
+                meta.update(Status::Aborted);
+                return events;
+            }
+
+            // barriers are dismantled from the tile in front of them
+            let breach_hits = plan
+                .targets
+                .iter()
+                .filter_map(|target| target.id.resolve().map(|structure| (target, structure)))
+                .filter(|(target, structure)| target.workplace != structure.pos())
+                .map(|(_, structure)| structure.hits())
+                .sum();
+
+            if let Some(composition) =
+                compose(intel, breach_hits, home.room().energy_capacity_available())
+            {
+                info!(
+                    "{} siege of {}: intel: {:?}, squad: {:?}",
+                    home.name(),
+                    data.target,
+                    intel,
+                    composition
+                );
+                data.composition = Some(composition);
+                data.stage = SiegeStage::Assembling;
+                data.spawn_squad(composition, meta, assignment, home.name(), &mut events);
+            } else {
+                warn!(
+                    "{} siege of {}: towers are too strong {:?}",
+                    home.name(),
+                    data.target,
+                    intel
+                );
+                meta.update(Status::Aborted);
+            }
+        }
+        Status::InProgress
+            if game::time().is_multiple_of(100) && !assignment.has_alive_members() =>
+        {
+            // squad was lost, the next one is planned with fresh intel
+            data.stage = SiegeStage::Scouting;
+            meta.update(Status::Created);
+        }
+        Status::InProgress => {
+            let (Some(plan), Some(composition)) = (data.plan.as_ref(), data.composition) else {
+                return events;
+            };
+            let squad: Vec<Creep> = match assignment {
+                Assignment::Squads(squads) => squads.last().map_or_else(Vec::new, |squad| {
+                    squad
+                        .members
+                        .iter()
+                        .filter_map(|name| game::creeps().get(name.clone()))
+                        .collect()
+                }),
+                _ => Vec::new(),
+            };
+            let gathered = squad
+                .iter()
+                .all(|member| member.pos().get_range_to(plan.staging) <= SIEGE_STAGING_RANGE);
+
+            data.stage = match data.stage {
+                SiegeStage::Assembling
+                    if squad.len() >= (composition.dismantlers + composition.healers) as usize
+                        && gathered =>
+                {
+                    SiegeStage::Breaching
+                }
+                SiegeStage::Breaching
+                    if squad.iter().any(|member| {
+                        member.hits() * 100 < member.hits_max() * u32::from(plan.retreat)
+                    }) =>
+                {
+                    SiegeStage::Retreating
+                }
+                SiegeStage::Retreating
+                    if gathered
+                        && squad.iter().all(|member| member.hits() == member.hits_max()) =>
+                {
+                    SiegeStage::Breaching
+                }
+                stage => stage,
+            };
+        }
+        _ => {}
+    }
+    events
+}
+
+// squad that outheals the towers and breaches the path in time, unboosted one is preferred
+fn compose(intel: &SiegeIntel, breach_hits: u32, energy: u32) -> Option<SiegeComposition> {
+    let heal_parts =
+        Role::CombatHealer(ComHealer::new(None, None)).parts_number(Part::Heal, energy);
+    let work_parts =
+        Role::CombatDismantler(ComDismantler::new(None, None)).parts_number(Part::Work, energy);
+    let damage = intel.towers * TOWER_POWER_ATTACK;
+
+    [false, true].into_iter().find_map(|boosted| {
+        let (tough, heal, dismantle) = if boosted {
+            (
+                boost_ratio(ResourceType::CatalyzedGhodiumAlkalide),
+                boost_ratio(ResourceType::CatalyzedLemergiumAlkalide),
+                boost_ratio(ResourceType::CatalyzedZynthiumAcid),
+            )
+        } else {
+            (1., 1., 1.)
+        };
+
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            clippy::cast_precision_loss
+        )]
+        let (damage, heal_power, dismantle_power) = (
+            (damage as f32 * tough) as u32,
+            (heal_parts as f32 * HEAL_POWER as f32 * heal) as u32,
+            (work_parts as f32 * DISMANTLE_POWER as f32 * dismantle) as u32,
+        );
+
+        let healers = damage.div_ceil(heal_power.max(1)).max(1);
+        (healers <= SIEGE_MAX_HEALERS).then(|| SiegeComposition {
+            dismantlers: breach_hits
+                .div_ceil(dismantle_power.max(1) * SIEGE_BREACH_TICKS)
+                .clamp(1, SIEGE_MAX_DISMANTLERS),
+            healers,
+            boosted,
+        })
+    })
+}
+
+fn boost_ratio(resource: ResourceType) -> f32 {
+    #[allow(clippy::cast_precision_loss)]
+    match resource.boost() {
+        Some(Boost::Heal(ratio) | Boost::Dismantle(ratio)) => ratio as f32,
+        Some(Boost::Tough(ratio)) => ratio,
+        _ => 1.,
+    }
+}
+
+fn build_plan(home_name: RoomName, room: &Room) -> Option<SiegePlan> {
+    let core = room
+        .find(find::HOSTILE_SPAWNS, None)
+        .first()
+        .map(HasPosition::pos)
+        .or_else(|| room.controller().map(|ctrl| ctrl.pos()))?;
+    let staging = staging_position(home_name, room.name(), core)?;
+
+    let mut barriers: HashMap<Position, Vec<ObjectId<Structure>>> = HashMap::new();
+    let mut matrix = LocalCostMatrix::new();
+    let structures = room.find(find::STRUCTURES, None);
+    for structure in &structures {
+        let pos = structure.pos();
+        match structure {
+            StructureObject::StructureRoad(_) | StructureObject::StructureContainer(_) => {}
+            StructureObject::StructureRampart(rampart) if rampart.my() => {}
+            StructureObject::StructureRampart(_) | StructureObject::StructureWall(_) => {
+                let hits = structure.as_attackable().map_or(0, HasHits::hits);
+                barriers.entry(pos).or_default().push(structure.as_structure().id());
+                let cost = (hits / SIEGE_HITS_PER_COST).clamp(5, 250);
+                if matrix.get(pos.xy()) < 0xff {
+                    matrix.set(pos.xy(), u8::try_from(cost).unwrap_or(250));
+                }
+            }
+            _ => matrix.set(pos.xy(), 0xff),
+        }
+    }
+
+    let target_room = room.name();
+    let cost_matrix: CostMatrix = matrix.into();
+    let search = find_path(staging, core, 1, move |room_name: RoomName| {
+        if room_name == target_room {
+            MultiRoomCostResult::CostMatrix(cost_matrix.clone())
+        } else {
+            MultiRoomCostResult::Default
+        }
+    });
+    if search.incomplete() {
+        return None;
+    }
+
+    let mut targets = Vec::new();
+    let mut breach = None;
+    let mut previous = staging;
+    for pos in search.path() {
+        if let Some(ids) = barriers.get(&pos) {
+            breach.get_or_insert(pos);
+            targets.extend(ids.iter().map(|id| SiegeTarget { id: *id, workplace: previous }));
+        }
+        previous = pos;
+    }
+
+    let from = breach.unwrap_or(staging);
+    let mut inner: Vec<(u8, u32, SiegeTarget)> = structures
+        .iter()
+        .filter_map(|structure| {
+            let priority = match structure.structure_type() {
+                StructureType::Tower => 0,
+                StructureType::Spawn => 1,
+                _ => return None,
+            };
+            (!structure.as_owned().is_some_and(OwnedStructureProperties::my)).then(|| {
+                let pos = structure.pos();
+                let target = SiegeTarget { id: structure.as_structure().id(), workplace: pos };
+                (priority, from.get_range_to(pos), target)
+            })
+        })
+        .collect();
+    inner.sort_by_key(|(priority, range, _)| (*priority, *range));
+    targets.extend(inner.into_iter().map(|(_, _, target)| target));
+
+    info!(
+        "siege plan for {}: staging: {}, breach: {:?}, targets: {}",
+        target_room,
+        staging,
+        breach,
+        targets.len()
+    );
+    Some(SiegePlan { staging, breach, targets, retreat: SIEGE_RETREAT_PERCENT })
+}
+
+// a walkable tile in the neighbour room on the route, close to the exit facing the target
+fn staging_position(home_name: RoomName, target: RoomName, core: Position) -> Option<Position> {
+    let route = game::map::find_route(
+        home_name,
+        target,
+        None::<game::map::FindRouteOptions<fn(RoomName, RoomName) -> f64>>,
+    )
+    .ok()?;
+    let exit = route.last()?.exit;
+    let staging_room = route.iter().rev().nth(1).map_or(home_name, |step| step.room);
+    let terrain = game::map::get_room_terrain(staging_room)?;
+
+    let offset = u8::try_from(SIEGE_STAGING_RANGE).unwrap_or(3);
+    let (core_x, core_y) = (core.x().u8(), core.y().u8());
+    (2..48_u8)
+        .map(|i| match exit {
+            ExitDirection::Top => (i, offset, i.abs_diff(core_x)),
+            ExitDirection::Bottom => (i, 49 - offset, i.abs_diff(core_x)),
+            ExitDirection::Left => (offset, i, i.abs_diff(core_y)),
+            ExitDirection::Right => (49 - offset, i, i.abs_diff(core_y)),
+        })
+        .filter(|(x, y, _)| terrain.get(*x, *y) != Terrain::Wall)
+        .min_by_key(|(_, _, distance)| *distance)
+        .and_then(|(x, y, _)| {
+            Some(Position::new(
+                RoomCoordinate::new(x).ok()?,
+                RoomCoordinate::new(y).ok()?,
+                staging_room,
+            ))
+        })
+}
@@ -1,6 +1,8 @@
 use screeps::game;
 use serde::{Deserialize, Serialize};
 
+use crate::utils::constants::REQUEST_HISTORY_SIZE;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Meta {
     #[serde(default)]
@@ -9,11 +11,26 @@ pub struct Meta {
     pub created_at: u32,
     #[serde(default = "game::time")]
     pub updated_at: u32,
+    /// previous statuses with the tick they were left at
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<(Status, u32)>,
+    /// why the watchdog reset or aborted the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// recalculated by the room every tick, the higher the earlier it's served
+    #[serde(skip)]
+    pub priority: u8,
 }
 
 impl Meta {
     pub fn update(&mut self, status: Status) {
-        self.status = status;
+        if self.status != status {
+            let previous = std::mem::replace(&mut self.status, status);
+            self.history.push((previous, game::time()));
+            if self.history.len() > REQUEST_HISTORY_SIZE {
+                self.history.remove(0);
+            }
+        }
         self.updated_at = game::time();
     }
 
@@ -24,7 +41,14 @@ impl Meta {
 
 impl Default for Meta {
     fn default() -> Self {
-        Self { status: Status::Created, created_at: game::time(), updated_at: game::time() }
+        Self {
+            status: Status::Created,
+            created_at: game::time(),
+            updated_at: game::time(),
+            history: Vec::new(),
+            note: None,
+            priority: 0,
+        }
     }
 }
 
@@ -0,0 +1,48 @@
+use screeps::{HasId, game};
+
+use super::{Request, RequestKind};
+use crate::rooms::shelter::Shelter;
+use crate::utils::constants::REQUEST_AGE_STEP;
+
+// the importance of the kind alone
+const fn base(kind: &RequestKind) -> u8 {
+    match kind {
+        RequestKind::SafeMode(_) => 90,
+        RequestKind::Defend(_) | RequestKind::Protect(_) => 80,
+        RequestKind::Crash(_) | RequestKind::Caravan(_) => 60,
+        RequestKind::Carry(_) | RequestKind::Withdraw(_) | RequestKind::Pickup(_) => 30,
+        RequestKind::Build(_) => 25,
+        RequestKind::Repair(_) => 20,
+        RequestKind::Lab(_) | RequestKind::Factory(_) | RequestKind::Transfer(_) => 15,
+        _ => 10,
+    }
+}
+
+// towers under attack and the empty spawn block can't wait
+fn urgency(request: &Request, home: &Shelter) -> u8 {
+    match &request.kind {
+        RequestKind::Carry(d) => {
+            let base = &home.base;
+            let room = &base.room;
+            if base.towers.iter().any(|tower| tower.raw_id() == d.to) {
+                if home.invasion() { 50 } else { 10 }
+            } else if base.spawns.iter().any(|spawn| spawn.raw_id() == d.to)
+                || base.extensions.iter().any(|extension| extension.raw_id() == d.to)
+            {
+                if room.energy_available() * 2 < room.energy_capacity_available() { 40 } else { 20 }
+            } else {
+                0
+            }
+        }
+        RequestKind::Repair(_) if home.invasion() => 30,
+        _ => 0,
+    }
+}
+
+/// Kind, urgency and age of the request folded into one number
+pub fn priority(request: &Request, home: &Shelter) -> u8 {
+    let age = game::time().saturating_sub(request.created_at()) / REQUEST_AGE_STEP;
+    base(&request.kind)
+        .saturating_add(urgency(request, home))
+        .saturating_add(u8::try_from(age.min(10)).unwrap_or_default())
+}
@@ -0,0 +1,91 @@
+use screeps::game;
+
+use super::assignment::Assignment;
+use super::meta::Status;
+use super::{Request, RequestKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    /// back to `Created` for another doer
+    Reset,
+    Abort,
+}
+
+// ticks a request may stay in the status without an update and what to do after
+const fn policy(kind: &RequestKind, status: &Status) -> Option<(u32, Action)> {
+    match (kind, status) {
+        (
+            RequestKind::Pickup(_) | RequestKind::Withdraw(_) | RequestKind::Carry(_),
+            Status::InProgress,
+        ) => Some((300, Action::Reset)),
+        (
+            RequestKind::Pickup(_) | RequestKind::Withdraw(_) | RequestKind::Carry(_),
+            Status::Created,
+        ) => Some((1_500, Action::Abort)),
+        (
+            RequestKind::Build(_) | RequestKind::Repair(_) | RequestKind::Dismantle(_),
+            Status::InProgress,
+        ) => Some((1_500, Action::Reset)),
+        (
+            RequestKind::Transfer(_) | RequestKind::Factory(_),
+            Status::InProgress | Status::Carry,
+        ) => Some((3_000, Action::Abort)),
+        (
+            RequestKind::Claim(_)
+            | RequestKind::Book(_)
+            | RequestKind::LongRangeWithdraw(_)
+            | RequestKind::Crash(_)
+            | RequestKind::SafeMode(_),
+            Status::Spawning | Status::InProgress,
+        ) => Some((5_000, Action::Abort)),
+        _ => None,
+    }
+}
+
+/// Resets requests left by dead doers and applies the timeout policy, returns why the request
+/// was touched
+pub fn inspect(request: &mut Request) -> Option<String> {
+    if request.meta.is_finished() {
+        return None;
+    }
+
+    let dead = match request.meta.status {
+        Status::InProgress | Status::Carry | Status::Boosting | Status::Finishing => {
+            request.assignment.remove_dead()
+        }
+        _ => Vec::new(),
+    };
+    if !dead.is_empty() && !request.assignment.has_any_members() {
+        return Some(reset(request, format!("dead assignees: {}", dead.join(", "))));
+    }
+
+    let (timeout, action) = policy(&request.kind, &request.meta.status)?;
+    let idle = game::time().saturating_sub(request.meta.updated_at);
+    if idle <= timeout {
+        return None;
+    }
+
+    let reason = format!("{:?} for {} ticks, timeout {}", request.meta.status, idle, timeout);
+    Some(match action {
+        Action::Reset => reset(request, reason),
+        Action::Abort => {
+            request.meta.update(Status::Aborted);
+            request.meta.note = Some(format!("aborted, {reason}"));
+            format!("aborted {}: {reason}", request.kind.name())
+        }
+    })
+}
+
+fn reset(request: &mut Request, reason: String) -> String {
+    // the doers still alive keep working on it, a dead assignment is cleared for the next doer
+    if !request.assignment.has_alive_members() {
+        match &mut request.assignment {
+            Assignment::Single(slot) => *slot = None,
+            Assignment::Multi(set) => set.clear(),
+            Assignment::None | Assignment::Squads(_) => {}
+        }
+    }
+    request.meta.update(Status::Created);
+    request.meta.note = Some(format!("reset, {reason}"));
+    format!("reset {}: {reason}", request.kind.name())
+}
@@ -4,7 +4,7 @@ use log::debug;
 use screeps::{
     ConstructionSite, Creep, Event, HasHits, HasId, HasPosition, INVADER_USERNAME, MaybeHasId,
     Mineral, Nuke, Part, PowerCreep, RESOURCES_ALL, RawObjectId, Resource, ResourceType, Room,
-    RoomName, SharedCreepProperties, Source, StructureContainer, StructureController,
+    RoomName, RoomXY, SharedCreepProperties, Source, StructureContainer, StructureController,
     StructureExtension, StructureFactory, StructureNuker, StructureObject, StructureObserver,
     StructurePowerSpawn, StructureRoad, StructureSpawn, StructureStorage, StructureTerminal,
     StructureTower, StructureWall, Tombstone, find, game,
@@ -25,19 +25,24 @@ use crate::rooms::{
     },
     wrappers::{
         Fillable,
-        claimed::structures::{labs::Labs, links::Links, ramparts::Ramparts},
+        claimed::structures::{
+            labs::Labs,
+            links::Links,
+            ramparts::{Ramparts, weakest},
+        },
     },
 };
 use crate::units::{
     creeps::CreepMemory,
     roles::{Role, combat::guard::Guard},
 };
-use crate::utils::constants::{
-    MAX_WALL_HITS, MIN_PERIMETR_HITS, MY_ROOMS_PICKUP_RESOURCE_THRESHOLD,
-};
+use crate::utils::constants::MY_ROOMS_PICKUP_RESOURCE_THRESHOLD;
 
+mod safe_mode;
 mod structures;
 
+pub(crate) use structures::ramparts::Fortification;
+
 //todo implement prelude.rs
 pub(crate) struct Claimed {
     pub(crate) room: Room,
@@ -344,12 +349,20 @@ impl Claimed {
         requests.into_iter()
     }
 
-    pub fn repair_walls(&self) -> impl Iterator<Item = Request> {
-        let mut requests = Vec::new();
-
-        for wall in &self.walls {
-            if wall.hits() < MAX_WALL_HITS {
-                requests.push(Request::new(
+    pub fn repair_walls(
+        &self,
+        fortification: &Fortification,
+        threats: &[(RoomXY, u32)],
+    ) -> impl Iterator<Item = Request> {
+        let critical = self.critical_structures();
+        let target = |wall: &StructureWall| {
+            let xy = wall.pos().xy();
+            fortification.target(xy, &critical, threats.iter().any(|(threat, _)| *threat == xy))
+        };
+        weakest(self.walls.iter(), target)
+            .into_iter()
+            .map(|wall| {
+                Request::new(
                     RequestKind::Repair(RepairData::with_max_attempts_and_hits(
                         wall.id().into_type(),
                         wall.pos(),
@@ -357,10 +370,10 @@ impl Claimed {
                         wall.hits(),
                     )),
                     Assignment::Single(None),
-                ));
-            }
-        }
-        requests.into_iter()
+                )
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
     }
 
     //todo logic similar to logic for towers
@@ -371,7 +384,7 @@ impl Claimed {
     ) -> impl Iterator<Item = RoomEvent> {
         self.invasion_check(room_memory, creeps)
             .into_iter()
-            .chain(self.perimetr_check())
+            .chain(self.safe_mode_check())
             .chain((!self.nukes.is_empty()).then(|| RoomEvent::NukeFalling))
     }
 
@@ -381,67 +394,45 @@ impl Claimed {
         creeps: &HashMap<String, CreepMemory>,
     ) -> SmallVec<[RoomEvent; 4]> {
         let mut events: SmallVec<[RoomEvent; 4]> = SmallVec::new();
-        match self.controller.level() {
-            //todo create different claimed room types
-            ..=3 => {
-                //any parts
-                if self.hostiles.iter().any(|hostile| {
-                    hostile.owner().username() != INVADER_USERNAME
-                        && has_part(
-                            &[Part::Attack, Part::RangedAttack, Part::Claim],
-                            hostile,
-                            false,
-                        )
-                }) {
-                    events
-                        .push(RoomEvent::Intrusion(Some(format!("{} Invasion!", self.get_name()))));
-                    let _ = self.controller.activate_safe_mode();
-                }
+        let level = self.controller.level();
+        //todo create different claimed room types
+        if level <= 3 {
+            //any parts
+            if self.hostiles.iter().any(|hostile| {
+                hostile.owner().username() != INVADER_USERNAME
+                    && has_part(&[Part::Attack, Part::RangedAttack, Part::Claim], hostile, false)
+            }) {
+                events.push(RoomEvent::Intrusion(Some(format!("{} Invasion!", self.get_name()))));
             }
-            4..=6 => {
-                if !find_player_boosted_creeps(&self.hostiles).is_empty() && self.towers.is_empty()
-                {
-                    let _ = self.controller.activate_safe_mode();
-                }
-            }
-            _ => {
-                let boosted_enemies = find_player_boosted_creeps(&self.hostiles);
+        } else if level >= 7 {
+            let boosted_enemies = find_player_boosted_creeps(&self.hostiles);
 
-                if !boosted_enemies.is_empty() && !self.spawns.is_empty() {
-                    events.push(RoomEvent::AddBoost(BoostReason::Invasion, 5000));
-                    let guard = Role::Guard(Guard::new(Some(self.get_name())));
+            if !boosted_enemies.is_empty() && !self.spawns.is_empty() {
+                events.push(RoomEvent::AddBoost(BoostReason::Invasion, 5000));
+                let guard = Role::Guard(Guard::new(Some(self.get_name())));
 
-                    let alive_number = room_memory.find_roles(&guard, creeps).count();
-                    let to_spawn = if alive_number > 0 { 0 } else { 1 };
-                    debug!(
-                        "{} alive guards: {} boosted_enemies: {}, to spawn: {}",
-                        self.get_name(),
-                        alive_number,
-                        boosted_enemies.len(),
-                        to_spawn
-                    );
-                    if to_spawn > 0 {
-                        events.push(RoomEvent::Spawn(guard, to_spawn));
-                    }
-                    events.push(RoomEvent::Intrusion(Some(format!(
-                        "Boosted player invasion in room {}!!",
-                        self.get_name()
-                    ))));
-                } else if room_memory.intrusion && room_memory.last_intrusion + 100 < game::time() {
-                    events.push(RoomEvent::Intrusion(None));
+                let alive_number = room_memory.find_roles(&guard, creeps).count();
+                let to_spawn = if alive_number > 0 { 0 } else { 1 };
+                debug!(
+                    "{} alive guards: {} boosted_enemies: {}, to spawn: {}",
+                    self.get_name(),
+                    alive_number,
+                    boosted_enemies.len(),
+                    to_spawn
+                );
+                if to_spawn > 0 {
+                    events.push(RoomEvent::Spawn(guard, to_spawn));
                 }
+                events.push(RoomEvent::Intrusion(Some(format!(
+                    "Boosted player invasion in room {}!!",
+                    self.get_name()
+                ))));
+            } else if room_memory.intrusion && room_memory.last_intrusion + 100 < game::time() {
+                events.push(RoomEvent::Intrusion(None));
             }
         }
         events
     }
-
-    fn perimetr_check(&self) -> Option<RoomEvent> {
-        (self.controller.level() > 5
-            && self.ramparts.perimeter().any(|rampart| rampart.hits() < MIN_PERIMETR_HITS))
-        .then(|| {
-            RoomEvent::ActivateSafeMode("Perimeter out of order! Enabling safe mode!".to_string())
-        })
-    }
 }
 
 fn find_player_boosted_creeps(enemies: &[Creep]) -> Vec<CreepHostile> {
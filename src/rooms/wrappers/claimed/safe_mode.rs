@@ -0,0 +1,306 @@
+use std::fmt::{Display, Formatter};
+
+use log::{debug, info};
+use screeps::{
+    ATTACK_POWER, Boost, Creep, DISMANTLE_POWER, HEAL_POWER, HasHits, HasPosition,
+    INVADER_USERNAME, OwnedStructureProperties, Part, Position, RANGED_ATTACK_POWER, RoomName,
+    TOWER_ENERGY_COST, game,
+};
+
+use crate::commons::{has_part, tower_attack_power};
+use crate::rooms::RoomEvent;
+use crate::rooms::wrappers::claimed::Claimed;
+use crate::utils::constants::{LONG_RANGE_ACTION, MIN_PERIMETR_HITS, SAFE_MODE_BREACH_TICKS};
+
+/// The reason to activate the safe mode right now
+#[derive(Debug)]
+pub(crate) enum Threat {
+    /// one of the spawns, the storage or the terminal lost hits with an attacker in reach
+    CriticalDamaged(String),
+    /// a perimeter rampart falls before the attackers can be killed
+    PerimeterBreach { hits: u32, ticks_to_breach: u32, ticks_to_kill: Option<u32> },
+    /// no perimeter and not enough towers and defenders to stop the attackers
+    Undefended { damage: u32, heal: u32 },
+}
+
+/// The reason to keep the safe mode charge for later
+#[derive(Debug)]
+pub(crate) enum Hold {
+    NoThreat,
+    AlreadyActive,
+    NoCharges,
+    Cooldown(u32),
+    UpgradeBlocked(u32),
+    ActiveElsewhere(RoomName),
+    Defendable { damage: u32, heal: u32 },
+}
+
+#[derive(Debug)]
+pub(crate) enum SafeModeDecision {
+    Activate(Threat),
+    Hold(Hold),
+}
+
+impl Display for Threat {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Threat::CriticalDamaged(structure) => write!(f, "{structure} is under attack"),
+            Threat::PerimeterBreach { hits, ticks_to_breach, ticks_to_kill } => write!(
+                f,
+                "perimeter breach in {ticks_to_breach} ticks (hits: {hits}), kill in: {ticks_to_kill:?}"
+            ),
+            Threat::Undefended { damage, heal } => {
+                write!(f, "undefended, damage: {damage} against heal: {heal}")
+            }
+        }
+    }
+}
+
+impl Display for Hold {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Hold::NoThreat => write!(f, "no threat"),
+            Hold::AlreadyActive => write!(f, "already active"),
+            Hold::NoCharges => write!(f, "no charges available"),
+            Hold::Cooldown(ticks) => write!(f, "cooldown: {ticks}"),
+            Hold::UpgradeBlocked(ticks) => write!(f, "upgrade blocked: {ticks}"),
+            Hold::ActiveElsewhere(room_name) => write!(f, "already active in {room_name}"),
+            Hold::Defendable { damage, heal } => {
+                write!(f, "defendable, damage: {damage} against heal: {heal}")
+            }
+        }
+    }
+}
+
+impl Display for SafeModeDecision {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            SafeModeDecision::Activate(threat) => write!(f, "activate: {threat}"),
+            SafeModeDecision::Hold(hold) => write!(f, "hold: {hold}"),
+        }
+    }
+}
+
+impl Claimed {
+    pub(crate) fn safe_mode_check(&self) -> Option<RoomEvent> {
+        let decision = self.safe_mode_decision();
+        match &decision {
+            SafeModeDecision::Activate(threat) => {
+                info!("{} safe mode decision: {}", self.get_name(), decision);
+                Some(RoomEvent::ActivateSafeMode(format!(
+                    "{} enabling safe mode: {}!",
+                    self.get_name(),
+                    threat
+                )))
+            }
+            SafeModeDecision::Hold(_) => {
+                debug!("{} safe mode decision: {}", self.get_name(), decision);
+                None
+            }
+        }
+    }
+
+    pub(crate) fn safe_mode_decision(&self) -> SafeModeDecision {
+        let attackers: Vec<&Creep> = self
+            .hostiles
+            .iter()
+            .filter(|hostile| {
+                hostile.owner().username() != INVADER_USERNAME
+                    && has_part(
+                        &[Part::Attack, Part::RangedAttack, Part::Work, Part::Claim],
+                        hostile,
+                        true,
+                    )
+            })
+            .collect();
+
+        if attackers.is_empty() {
+            return SafeModeDecision::Hold(Hold::NoThreat);
+        } else if self.controller.safe_mode().is_some() {
+            return SafeModeDecision::Hold(Hold::AlreadyActive);
+        }
+
+        let threat = match self.find_threat(&attackers) {
+            Ok(threat) => threat,
+            Err(hold) => return SafeModeDecision::Hold(hold),
+        };
+
+        match self.safe_mode_availability() {
+            Ok(()) => SafeModeDecision::Activate(threat),
+            Err(hold) => SafeModeDecision::Hold(hold),
+        }
+    }
+
+    fn find_threat(&self, attackers: &[&Creep]) -> Result<Threat, Hold> {
+        // old damage alone is no threat, an attacker has to reach the structure
+        let attacked = |pos: Position| {
+            attackers.iter().any(|attacker| attacker.pos().get_range_to(pos) <= LONG_RANGE_ACTION)
+        };
+        if let Some(damaged) = self
+            .spawns
+            .iter()
+            .filter(|spawn| spawn.hits() < spawn.hits_max() && attacked(spawn.pos()))
+            .map(|spawn| format!("spawn {}", spawn.name()))
+            .chain(
+                self.storage
+                    .iter()
+                    .filter(|storage| {
+                        storage.hits() < storage.hits_max() && attacked(storage.pos())
+                    })
+                    .map(|_| "storage".to_string()),
+            )
+            .chain(
+                self.terminal
+                    .iter()
+                    .filter(|terminal| {
+                        terminal.hits() < terminal.hits_max() && attacked(terminal.pos())
+                    })
+                    .map(|_| "terminal".to_string()),
+            )
+            .next()
+        {
+            return Ok(Threat::CriticalDamaged(damaged));
+        }
+
+        let heal: u32 = attackers.iter().map(|attacker| heal_power(attacker)).sum();
+        let (damage, ticks_to_kill) = attackers
+            .iter()
+            .map(|attacker| {
+                let damage = self.predicted_damage(attacker);
+                let ticks = (damage > heal).then(|| attacker.hits().div_ceil(damage - heal));
+                (damage, ticks)
+            })
+            .max_by_key(|(damage, _)| *damage)
+            .unwrap_or_default();
+
+        if let Some((hits, ticks_to_breach)) = self
+            .ramparts
+            .perimeter()
+            .filter_map(|rampart| {
+                let siege_damage: u32 = attackers
+                    .iter()
+                    .filter(|attacker| attacker.pos().get_range_to(rampart.pos()) <= 3)
+                    .map(|attacker| siege_power(attacker, attacker.pos().is_near_to(rampart.pos())))
+                    .sum();
+                (siege_damage > 0).then(|| (rampart.hits(), rampart.hits() / siege_damage))
+            })
+            .min_by_key(|(_, ticks)| *ticks)
+            && (hits < MIN_PERIMETR_HITS
+                || (ticks_to_breach < SAFE_MODE_BREACH_TICKS
+                    && ticks_to_kill.is_none_or(|ticks| ticks > ticks_to_breach)))
+        {
+            return Ok(Threat::PerimeterBreach { hits, ticks_to_breach, ticks_to_kill });
+        }
+
+        if ticks_to_kill.is_none() && self.ramparts.perimeter().next().is_none() {
+            Ok(Threat::Undefended { damage, heal })
+        } else {
+            Err(Hold::Defendable { damage, heal })
+        }
+    }
+
+    fn safe_mode_availability(&self) -> Result<(), Hold> {
+        if self.controller.safe_mode_available() == 0 {
+            Err(Hold::NoCharges)
+        } else if let Some(cooldown) = self.controller.safe_mode_cooldown() {
+            Err(Hold::Cooldown(cooldown))
+        } else if let Some(blocked) = self.controller.upgrade_blocked() {
+            Err(Hold::UpgradeBlocked(blocked))
+        } else if let Some(room_name) = game::rooms().values().find_map(|room| {
+            room.controller()
+                .filter(|ctrl| ctrl.my() && ctrl.safe_mode().is_some())
+                .map(|_| room.name())
+                .filter(|room_name| *room_name != self.get_name())
+        }) {
+            Err(Hold::ActiveElsewhere(room_name))
+        } else {
+            Ok(())
+        }
+    }
+
+    // damage per tick towers and defenders can deal to the target, tough boosts included
+    fn predicted_damage(&self, target: &Creep) -> u32 {
+        let towers: u32 = self
+            .towers
+            .iter()
+            .filter(|tower| {
+                tower.store().get_used_capacity(Some(screeps::ResourceType::Energy))
+                    >= TOWER_ENERGY_COST
+            })
+            .map(|tower| tower_attack_power(tower.pos().get_range_to(target.pos())))
+            .sum();
+
+        let defenders: u32 = self
+            .my_creeps
+            .iter()
+            .flat_map(Creep::body)
+            .filter(|bodypart| bodypart.hits() > 0)
+            .map(|bodypart| match bodypart.part() {
+                Part::Attack => ATTACK_POWER * boost_multiplier(bodypart.boost(), Part::Attack),
+                Part::RangedAttack => {
+                    RANGED_ATTACK_POWER * boost_multiplier(bodypart.boost(), Part::RangedAttack)
+                }
+                _ => 0,
+            })
+            .sum();
+
+        let tough = target
+            .body()
+            .iter()
+            .filter(|bodypart| bodypart.part() == Part::Tough && bodypart.hits() > 0)
+            .filter_map(|bodypart| match bodypart.boost().and_then(screeps::ResourceType::boost) {
+                Some(Boost::Tough(ratio)) => Some(ratio),
+                _ => None,
+            })
+            .reduce(f32::min)
+            .unwrap_or(1.);
+
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            clippy::cast_precision_loss
+        )]
+        let damage = ((towers + defenders) as f32 * tough) as u32;
+        damage
+    }
+}
+
+fn heal_power(creep: &Creep) -> u32 {
+    creep
+        .body()
+        .iter()
+        .filter(|bodypart| bodypart.part() == Part::Heal && bodypart.hits() > 0)
+        .map(|bodypart| HEAL_POWER * boost_multiplier(bodypart.boost(), Part::Heal))
+        .sum()
+}
+
+// damage per tick the creep deals to a structure, melee parts only count when adjacent
+fn siege_power(creep: &Creep, adjacent: bool) -> u32 {
+    creep
+        .body()
+        .iter()
+        .filter(|bodypart| bodypart.hits() > 0)
+        .map(|bodypart| match bodypart.part() {
+            Part::Work if adjacent => {
+                DISMANTLE_POWER * boost_multiplier(bodypart.boost(), Part::Work)
+            }
+            Part::Attack if adjacent => {
+                ATTACK_POWER * boost_multiplier(bodypart.boost(), Part::Attack)
+            }
+            Part::RangedAttack => {
+                RANGED_ATTACK_POWER * boost_multiplier(bodypart.boost(), Part::RangedAttack)
+            }
+            _ => 0,
+        })
+        .sum()
+}
+
+fn boost_multiplier(boost: Option<screeps::ResourceType>, part: Part) -> u32 {
+    match (part, boost.and_then(screeps::ResourceType::boost)) {
+        (Part::Attack, Some(Boost::Attack(m)))
+        | (Part::RangedAttack, Some(Boost::RangedAttack(m)))
+        | (Part::Heal, Some(Boost::Heal(m)))
+        | (Part::Work, Some(Boost::Dismantle(m))) => m,
+        _ => 1,
+    }
+}
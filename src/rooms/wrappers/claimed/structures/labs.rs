@@ -2,7 +2,10 @@ use std::{cmp, collections::HashMap};
 
 use itertools::Itertools;
 use log::debug;
-use screeps::{HasId, HasPosition, ObjectId, ResourceType, StructureLab};
+use screeps::{
+    Creep, HasId, HasPosition, LAB_BOOST_MINERAL, LAB_MINERAL_CAPACITY, ObjectId, Part, Position,
+    ResourceType, SharedCreepProperties, StructureLab, game,
+};
 
 use crate::commons::find_container_with;
 use crate::rooms::{
@@ -10,12 +13,15 @@ use crate::rooms::{
     shelter::Shelter,
     state::{
         constructions::{LabStatus, PlannedCell, RoomPlan, RoomStructure},
+        boosts::{self, BoostOrder},
         requests::{CarryData, Request, RequestKind, assignment::Assignment, meta::Status},
     },
 };
-use crate::utils::constants::LAB_PRODUCTION;
+use crate::units::creeps::CreepMemory;
+use crate::units::roles::Kind;
+use crate::units::tasks::Task;
+use crate::utils::constants::{LAB_PRODUCTION, UNBOOST_TICKS};
 
-const MIN_ENERGY_AMOUNT: u32 = 1000;
 const MIN_RESOURCE_AMOUNT: u32 = 2000;
 
 impl Shelter<'_> {
@@ -53,12 +59,18 @@ impl Shelter<'_> {
     }
 
     fn update_lab_state(&self) -> Option<RoomEvent> {
-        //all unique boostable resources
+        //all unique boostable resources and the ones reserved for spawning creeps
         let boost_resources: Vec<ResourceType> = self
             .state
             .boosts
             .iter()
             .flat_map(|boost_reason| boost_reason.0.value())
+            .chain(
+                self.state
+                    .boost_orders
+                    .iter()
+                    .flat_map(|order| order.resources.iter().map(|(res, _)| *res)),
+            )
             .unique()
             .collect();
 
@@ -89,13 +101,142 @@ impl Shelter<'_> {
             })
     }
 
+    // the energy is brought by the logistics broker
     fn keep_boost_ready(&self, resource: ResourceType, lab: &StructureLab) -> Option<RoomEvent> {
-        self.load_lab(lab, (ResourceType::Energy, MIN_ENERGY_AMOUNT)) //supply energy
+        self.unload(lab, &[resource]) //unload resources
+            //load boost resource
             .or_else(|| {
-                self.unload(lab, &[resource]) //unload resources
-                    //load boost resource
-                    .or_else(|| self.load_lab(lab, (resource, MIN_RESOURCE_AMOUNT)))
+                let amount = self
+                    .state
+                    .boost_reserved(resource)
+                    .clamp(MIN_RESOURCE_AMOUNT, LAB_MINERAL_CAPACITY);
+                self.load_lab(lab, (resource, amount))
+            })
+    }
+
+    /// Reserves the minerals for a creep as it starts spawning, so the labs are loaded when it
+    /// comes out
+    pub(crate) fn plan_boosts(&self, creeps: &HashMap<String, CreepMemory>) -> Option<RoomEvent> {
+        creeps
+            .iter()
+            .filter(|(name, memory)| {
+                memory.role.get_home().is_some_and(|home| *home == self.name())
+                    && self.state.boost_orders.iter().all(|order| order.creep != **name)
+            })
+            .find_map(|(name, memory)| {
+                let creep = game::creeps().get(name.clone()).filter(Creep::spawning)?;
+                let wanted: Vec<(u32, [ResourceType; 2])> = memory
+                    .role
+                    .boosts(&creep)
+                    .into_iter()
+                    .map(|(part, resources)| {
+                        let parts = creep.body().iter().filter(|bp| bp.part() == part).count();
+                        (u32::try_from(parts).unwrap_or_default(), resources)
+                    })
+                    .collect();
+                let available = wanted
+                    .iter()
+                    .flat_map(|(_, resources)| resources)
+                    .unique()
+                    .map(|res| (*res, self.boost_stock(*res)))
+                    .collect();
+                let resources = boosts::plan(&wanted, available);
+                (!resources.is_empty()).then(|| {
+                    RoomEvent::ReserveBoosts(BoostOrder::new(name.clone(), resources, game::time()))
+                })
+            })
+    }
+
+    // minerals in the storage, the terminal and the boost lab the other creeps don't wait for
+    fn boost_stock(&self, resource: ResourceType) -> u32 {
+        let in_lab = self
+            .base
+            .labs
+            .boosts()
+            .get(&resource)
+            .map_or(0, |lab| lab.store().get_used_capacity(Some(resource)));
+        (self.storage().map_or(0, |s| s.store().get_used_capacity(Some(resource)))
+            + self.terminal().map_or(0, |t| t.store().get_used_capacity(Some(resource)))
+            + in_lab)
+            .saturating_sub(self.state.boost_reserved(resource))
+    }
+
+    /// Boosts the creep from the labs in stock: the minerals reserved for it when it has a boost
+    /// order, else the ones the orders don't hold. The labs go with the number of parts they
+    /// boost, the most parts first
+    pub fn boost_task(
+        &self,
+        creep: &Creep,
+        all_boosts: HashMap<Part, [ResourceType; 2]>,
+    ) -> Option<Task> {
+        let order = self.state.boost_orders.iter().find(|order| order.creep == creep.name());
+        let labs: Vec<(ObjectId<StructureLab>, u32)> = creep
+            .body()
+            .iter()
+            .filter(|bodypart| bodypart.boost().is_none())
+            .map(screeps::BodyPart::part)
+            .counts()
+            .into_iter()
+            .filter_map(|(part, count)| {
+                all_boosts.get(&part)?.iter().find_map(|resource| {
+                    let lab = self.base.labs.boosts().get(resource)?;
+                    let stock = lab.store().get_used_capacity(Some(*resource));
+                    let minerals = match order {
+                        Some(order) => order.reserved(*resource).min(stock),
+                        None => stock.saturating_sub(self.state.boost_reserved(*resource)),
+                    };
+                    let parts = (minerals / LAB_BOOST_MINERAL)
+                        .min(u32::try_from(count).unwrap_or_default());
+                    (parts > 0).then(|| (lab.id(), parts))
+                })
+            })
+            .sorted_by_key(|(_, parts)| cmp::Reverse(*parts))
+            .collect();
+        (!labs.is_empty()).then_some(Task::Boost(labs))
+    }
+
+    /// Calls a boosted creep in or next to the home back to the labs when its life is about the
+    /// way there, the unboost returns half of the minerals
+    pub fn unboost_task(&self, creep: &Creep) -> Option<Task> {
+        let ttl = creep.ticks_to_live()?;
+        if self.invasion()
+            || creep.body().iter().all(|bodypart| bodypart.boost().is_none())
+            || game::map::get_room_linear_distance(creep.pos().room_name(), self.name(), false) > 1
+        {
+            return None;
+        }
+
+        let (id, pos) = self.unboost_spot()?;
+        let distance = creep.pos().get_range_to(pos);
+        (ttl > distance && ttl <= distance + UNBOOST_TICKS).then_some(Task::Unboost(id, pos))
+    }
+
+    /// A lab with a container next to it, the container catches the minerals an unboost drops.
+    /// The labs ready to unboost first
+    fn unboost_spot(&self) -> Option<(ObjectId<StructureLab>, Position)> {
+        self.base
+            .labs
+            .all()
+            .flat_map(|lab| {
+                self.base
+                    .containers
+                    .iter()
+                    .filter(|container| container.pos().is_near_to(lab.pos()))
+                    .map(move |container| (lab, container.pos()))
+            })
+            .min_by_key(|(lab, _)| lab.cooldown())
+            .map(|(lab, pos)| (lab.id(), pos))
+    }
+
+    /// Sends the minerals an unboost left in a container next to a lab to the storage
+    pub(crate) fn collect_unboosts(&self) -> Option<RoomEvent> {
+        self.base
+            .containers
+            .iter()
+            .filter(|container| {
+                self.base.labs.all().any(|lab| container.pos().is_near_to(lab.pos()))
             })
+            .find_map(|container| self.unload(container, &[ResourceType::Energy]))
     }
 
     //todo might be a bug -> replaces existed request by a new one
@@ -204,11 +345,11 @@ impl Labs {
         &self.outputs
     }
 
-    const fn boosts(&self) -> &HashMap<ResourceType, StructureLab> {
+    pub(crate) const fn boosts(&self) -> &HashMap<ResourceType, StructureLab> {
         &self.boosts
     }
 
-    pub(crate) fn boost_lab(&self, resource: ResourceType) -> Option<ObjectId<StructureLab>> {
-        self.boosts.get(&resource).map(screeps::HasId::id)
+    pub(crate) fn all(&self) -> impl Iterator<Item = &StructureLab> {
+        self.inputs.iter().chain(self.outputs.iter()).chain(self.boosts.values())
     }
 }
@@ -1,50 +1,148 @@
+use std::cmp::Reverse;
+
 use log::warn;
-use screeps::{HasPosition, ResourceType, StructureLink};
+use screeps::{HasPosition, ResourceType, RoomXY, StructureLink};
 
 use crate::rooms::state::constructions::{LinkType, PlannedCell, RoomPlan, RoomStructure};
 use crate::rooms::wrappers::claimed::Claimed;
+use crate::utils::constants::{LINK_BATCH, LINK_SPILL};
 
 impl Claimed {
     pub(crate) fn run_links(&self) {
-        if let Some(farm_link) = self.links.other.iter().find(|link| {
-            link.cooldown() == 0 && link.store().get_used_capacity(Some(ResourceType::Energy)) > 700
-        }) {
-            if let Some(receiver) = self.links.receiver() {
-                let transfer_result = farm_link.transfer_energy(receiver, None);
-                match transfer_result {
-                    Ok(()) => {}
-                    Err(err) => {
-                        warn!("room: {} farm link transfer error: {:?}", self.get_name(), err);
-                    }
-                }
-            } else {
-                //todo else -> send to ctrl_link?
+        let nodes: Vec<LinkNode> = self
+            .links
+            .all
+            .iter()
+            .map(|(link_type, link)| LinkNode {
+                role: LinkRole::from(*link_type),
+                xy: link.pos().xy(),
+                energy: link.store().get_used_capacity(Some(ResourceType::Energy)),
+                free: u32::try_from(link.store().get_free_capacity(Some(ResourceType::Energy)))
+                    .unwrap_or_default(),
+                ready: link.cooldown() == 0,
+            })
+            .collect();
+        let refill = self.room.energy_available() < self.room.energy_capacity_available();
+
+        for (from, to, amount) in balance(&nodes, refill) {
+            let (_, sender) = &self.links.all[from];
+            let (_, receiver) = &self.links.all[to];
+            if let Err(err) = sender.transfer_energy(receiver, Some(amount)) {
+                warn!("room: {} link {} transfer error: {:?}", self.get_name(), sender.pos(), err);
             }
         }
+    }
+}
 
-        if let Some(ctrl_link) = self.links.ctrl()
-            && ctrl_link.store().get_free_capacity(Some(ResourceType::Energy)) > 500
-            && let Some(sender) = self.links.sender().filter(|s| {
-                s.cooldown() == 0 && s.store().get_used_capacity(Some(ResourceType::Energy)) > 0
-            })
+/// What a link does in the flow of energy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkRole {
+    /// filled by the harvesters
+    Source,
+    /// next to the storage, takes the spare energy and sends what the trader puts in
+    Hub,
+    /// feeds the upgraders
+    Controller,
+    /// filled by the remote haulers at the room edge
+    Edge,
+}
+
+impl From<LinkType> for LinkRole {
+    fn from(link_type: LinkType) -> Self {
+        match link_type {
+            LinkType::Sender | LinkType::Receiver => LinkRole::Hub,
+            LinkType::Ctrl => LinkRole::Controller,
+            LinkType::Source => LinkRole::Source,
+            LinkType::Edge => LinkRole::Edge,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LinkNode {
+    pub role: LinkRole,
+    pub xy: RoomXY,
+    pub energy: u32,
+    pub free: u32,
+    /// no cooldown and nothing sent this tick
+    pub ready: bool,
+}
+
+impl LinkNode {
+    const fn produces(&self) -> bool {
+        matches!(self.role, LinkRole::Source | LinkRole::Edge)
+    }
+}
+
+/// Transfers of the tick as sender, receiver and amount. Every link sends once, straight from
+/// the source and edge links to where the energy is spent, so it pays the 3% loss once. The hub
+/// tops up the controller links the source links can't, the extension refill goes first
+pub fn balance(nodes: &[LinkNode], refill: bool) -> Vec<(usize, usize, u32)> {
+    let mut links = nodes.to_vec();
+    let mut transfers = Vec::new();
+    let with = |links: &[LinkNode], role: LinkRole| -> Vec<usize> {
+        (0..links.len()).filter(|i| links[*i].role == role).collect()
+    };
+
+    let mut consumers = with(&links, LinkRole::Controller);
+    consumers.sort_by_key(|i| Reverse(links[*i].free));
+    if refill {
+        consumers.splice(0..0, with(&links, LinkRole::Hub));
+    }
+    for to in consumers {
+        let free = links[to].free;
+        if free < LINK_BATCH {
+            continue;
+        }
+        let nearest = |i: &usize| {
+            (links[*i].energy.min(free), Reverse(links[*i].xy.get_range_to(links[to].xy)))
+        };
+        let from = (0..links.len())
+            .filter(|i| links[*i].produces() && links[*i].ready && links[*i].energy >= LINK_BATCH)
+            .max_by_key(nearest)
+            .or_else(|| {
+                (links[to].role == LinkRole::Controller)
+                    .then(|| {
+                        with(&links, LinkRole::Hub)
+                            .into_iter()
+                            .filter(|i| links[*i].ready && links[*i].energy >= LINK_BATCH.min(free))
+                            .max_by_key(nearest)
+                    })
+                    .flatten()
+            });
+        if let Some(from) = from {
+            transfers.push(send(&mut links, from, to));
+        }
+    }
+
+    // the full producers spill into the hub, the storage takes it all
+    for from in 0..links.len() {
+        if !links[from].produces() || !links[from].ready || links[from].energy < LINK_SPILL {
+            continue;
+        }
+        if let Some(to) = with(&links, LinkRole::Hub)
+            .into_iter()
+            .filter(|i| links[*i].free >= links[from].energy)
+            .max_by_key(|i| links[*i].free)
         {
-            let transfer_result = sender.transfer_energy(ctrl_link, None);
-            match transfer_result {
-                Ok(()) => {}
-                Err(err) => {
-                    warn!("room: {} sender link transfer error: {:?}", self.get_name(), err);
-                }
-            }
+            transfers.push(send(&mut links, from, to));
         }
     }
+    transfers
+}
+
+fn send(links: &mut [LinkNode], from: usize, to: usize) -> (usize, usize, u32) {
+    let amount = links[from].energy.min(links[to].free);
+    links[from].energy -= amount;
+    links[from].ready = false;
+    // the receiver gets the amount less the loss
+    links[to].free -= amount * 97 / 100;
+    (from, to, amount)
 }
 
 #[derive(Default)]
 pub struct Links {
-    sender: Option<StructureLink>,
-    receiver: Option<StructureLink>,
-    ctrl: Option<StructureLink>,
-    other: Vec<StructureLink>,
+    all: Vec<(LinkType, StructureLink)>,
 }
 
 impl Links {
@@ -53,11 +151,7 @@ impl Links {
             return Links::default();
         };
 
-        let mut sender = None;
-        let mut receiver = None;
-        let mut ctrl = None;
-        let mut other = Vec::new();
-
+        let mut all = Vec::new();
         for link in links {
             //doesn't matter which type of link is passed for seacrh
             //hash works for xy and hight level room structure type only
@@ -66,12 +160,7 @@ impl Links {
 
             if let Some(planned_cell) = plan.get_cell(cell) {
                 match planned_cell.structure {
-                    RoomStructure::Link(link_type) => match link_type {
-                        LinkType::Sender => sender = Some(link),
-                        LinkType::Receiver => receiver = Some(link),
-                        LinkType::Ctrl => ctrl = Some(link),
-                        LinkType::Source => other.push(link),
-                    },
+                    RoomStructure::Link(link_type) => all.push((link_type, link)),
                     _ => {
                         warn!("invalid plan search for: {}", link.pos());
                     }
@@ -79,18 +168,56 @@ impl Links {
             }
         }
 
-        Self { sender, receiver, ctrl, other }
+        Self { all }
     }
 
-    pub const fn sender(&self) -> Option<&StructureLink> {
-        self.sender.as_ref()
+    fn find(&self, link_type: LinkType) -> Option<&StructureLink> {
+        self.all.iter().find(|(lt, _)| *lt == link_type).map(|(_, link)| link)
     }
 
-    pub const fn receiver(&self) -> Option<&StructureLink> {
-        self.receiver.as_ref()
+    pub fn sender(&self) -> Option<&StructureLink> {
+        self.find(LinkType::Sender)
     }
 
-    pub const fn ctrl(&self) -> Option<&StructureLink> {
-        self.ctrl.as_ref()
+    pub fn receiver(&self) -> Option<&StructureLink> {
+        self.find(LinkType::Receiver)
+    }
+
+    pub fn ctrl(&self) -> Option<&StructureLink> {
+        self.find(LinkType::Ctrl)
+    }
+
+    pub fn edge(&self) -> Option<&StructureLink> {
+        self.find(LinkType::Edge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use screeps::RoomXY;
+
+    use crate::rooms::wrappers::claimed::structures::links::{LinkNode, LinkRole, balance};
+
+    #[test]
+    fn link_balance_test() {
+        let node = |role, x, energy: u32, ready| LinkNode {
+            role,
+            xy: RoomXY::checked_new(x, 10).unwrap(),
+            energy,
+            free: 800 - energy,
+            ready,
+        };
+        let nodes = [
+            node(LinkRole::Source, 40, 800, true),
+            node(LinkRole::Source, 5, 750, true),
+            node(LinkRole::Controller, 30, 100, true),
+            node(LinkRole::Hub, 20, 0, true),
+            node(LinkRole::Source, 45, 800, false),
+        ];
+
+        // the controller takes from the closest full source, the other one spills into the hub
+        assert_eq!(balance(&nodes, false), vec![(0, 2, 700), (1, 3, 750)]);
+        // the extensions wait for the hub, the controller takes what is left
+        assert_eq!(balance(&nodes, true), vec![(0, 3, 800), (1, 2, 700)]);
     }
 }
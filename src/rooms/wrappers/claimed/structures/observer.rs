@@ -1,4 +1,5 @@
 use log::error;
+use screeps::{OBSERVER_RANGE, RoomName, game};
 
 use crate::{rooms::wrappers::claimed::Claimed, commons};
 
@@ -7,12 +8,18 @@ use crate::{rooms::wrappers::claimed::Claimed, commons};
 //remove_from_observe('E6S20')
 //Game.getObjectById('675740745ef8fa1b1eae907a').observeRoom('W1S40')
 impl Claimed {
-    pub(crate) fn run_observer(&self) {
+    pub(crate) fn run_observer(&self, priority: Option<RoomName>) {
         if let Some(observer) = &self.observer {
             let x = commons::get_random(-5, 5);
             let y = commons::get_random(-5, 5);
 
-            if let Some(target) = self.get_name().checked_add((x, y)) {
+            if let Some(target) = priority
+                .filter(|room_name| {
+                    game::map::get_room_linear_distance(self.get_name(), *room_name, false)
+                        <= OBSERVER_RANGE
+                })
+                .or_else(|| self.get_name().checked_add((x, y)))
+            {
                 let res = observer.observe_room(target);
                 match res {
                     Ok(()) => {}
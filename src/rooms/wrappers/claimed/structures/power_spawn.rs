@@ -7,7 +7,6 @@ use crate::rooms::state::requests::assignment::Assignment;
 use crate::rooms::state::requests::{CarryData, Request, RequestKind};
 use crate::rooms::wrappers::claimed::Claimed;
 const POWER_LOAD_CAPACITY: u32 = 100;
-const MIN_ENERGY_AMOUNT: u32 = 250_000;
 
 impl Claimed {
     pub(crate) fn run_power(&self) -> Option<Request> {
@@ -34,26 +33,6 @@ impl Claimed {
                                 Assignment::Single(None),
                             )
                         })
-                    } else if power_spawn.store().get_used_capacity(Some(ResourceType::Energy)) < 50
-                    {
-                        find_container_with(
-                            ResourceType::Energy,
-                            Some(MIN_ENERGY_AMOUNT),
-                            self.storage(),
-                            None,
-                            None,
-                        )
-                        .map(|(id, _)| {
-                            Request::new(
-                                RequestKind::Carry(CarryData::new(
-                                    id,
-                                    power_spawn.raw_id(),
-                                    ResourceType::Energy,
-                                    4000,
-                                )),
-                                Assignment::Single(None),
-                            )
-                        })
                     } else {
                         None
                     }
@@ -1,16 +1,78 @@
-use screeps::{HasHits, HasId, HasPosition, ObjectId, Position, RoomCoordinate, StructureRampart};
+use itertools::Itertools;
+use screeps::local::RoomXY;
+use screeps::{
+    HasHits, HasId, HasPosition, Part, Position, RoomCoordinate, StructureRampart, rampart_hits_max,
+};
 
 use crate::commons::get_positions_near_by;
 use crate::rooms::state::constructions::{PlannedCell, RoomPlan, RoomStructure};
 use crate::rooms::state::requests::assignment::Assignment;
 use crate::rooms::state::requests::{RepairData, Request, RequestKind};
 use crate::rooms::wrappers::claimed::Claimed;
-use crate::utils::constants::MAX_RAMPART_HITS;
+use crate::utils::constants::{
+    FORTIFY_CRITICAL_RANGE, FORTIFY_INCOME_NORM, FORTIFY_LEVEL_HITS, FORTIFY_REPAIRS,
+    FORTIFY_STORED_NORM, FORTIFY_THREAT_FACTOR, MIN_PERIMETR_HITS,
+};
+
+/// Hit targets of the ramparts and the walls from the controller level, the stored energy and
+/// the income, raised next to the critical structures and where hostiles came lately
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Fortification {
+    rcl: u8,
+    stored: u32,
+    income: u32,
+}
 
-impl Claimed {
-    pub(crate) fn run_ramparts(&self) -> impl Iterator<Item = Request> {
-        let mut requests = Vec::new();
+impl Fortification {
+    pub(crate) const fn new(rcl: u8, stored: u32, income: u32) -> Self {
+        Self { rcl, stored, income }
+    }
+
+    // what the economy affords everywhere
+    fn base(&self) -> u64 {
+        let level = FORTIFY_LEVEL_HITS[usize::from(self.rcl.min(8))];
+        let stored = self.stored.clamp(FORTIFY_STORED_NORM / 2, FORTIFY_STORED_NORM * 4);
+        let income = self.income.clamp(FORTIFY_INCOME_NORM / 2, FORTIFY_INCOME_NORM * 2);
+        u64::from(level) * u64::from(stored) / u64::from(FORTIFY_STORED_NORM) * u64::from(income)
+            / u64::from(FORTIFY_INCOME_NORM)
+    }
+
+    pub(crate) fn target(&self, xy: RoomXY, critical: &[RoomXY], threatened: bool) -> u32 {
+        let mut target = self.base();
+        if critical.iter().any(|structure| structure.get_range_to(xy) <= FORTIFY_CRITICAL_RANGE) {
+            target *= 2;
+        }
+        if threatened {
+            target *= u64::from(FORTIFY_THREAT_FACTOR);
+        }
+        let max = rampart_hits_max(u32::from(self.rcl));
+        u32::try_from(target).unwrap_or(max).clamp(MIN_PERIMETR_HITS.min(max), max)
+    }
+}
 
+/// The structures to repair now: below their targets, the weakest against the target first
+pub(crate) fn weakest<'a, T: HasHits>(
+    structures: impl Iterator<Item = &'a T>,
+    target: impl Fn(&T) -> u32,
+) -> Vec<&'a T> {
+    structures
+        .filter_map(|structure| {
+            let target = target(structure);
+            (structure.hits() < target)
+                .then(|| (u64::from(structure.hits()) * 1000 / u64::from(target), structure))
+        })
+        .sorted_by_key(|(ratio, _)| *ratio)
+        .take(FORTIFY_REPAIRS)
+        .map(|(_, structure)| structure)
+        .collect()
+}
+
+impl Claimed {
+    pub(crate) fn run_ramparts(
+        &self,
+        fortification: &Fortification,
+        threats: &[(RoomXY, u32)],
+    ) -> impl Iterator<Item = Request> {
         let nuke_positions: Vec<Position> = self
             .nukes
             .iter()
@@ -27,21 +89,67 @@ impl Claimed {
         let enemies = self.hostiles.len();
         for rampart in self.ramparts.all() {
             rampart.toogle(enemies == 0);
-
-            if rampart.need_repair(&nuke_positions) {
-                requests.push(Request::new(
-                    RequestKind::Repair(RepairData::with_max_attempts_and_hits(
-                        rampart.id().into_type(),
-                        rampart.pos(),
-                        25,
-                        rampart.hits(),
-                    )),
-                    Assignment::Single(None),
-                ));
-            }
         }
 
-        requests.into_iter()
+        let critical = self.critical_structures();
+        let target = |rampart: &StructureRampart| {
+            let xy = rampart.pos().xy();
+            fortification.target(xy, &critical, threats.iter().any(|(threat, _)| *threat == xy))
+        };
+        weakest(
+            self.ramparts
+                .all()
+                .iter()
+                .filter(|rampart| rampart.exposed(&nuke_positions))
+                .map(|rampart| &rampart.structure),
+            target,
+        )
+        .into_iter()
+        .map(|rampart| {
+            Request::new(
+                RequestKind::Repair(RepairData::with_max_attempts_and_hits(
+                    rampart.id().into_type(),
+                    rampart.pos(),
+                    25,
+                    rampart.hits(),
+                )),
+                Assignment::Single(None),
+            )
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+    }
+
+    /// Ramparts with a hostile able to damage them in range
+    pub(crate) fn threatened_ramparts(&self) -> Vec<RoomXY> {
+        let attackers: Vec<Position> = self
+            .hostiles
+            .iter()
+            .filter(|hostile| {
+                hostile.body().iter().any(|part| {
+                    matches!(part.part(), Part::Attack | Part::RangedAttack | Part::Work)
+                })
+            })
+            .map(HasPosition::pos)
+            .collect();
+        self.ramparts
+            .all()
+            .iter()
+            .map(Rampart::pos)
+            .filter(|pos| attackers.iter().any(|attacker| attacker.get_range_to(*pos) <= 3))
+            .map(Position::xy)
+            .collect()
+    }
+
+    pub(crate) fn critical_structures(&self) -> Vec<RoomXY> {
+        self.spawns
+            .iter()
+            .map(HasPosition::pos)
+            .chain(self.towers.iter().map(HasPosition::pos))
+            .chain(self.storage.iter().map(HasPosition::pos))
+            .chain(self.terminal.iter().map(HasPosition::pos))
+            .map(Position::xy)
+            .collect()
     }
 }
 
@@ -102,20 +210,38 @@ impl Rampart {
         }
     }
 
-    fn need_repair(&self, nuke_positions: &[Position]) -> bool {
-        self.structure.hits() < MAX_RAMPART_HITS
-            && (nuke_positions.is_empty() || nuke_positions.contains(&self.structure.pos()))
-    }
-
-    fn hits(&self) -> u32 {
-        self.structure.hits()
+    // a falling nuke leaves only the ramparts under it worth the repairs
+    fn exposed(&self, nuke_positions: &[Position]) -> bool {
+        nuke_positions.is_empty() || nuke_positions.contains(&self.structure.pos())
     }
 
     fn pos(&self) -> Position {
         self.structure.pos()
     }
+}
 
-    fn id(&self) -> ObjectId<StructureRampart> {
-        self.structure.id()
+#[cfg(test)]
+mod tests {
+    use screeps::RoomXY;
+
+    use crate::rooms::wrappers::claimed::Fortification;
+
+    #[test]
+    fn fortification_target_test() {
+        let xy = |x, y| RoomXY::checked_new(x, y).unwrap();
+        let spawn = [xy(20, 20)];
+
+        let normal = Fortification::new(8, 100_000, 20);
+        assert_eq!(normal.target(xy(30, 30), &spawn, false), 30_000_000);
+        assert_eq!(normal.target(xy(22, 22), &spawn, false), 60_000_000);
+        assert_eq!(normal.target(xy(22, 22), &spawn, true), 180_000_000);
+
+        // a rich room goes up to the level limit, a poor one keeps the minimal perimeter
+        assert_eq!(
+            Fortification::new(8, 1_000_000, 40).target(xy(22, 22), &spawn, true),
+            300_000_000
+        );
+        assert_eq!(Fortification::new(4, 0, 5).target(xy(30, 30), &spawn, false), 100_000);
+        assert_eq!(Fortification::new(1, 0, 5).target(xy(30, 30), &spawn, false), 0);
     }
 }
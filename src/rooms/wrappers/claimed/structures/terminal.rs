@@ -34,19 +34,6 @@ impl Shelter<'_> {
                                         .collect::<Vec<_>>(),
                                 )
                             })
-                            .or_else(|| {
-                                let energy =
-                                    terminal.store().get_used_capacity(Some(ResourceType::Energy));
-                                (energy < 10000)
-                                    .then(|| {
-                                        self.supply_resources(
-                                            terminal.raw_id(),
-                                            ResourceType::Energy,
-                                            10000 - energy,
-                                        )
-                                    })
-                                    .flatten()
-                            })
                     })
                     .flatten()
             },
@@ -72,10 +59,12 @@ impl Shelter<'_> {
         self.get_trades().find_map(|trade_order| {
             let all = terminal.store().get_used_capacity(Some(trade_order.resource));
             if trade_order.amount > 0 {
-                if all >= trade_order.amount {
+                // a buy pays the fee in energy only
+                if trade_order.order_type == OrderType::Buy || all >= trade_order.amount {
                     match trade_order.order_type {
                         OrderType::Buy
-                            if let Some(order) = find_appropriate_lowest_price_order(
+                            if !trade_order.posted
+                                && let Some(order) = find_appropriate_lowest_price_order(
                                 self.name(),
                                 orders,
                                 OrderType::Sell,
@@ -91,7 +80,8 @@ impl Shelter<'_> {
                             }
                         }
                         OrderType::Sell
-                            if let Some(order) = find_appropriate_highest_price_order(
+                            if !trade_order.posted
+                                && let Some(order) = find_appropriate_highest_price_order(
                                 self.name(),
                                 orders,
                                 OrderType::Buy,
@@ -14,6 +14,7 @@ use crate::commons::{
     capture_room_numbers, find_walkable_positions_near_by, get_room_regex, is_highway,
     is_near_edge, is_skr,
 };
+use crate::rooms::state::constructions::BaseSite;
 use crate::utils::constants::DEPOSIT_REQUEST_THRESHOLD;
 
 #[derive(Debug)]
@@ -26,6 +27,8 @@ pub struct Neutral {
     pub(crate) power_banks: Vec<StructurePowerBank>,
     pub(crate) enemies: Vec<Creep>,
     pub(crate) is_blocked: bool,
+    /// object positions of a free room a base could be planned in
+    pub(crate) site: Option<BaseSite>,
 }
 
 impl Neutral {
@@ -52,6 +55,19 @@ impl Neutral {
             |structure| matches!(structure, StructureObject::StructureWall(w) if w.hits() == 0),
         );
 
+        let site = room
+            .controller()
+            .filter(|controller| controller.level() == 0 && controller.reservation().is_none())
+            .and_then(|controller| {
+                let mineral = room.find(find::MINERALS, None).first()?.pos().xy();
+                let sources = room.find(find::SOURCES, None);
+                (!sources.is_empty()).then(|| BaseSite {
+                    ctrl: controller.pos().xy(),
+                    sources: sources.iter().map(|source| source.pos().xy()).collect(),
+                    mineral,
+                })
+            });
+
         Self {
             room_name: room.name(),
             controller: room.controller(),
@@ -61,6 +77,7 @@ impl Neutral {
             power_banks,
             enemies,
             is_blocked,
+            site,
         }
     }
 
@@ -117,8 +134,8 @@ impl Neutral {
                         controller.owner().map(|owner| owner.username()),
                         controller.safe_mode().is_some_and(|mode| mode != 0),
                     ));
-                } else {
-                    //central square room?
+                } else if let Some(site) = &self.site {
+                    events.push(ColonyEvent::Site(self.room_name, site.clone()));
                 }
             } else {
                 warn!("{} parts can't be captured!", self.room_name);
@@ -5,6 +5,10 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::rooms::wrappers::claimed::Claimed;
 
+mod metrics;
+
+pub use metrics::Metrics;
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Statistic {
     #[serde(default = "game::time")]
@@ -17,6 +21,8 @@ pub struct Statistic {
     pub cpu_used: f64,
     #[serde(default = "HashMap::new")]
     pub rooms: HashMap<RoomName, RoomStats>,
+    #[serde(skip)]
+    pub metrics: Metrics,
 }
 
 impl Statistic {
@@ -0,0 +1,248 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use log::{debug, warn};
+use screeps::{Creep, EventType, HasId, ObjectId, RoomName, StructureSpawn, game, raw_memory};
+use serde::{Deserialize, Serialize};
+
+use crate::rooms::wrappers::claimed::Claimed;
+use crate::units::creeps::CreepMemory;
+use crate::utils::constants::{
+    STATS_HISTORY_SIZE, STATS_INTERVAL, STATS_SEGMENT, STATS_SEGMENT_LIMIT,
+};
+
+/// Metrics collected between two exports to the stats segment
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    ticks: u32,
+    cpu: HashMap<&'static str, f64>,
+    // busy and total spawn ticks
+    spawns: HashMap<RoomName, (u32, u32)>,
+    // the creep each spawn was busy with on the last tick
+    spawning: HashMap<ObjectId<StructureSpawn>, String>,
+    income: HashMap<(RoomName, &'static str), u32>,
+    spend: HashMap<(RoomName, &'static str), u32>,
+    last_export: Option<Progress>,
+    history: VecDeque<Sample>,
+    // the history is restored from the segment after a global reset
+    restored: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Progress {
+    tick: u32,
+    gcl: f64,
+    gpl: f64,
+    credits: f64,
+}
+
+impl Progress {
+    fn now() -> Self {
+        Self {
+            tick: game::time(),
+            gcl: game::gcl::progress(),
+            gpl: game::gpl::progress(),
+            credits: game::market::credits(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Sample {
+    tick: u32,
+    #[serde(flatten)]
+    values: BTreeMap<String, f64>,
+}
+
+/// Flat document for dashboards: the latest values keyed by dotted names and the history of the
+/// colony wide ones
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Document {
+    tick: u32,
+    metrics: BTreeMap<String, f64>,
+    history: VecDeque<Sample>,
+}
+
+impl Metrics {
+    pub fn record_cpu(&mut self, subsystem: &'static str, used: f64) {
+        *self.cpu.entry(subsystem).or_default() += used;
+    }
+
+    pub(crate) fn record_room(&mut self, base: &Claimed) {
+        let name = base.get_name();
+        let busy = base.spawns.iter().filter(|spawn| spawn.spawning().is_some()).count();
+        let spawns = self.spawns.entry(name).or_default();
+        spawns.0 += u32::try_from(busy).unwrap_or_default();
+        spawns.1 += u32::try_from(base.spawns.len()).unwrap_or_default();
+
+        for event in &base.events {
+            match &event.event {
+                EventType::Harvest(e)
+                    if base.sources.iter().any(|s| s.id().to_string() == e.target_id) =>
+                {
+                    *self.income.entry((name, "harvest")).or_default() += e.amount;
+                }
+                EventType::Build(e) => *self.spend.entry((name, "build")).or_default() += e.amount,
+                EventType::Repair(e) => {
+                    *self.spend.entry((name, "repair")).or_default() += e.energy_spent;
+                }
+                EventType::UpgradeController(e) => {
+                    *self.spend.entry((name, "upgrade")).or_default() += e.energy_spent;
+                }
+                _ => {}
+            }
+        }
+
+        let mut spawned = 0;
+        for spawn in &base.spawns {
+            let Some(creep_name) =
+                spawn.spawning().and_then(|spawning| spawning.name().as_string())
+            else {
+                continue;
+            };
+            if self.spawning.get(&spawn.id()) != Some(&creep_name) {
+                spawned +=
+                    game::creeps().get(creep_name.clone()).map_or(0, |creep| body_cost(&creep));
+                self.spawning.insert(spawn.id(), creep_name);
+            }
+        }
+        if spawned > 0 {
+            *self.spend.entry((name, "spawn")).or_default() += spawned;
+        }
+    }
+
+    /// Called every tick, writes the segment every `STATS_INTERVAL` ticks
+    pub fn export(&mut self, creeps: &HashMap<String, CreepMemory>) {
+        self.ticks += 1;
+
+        if !self.restored {
+            self.restore();
+        }
+
+        let now = Progress::now();
+        let Some(last) = self.last_export else {
+            self.last_export = Some(now);
+            return;
+        };
+        if now.tick < last.tick + STATS_INTERVAL {
+            return;
+        }
+
+        let metrics = self.snapshot(last, now, creeps);
+        let values = metrics
+            .iter()
+            .filter(|(key, _)| !key.starts_with("room."))
+            .map(|(key, value)| (key.clone(), *value))
+            .collect();
+        self.history.push_back(Sample { tick: now.tick, values });
+        while self.history.len() > STATS_HISTORY_SIZE {
+            self.history.pop_front();
+        }
+
+        let mut document = Document { tick: now.tick, metrics, history: self.history.clone() };
+        match write(&mut document) {
+            Ok(size) => debug!("stats exported: {} bytes", size),
+            Err(err) => warn!("stats export error: {}", err),
+        }
+
+        *self = Self {
+            last_export: Some(now),
+            history: std::mem::take(&mut self.history),
+            spawning: std::mem::take(&mut self.spawning),
+            restored: true,
+            ..Self::default()
+        };
+    }
+
+    fn snapshot(
+        &self,
+        last: Progress,
+        now: Progress,
+        creeps: &HashMap<String, CreepMemory>,
+    ) -> BTreeMap<String, f64> {
+        let ticks = f64::from(self.ticks.max(1));
+        let elapsed = f64::from((now.tick - last.tick).max(1));
+        let mut metrics = BTreeMap::new();
+
+        metrics.insert("cpu.bucket".to_string(), f64::from(game::cpu::bucket()));
+        metrics.insert("cpu.limit".to_string(), f64::from(game::cpu::limit()));
+        metrics.insert("cpu.used".to_string(), game::cpu::get_used());
+        for (subsystem, used) in &self.cpu {
+            metrics.insert(format!("cpu.{subsystem}"), used / ticks);
+        }
+
+        metrics.insert("gcl.level".to_string(), f64::from(game::gcl::level()));
+        metrics.insert("gcl.progress".to_string(), now.gcl / game::gcl::progress_total());
+        metrics.insert("gcl.rate".to_string(), rate(last.gcl, now.gcl, elapsed));
+        metrics.insert("gpl.level".to_string(), f64::from(game::gpl::level()));
+        metrics.insert("gpl.rate".to_string(), rate(last.gpl, now.gpl, elapsed));
+
+        metrics.insert("market.credits".to_string(), now.credits);
+        metrics.insert("market.pnl".to_string(), now.credits - last.credits);
+
+        for memory in creeps.values() {
+            *metrics.entry(format!("creeps.{}", memory.role)).or_default() += 1.;
+        }
+        metrics.insert(
+            "creeps.total".to_string(),
+            f64::from(u32::try_from(creeps.len()).unwrap_or_default()),
+        );
+
+        for (room, (busy, total)) in &self.spawns {
+            let utilization = f64::from(*busy) / f64::from((*total).max(1));
+            metrics.insert(format!("room.{room}.spawn.utilization"), utilization);
+        }
+        for ((room, category), amount) in &self.income {
+            metrics.insert(
+                format!("room.{room}.energy.income.{category}"),
+                f64::from(*amount) / elapsed,
+            );
+            *metrics.entry("energy.income".to_string()).or_default() +=
+                f64::from(*amount) / elapsed;
+        }
+        for ((room, category), amount) in &self.spend {
+            metrics.insert(
+                format!("room.{room}.energy.spend.{category}"),
+                f64::from(*amount) / elapsed,
+            );
+            *metrics.entry("energy.spend".to_string()).or_default() += f64::from(*amount) / elapsed;
+        }
+        metrics
+    }
+
+    // the segment is requested on the first tick and read on the next one
+    fn restore(&mut self) {
+        if let Some(segment) = raw_memory::segments().get(STATS_SEGMENT) {
+            match serde_json::from_str::<Document>(&segment) {
+                Ok(document) => self.history = document.history,
+                Err(err) => warn!("stats segment parse error: {}", err),
+            }
+            self.restored = true;
+        } else if self.ticks > 1 {
+            // nothing was exported yet
+            self.restored = true;
+        } else {
+            raw_memory::set_active_segments(&[STATS_SEGMENT]);
+        }
+    }
+}
+
+// the progress drops to zero on the level up
+fn rate(last: f64, now: f64, elapsed: f64) -> f64 {
+    if now >= last { (now - last) / elapsed } else { now / elapsed }
+}
+
+fn write(document: &mut Document) -> Result<usize, serde_json::Error> {
+    let mut serialized = serde_json::to_string(document)?;
+    while serialized.len() > STATS_SEGMENT_LIMIT && document.history.pop_front().is_some() {
+        serialized = serde_json::to_string(document)?;
+    }
+
+    raw_memory::segments().set(STATS_SEGMENT, serialized.clone());
+    raw_memory::set_active_segments(&[STATS_SEGMENT]);
+    raw_memory::set_public_segments(&[STATS_SEGMENT]);
+    Ok(serialized.len())
+}
+
+fn body_cost(creep: &Creep) -> u32 {
+    creep.body().iter().map(|part| part.part().cost()).sum()
+}
@@ -13,6 +13,7 @@ pub mod power_creep;
 pub mod roles;
 pub mod tasks;
 mod actions;
+mod formation;
 
 #[derive(Error, Debug, Eq, PartialEq)]
 pub enum UnitError {
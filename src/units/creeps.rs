@@ -4,10 +4,13 @@ use log::{debug, info, warn};
 use screeps::{Creep, HasPosition, Position, RoomName, SharedCreepProperties, game};
 use serde::{Deserialize, Serialize};
 
+use crate::movement::formation::{Formation, FormationPath};
 use crate::movement::{Movement, MovementGoal, MovementProfile, PathState};
 use crate::rooms::shelter::Shelter;
 use crate::rooms::state::requests::Request;
+use crate::units::formation::{FormationMember, run_formation};
 use crate::units::{move_to_goal_common, tasks::{Task, TaskResult}, roles::{Kind, Role}};
+use crate::utils::profiler;
 
 pub struct CrUnit<'m, 'h, 's> {
     creep: Creep,
@@ -29,6 +32,13 @@ impl CrUnit<'_, '_, '_> {
             && !matches!(self.memory.role.get_movement_profile(&self.creep), MovementProfile::Cargo)
     }
 
+    // squad members moving in a formation
+    fn formation(&self) -> Option<(String, Formation)> {
+        let squad_id = self.memory.role.squad_id()?;
+        let formation = self.home.squad_formation(squad_id);
+        (formation != Formation::Loose).then(|| (squad_id.to_string(), formation))
+    }
+
     fn add_request(&mut self, task: Task) {
         if let Ok(request) = Request::try_from(task) {
             info!("{}: {} inserted request: {:?}", self.home.name(), self.name(), request);
@@ -52,11 +62,32 @@ impl CrUnit<'_, '_, '_> {
     }
 
     pub fn run_unit(&mut self, _black_list: &HashSet<String>) -> Option<MovementGoal> {
-        let task = self
-            .memory
-            .task
-            .take()
-            .unwrap_or_else(|| self.memory.role.get_task(&self.creep, self.home));
+        let task = match self.memory.task.take() {
+            Some(task) if !self.memory.role.preempt(&task, &self.creep, self.home) => task,
+            Some(task) => {
+                debug!("{} preempted task: {:?}", self.name(), task);
+                if let Task::Route(stops) = &task {
+                    self.home.release_route(stops, self.name());
+                }
+                self.memory.role.get_task(&self.creep, self.home)
+            }
+            None => self.memory.role.get_task(&self.creep, self.home),
+        };
+        // the boosts are recovered before the creep dies, unless it is in a squad or on a request
+        let busy = self.memory.role.squad_id().is_some()
+            || self.home.serving(&self.name())
+            || matches!(&task, Task::Route(stops)
+                if stops.iter().any(|stop| stop.request.is_some()));
+        let task = match self.home.unboost_task(&self.creep) {
+            Some(unboost) if !busy && !matches!(task, Task::Unboost(..)) => {
+                if let Task::Route(stops) = &task {
+                    self.home.release_route(stops, self.name());
+                }
+                self.home.release_doer(&self.name());
+                unboost
+            }
+            _ => task,
+        };
 
         match task.run_task(&self.creep, &self.memory.role) {
             TaskResult::StillWorking(task, movement_goal) => {
@@ -89,6 +120,16 @@ impl CrUnit<'_, '_, '_> {
                 self.add_request(another);
                 movement_goal
             }
+            TaskResult::ResolveAndContinue(task, request, movement_goal) => {
+                self.memory.task = Some(task);
+                self.home.resolve_request(request, self.name());
+                movement_goal
+            }
+            TaskResult::ResetAndContinue(task, request, movement_goal) => {
+                self.memory.task = Some(task);
+                self.home.reset_request(&request, self.name());
+                movement_goal
+            }
             TaskResult::Abort => {
                 let task = self.memory.role.get_task(&self.creep, self.home);
                 match task.run_task(&self.creep, &self.memory.role) {
@@ -150,6 +191,8 @@ pub struct CreepMemory {
     pub task: Option<Task>,
     #[serde(skip)]
     pub path_state: Option<PathState>,
+    #[serde(skip)]
+    pub formation_path: Option<FormationPath>,
 }
 
 impl CreepMemory {
@@ -169,6 +212,7 @@ pub fn run_creeps(
     black_list: &HashSet<String>,
 ) {
     let mut creeps: HashMap<String, Creep> = game::creeps().entries().collect();
+    let mut formations: HashMap<String, (Formation, Vec<FormationMember>)> = HashMap::new();
 
     for (name, memory) in creeps_state.iter_mut() {
         let creep = match creeps.remove(name) {
@@ -182,9 +226,23 @@ pub fn run_creeps(
             .and_then(|home_name| homes.get_mut(home_name))
             .map(|home| CrUnit { creep, memory, home })
         {
-            unit.try_respawn();
-            let goal = unit.run_unit(black_list);
-            unit.move_to_goal(goal, movement);
+            let goal = profiler::measure(("role", unit.memory.role.name()), || {
+                unit.try_respawn();
+                unit.run_unit(black_list)
+            });
+            if let Some((squad_id, formation)) = unit.formation() {
+                formations
+                    .entry(squad_id)
+                    .or_insert_with(|| (formation, Vec::new()))
+                    .1
+                    .push(FormationMember { creep: unit.creep, goal });
+            } else {
+                unit.move_to_goal(goal, movement);
+            }
         }
     }
+
+    for (formation, members) in formations.into_values() {
+        run_formation(formation, members, creeps_state, movement);
+    }
 }
@@ -0,0 +1,276 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use screeps::{
+    Creep, HasPosition, Part, Position, SharedCreepProperties, StructureObject, Terrain, find, game,
+};
+
+use super::creeps::CreepMemory;
+use super::move_to_goal_common;
+use crate::commons::has_part;
+use crate::movement::formation::{Formation, FormationPath, quad_positions};
+use crate::movement::{Movement, MovementGoal, MovementGoalBuilder};
+use crate::utils::constants::QUAD_ROTATE_INTERVAL;
+
+pub(super) struct FormationMember {
+    pub creep: Creep,
+    pub goal: Option<MovementGoal>,
+}
+
+pub(super) fn run_formation(
+    formation: Formation,
+    mut members: Vec<FormationMember>,
+    memories: &mut HashMap<String, CreepMemory>,
+    movement: &mut Movement,
+) {
+    // the worker leads, healers follow
+    members
+        .sort_by_key(|member| (has_part(&[Part::Heal], &member.creep, false), member.creep.name()));
+
+    match (formation, members.as_slice()) {
+        (Formation::Duo, [leader, follower]) => run_duo(leader, follower, memories, movement),
+        (Formation::Quad, [_, _, ..]) => run_quad(&members, memories, movement),
+        _ => {
+            for member in members {
+                move_member(&member.creep, member.goal, memories, movement);
+            }
+        }
+    }
+}
+
+fn run_duo(
+    leader: &FormationMember,
+    follower: &FormationMember,
+    memories: &mut HashMap<String, CreepMemory>,
+    movement: &mut Movement,
+) {
+    let (leader_pos, follower_pos) = (leader.creep.pos(), follower.creep.pos());
+    if leader_pos.is_near_to(follower_pos) {
+        if leader.creep.fatigue() > 0 || follower.creep.fatigue() > 0 {
+            //wait for the fatigued member
+            return;
+        }
+        let moving = leader.goal.as_ref().is_some_and(|goal| !goal.is_goal_met(leader_pos));
+        if moving {
+            move_member(&leader.creep, leader.goal.clone(), memories, movement);
+            if let Some(direction) = follower_pos.get_direction_to(leader_pos) {
+                let _ = follower.creep.move_direction(direction);
+            }
+        }
+    } else {
+        // split up, the leader can't wait on the room edge
+        if leader_pos.is_room_edge() {
+            move_member(&leader.creep, leader.goal.clone(), memories, movement);
+        }
+        let goal = MovementGoalBuilder::new(leader_pos).range(1).build();
+        move_member(&follower.creep, Some(goal), memories, movement);
+    }
+}
+
+fn run_quad(
+    members: &[FormationMember],
+    memories: &mut HashMap<String, CreepMemory>,
+    movement: &mut Movement,
+) {
+    let leader_name = members[0].creep.name();
+    let Some(goal) = members.iter().find_map(|member| member.goal.clone()) else {
+        //hold the position
+        return;
+    };
+    let mut path = memories.get_mut(&leader_name).and_then(|memory| memory.formation_path.take());
+
+    if let Some(rally) = path.as_ref().and_then(|path| path.rally) {
+        if !gather(members, rally, memories, movement)
+            && let Some(path) = path.as_mut()
+        {
+            path.rally = None;
+        }
+    } else if let Some(anchor) = formed_anchor(members) {
+        if members.iter().all(|member| member.creep.fatigue() == 0)
+            && !rotate(members, anchor)
+            && !goal.is_goal_met(anchor)
+        {
+            if path
+                .as_ref()
+                .is_none_or(|path| path.goal.pos != goal.pos || path.next_step(anchor).is_none())
+            {
+                path = FormationPath::try_new(anchor, goal);
+            }
+
+            if let Some(path) = path.as_mut()
+                && let Some(next) = path.next_step(anchor)
+            {
+                if next.room_name() != anchor.room_name()
+                    || quad_positions(next).iter().any(|pos| pos.is_room_edge())
+                {
+                    path.rally = path.rally_after(anchor);
+                }
+
+                match path.rally {
+                    Some(rally) => {
+                        gather(members, rally, memories, movement);
+                    }
+                    None => {
+                        if let Some(direction) = anchor.get_direction_to(next) {
+                            for member in members {
+                                let _ = member.creep.move_direction(direction);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    } else {
+        regroup(members, goal, memories, movement);
+    }
+
+    if let Some(memory) = memories.get_mut(&leader_name) {
+        memory.formation_path = path;
+    }
+}
+
+// top left slot of the 2x2 block if all members stand in it and off the room edges
+fn formed_anchor(members: &[FormationMember]) -> Option<Position> {
+    let positions: Vec<Position> = members.iter().map(|member| member.creep.pos()).collect();
+    let x = positions.iter().map(|pos| pos.world_x()).min()?;
+    let y = positions.iter().map(|pos| pos.world_y()).min()?;
+    let anchor = Position::checked_from_world_coords(x, y).ok()?;
+    let slots = quad_positions(anchor);
+
+    (positions.iter().all_unique()
+        && positions.iter().all(|pos| slots.contains(pos) && !pos.is_room_edge()))
+    .then_some(anchor)
+}
+
+// move everyone to the quad slots around the anchor, true if somebody is still on the way
+fn gather(
+    members: &[FormationMember],
+    anchor: Position,
+    memories: &mut HashMap<String, CreepMemory>,
+    movement: &mut Movement,
+) -> bool {
+    let mut on_the_way = false;
+    for (member, slot) in members.iter().zip(quad_positions(anchor)) {
+        if member.creep.pos() != slot {
+            on_the_way = true;
+            let goal = MovementGoalBuilder::new(slot).build();
+            move_member(&member.creep, Some(goal), memories, movement);
+        }
+    }
+    on_the_way
+}
+
+// put the healthiest members toward the threat, true if the quad is rotating. The quad turns
+// at most once an interval, a threat on the move would hold it in place otherwise
+fn rotate(members: &[FormationMember], anchor: Position) -> bool {
+    if !game::time().is_multiple_of(QUAD_ROTATE_INTERVAL) {
+        return false;
+    }
+    let Some(threat) = closest_threat(&members[0].creep, anchor) else {
+        return false;
+    };
+
+    let slots: Vec<Position> = members
+        .iter()
+        .map(|member| member.creep.pos())
+        .sorted_by_key(|pos| pos.get_range_to(threat))
+        .collect();
+    let healthiest = members.iter().sorted_by_key(|member| {
+        let rank = slots.iter().position(|pos| *pos == member.creep.pos());
+        (Reverse(member.creep.hits() * 10 / member.creep.hits_max().max(1)), rank)
+    });
+
+    let mut rotating = false;
+    for (member, slot) in healthiest.zip(slots) {
+        if let Some(direction) = member.creep.pos().get_direction_to(slot) {
+            rotating = true;
+            let _ = member.creep.move_direction(direction);
+        }
+    }
+    rotating
+}
+
+fn closest_threat(creep: &Creep, anchor: Position) -> Option<Position> {
+    let room = creep.room()?;
+    room.find(find::HOSTILE_CREEPS, None)
+        .into_iter()
+        .filter(|hostile| has_part(&[Part::Attack, Part::RangedAttack], hostile, true))
+        .map(|hostile| hostile.pos())
+        .chain(room.find(find::HOSTILE_STRUCTURES, None).into_iter().filter_map(|structure| {
+            match structure {
+                StructureObject::StructureTower(tower) => Some(tower.pos()),
+                _ => None,
+            }
+        }))
+        .min_by_key(|pos| pos.get_range_to(anchor))
+}
+
+// members are scattered: build the quad around the leader or let the leader find a free space
+fn regroup(
+    members: &[FormationMember],
+    goal: MovementGoal,
+    memories: &mut HashMap<String, CreepMemory>,
+    movement: &mut Movement,
+) {
+    let leader = members[0].creep.pos();
+    let anchor = quad_positions(leader)
+        .into_iter()
+        .filter_map(|slot| {
+            let dx = slot.world_x() - leader.world_x();
+            let dy = slot.world_y() - leader.world_y();
+            leader.checked_add((-dx, -dy)).ok()
+        })
+        .find(|anchor| quad_positions(*anchor).iter().all(|pos| is_free(*pos)));
+
+    if let Some(anchor) = anchor {
+        let mut slots = quad_positions(anchor);
+        slots.retain(|slot| *slot != leader);
+        for member in &members[1..] {
+            if let Some((index, slot)) = slots
+                .iter()
+                .copied()
+                .enumerate()
+                .min_by_key(|(_, slot)| member.creep.pos().get_range_to(*slot))
+            {
+                slots.remove(index);
+                if member.creep.pos() != slot {
+                    let goal = MovementGoalBuilder::new(slot).build();
+                    move_member(&member.creep, Some(goal), memories, movement);
+                }
+            }
+        }
+    } else {
+        move_member(&members[0].creep, Some(goal), memories, movement);
+        for member in &members[1..] {
+            let goal = MovementGoalBuilder::new(leader).range(1).build();
+            move_member(&member.creep, Some(goal), memories, movement);
+        }
+    }
+}
+
+fn is_free(pos: Position) -> bool {
+    !pos.is_room_edge()
+        && game::map::get_room_terrain(pos.room_name())
+            .is_some_and(|terrain| terrain.get(pos.x().u8(), pos.y().u8()) != Terrain::Wall)
+}
+
+fn move_member(
+    creep: &Creep,
+    goal: Option<MovementGoal>,
+    memories: &mut HashMap<String, CreepMemory>,
+    movement: &mut Movement,
+) {
+    if let Some(memory) = memories.get_mut(&creep.name()) {
+        let can_move = creep.fatigue() == 0;
+        move_to_goal_common(
+            creep.name().as_str(),
+            creep.pos(),
+            creep.clone().into(),
+            goal,
+            movement,
+            &mut memory.path_state,
+            can_move,
+        );
+    }
+}
@@ -57,6 +57,11 @@ pub trait Kind {
         Task::Idle(10)
     }
 
+    /// Drop the current task for a new one from `get_task`
+    fn preempt(&self, _: &Task, _: &Creep, _: &Shelter) -> bool {
+        false
+    }
+
     fn respawn_timeout(&self, _: Option<&Creep>) -> Option<usize> {
         None
     }
@@ -211,38 +216,29 @@ impl Role {
         }
     }
 
-    //todo add invaded role priority
-    /// The higher the more important
-    pub const fn role_priority(&self) -> i8 {
+    pub fn squad_id(&self) -> Option<&str> {
         match self {
-            Role::Guard(_) => 9,
-            Role::Hauler(_) => 8,
-            Role::Miner(_) => 7,
-            Role::Defender(_) => 6,
-            Role::Trader(_) => 5,
-            Role::Overseer(_)
-            | Role::Upgrader(_)
-            | Role::PBAttacker(_)
-            | Role::PBHealer(_)
-            | Role::DepositMiner(_) => 4,
-            Role::PBCarrier(_) | Role::DepositHauler(_) => 3,
-            Role::SkMiner(_) | Role::Booker(_) | Role::HouseKeeper(_) => 2,
-            Role::Carrier(_) | Role::Dismantler(_) | Role::Puller(_) => 1,
-            Role::Scout(_) => -1,
-            _ => 0,
+            Role::PBAttacker(r) => r.squad_id.as_deref(),
+            Role::PBHealer(r) => r.squad_id.as_deref(),
+            Role::PBCarrier(r) => r.squad_id.as_deref(),
+            Role::CombatDismantler(r) => r.squad_id.as_deref(),
+            Role::CombatHealer(r) => r.squad_id.as_deref(),
+            Role::Healer(r) => r.squad_id.as_deref(),
+            Role::DepositMiner(r) => r.squad_id.as_deref(),
+            Role::DepositHauler(r) => r.squad_id.as_deref(),
+            _ => None,
         }
     }
-}
 
-impl Default for Role {
-    fn default() -> Role {
-        Role::Scout(Scout::new(None, None))
+    /// Number of the part in the body the role spawns with the energy
+    pub fn parts_number(&self, part: Part, energy: u32) -> u32 {
+        let number = self.body(energy).iter().filter(|body_part| **body_part == part).count();
+        u32::try_from(number).unwrap_or_default()
     }
-}
 
-impl Display for Role {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        let name = match self {
+    /// Short name for logs and the profiler
+    pub const fn name(&self) -> &'static str {
+        match self {
             Role::Upgrader(_) => "upgrader",
             Role::RemoteUpgrader(_) => "remote_upgrader",
             Role::Miner(_) => "miner",
@@ -273,8 +269,41 @@ impl Display for Role {
             Role::Overseer(_) => "overseer",
             Role::SkMiner(_) => "sk_miner",
             Role::Fighter(_) => "fighter",
-        };
-        write!(f, "{name}")
+        }
+    }
+
+    //todo add invaded role priority
+    /// The higher the more important
+    pub const fn role_priority(&self) -> i8 {
+        match self {
+            Role::Guard(_) => 9,
+            Role::Hauler(_) => 8,
+            Role::Miner(_) => 7,
+            Role::Defender(_) => 6,
+            Role::Trader(_) => 5,
+            Role::Overseer(_)
+            | Role::Upgrader(_)
+            | Role::PBAttacker(_)
+            | Role::PBHealer(_)
+            | Role::DepositMiner(_) => 4,
+            Role::PBCarrier(_) | Role::DepositHauler(_) => 3,
+            Role::SkMiner(_) | Role::Booker(_) | Role::HouseKeeper(_) => 2,
+            Role::Carrier(_) | Role::Dismantler(_) | Role::Puller(_) => 1,
+            Role::Scout(_) => -1,
+            _ => 0,
+        }
+    }
+}
+
+impl Default for Role {
+    fn default() -> Role {
+        Role::Scout(Scout::new(None, None))
+    }
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
     }
 }
 
@@ -65,7 +65,7 @@ impl Kind for Defender {
     }
 
     fn boosts(&self, creep: &Creep) -> HashMap<Part, [ResourceType; 2]> {
-        if self.boost && creep.ticks_to_live().is_some_and(|tick| tick > 1350) {
+        if self.boost && creep.ticks_to_live().is_none_or(|tick| tick > 1350) {
             [
                 (
                     Part::RangedAttack,
@@ -83,11 +83,7 @@ impl Kind for Defender {
     }
 
     fn get_task(&self, creep: &Creep, home: &mut Shelter) -> Task {
-        home.get_available_boost(creep, self.boosts(creep))
-            .map(|(id, body_part)| {
-                let parts_number = creep.body().iter().filter(|bp| bp.part() == body_part).count();
-                Task::Boost(id, u32::try_from(parts_number).ok())
-            })
+        home.boost_task(creep, self.boosts(creep))
             .or_else(|| {
                 get_request(home).and_then(|req| {
                     home.take_request(&req).map(|mut req| {
@@ -95,7 +95,7 @@ impl Kind for Fighter {
 
     fn boosts(&self, creep: &Creep) -> HashMap<Part, [ResourceType; 2]> {
         //todo change ticks to boost here when labs carry requests will be fixed
-        if self.boost && creep.ticks_to_live().is_some_and(|tick| tick > 1000) {
+        if self.boost && creep.ticks_to_live().is_none_or(|tick| tick > 1000) {
             [
                 (
                     Part::Move,
@@ -121,11 +121,7 @@ impl Kind for Fighter {
     }
 
     fn get_task(&self, creep: &Creep, home: &mut Shelter) -> Task {
-        home.get_available_boost(creep, self.boosts(creep))
-            .map(|(id, body_part)| {
-                let parts_number = creep.body().iter().filter(|bp| bp.part() == body_part).count();
-                Task::Boost(id, u32::try_from(parts_number).ok())
-            })
+        home.boost_task(creep, self.boosts(creep))
             .or_else(|| self.target.map(|target| Task::Protect(target, None)))
             .unwrap_or_default()
     }
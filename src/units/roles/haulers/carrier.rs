@@ -2,7 +2,7 @@ use std::fmt;
 
 use arrayvec::ArrayVec;
 use log::warn;
-use screeps::{Creep, HasId, HasPosition, Part, RoomName, SharedCreepProperties};
+use screeps::{Creep, HasId, HasPosition, Part, ResourceType, RoomName, SharedCreepProperties};
 use serde::{Deserialize, Serialize};
 
 use super::{Kind, Task, can_scale};
@@ -40,7 +40,17 @@ impl Kind for Carrier {
                 .next()
                 .expect("expect resource in a creep!");
 
-            if let Some(storage) = home.storage() {
+            // the energy goes into the edge link on the way when it has room
+            if let Some(link) = home.edge_link().filter(|link| {
+                resource == ResourceType::Energy
+                    && link.store().get_free_capacity(Some(ResourceType::Energy)) > 0
+                    && home.storage().is_none_or(|storage| {
+                        let range = creep.pos().get_range_to(link.pos());
+                        range < creep.pos().get_range_to(storage.pos())
+                    })
+            }) {
+                Task::DeliverToStructure(link.pos(), link.raw_id(), resource, None)
+            } else if let Some(storage) = home.storage() {
                 Task::DeliverToStructure(storage.pos(), storage.raw_id(), resource, None)
             } else {
                 warn!("{} {} there is no place to store! drop?", home.name(), creep.name());
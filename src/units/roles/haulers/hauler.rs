@@ -2,7 +2,6 @@ use std::fmt;
 
 use arrayvec::ArrayVec;
 use log::warn;
-use screeps::constants::ResourceType;
 use screeps::objects::Creep;
 use screeps::prelude::*;
 use screeps::{Part, RoomName};
@@ -10,9 +9,8 @@ use serde::{Deserialize, Serialize};
 
 use super::{Kind, Task, can_scale, default_parts_priority};
 use crate::movement::MovementProfile;
+use crate::rooms::logistics::{self, Courier};
 use crate::rooms::shelter::Shelter;
-use crate::rooms::state::requests::meta::Status;
-use crate::rooms::state::requests::{Request, RequestKind};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Hauler {
@@ -59,112 +57,21 @@ impl Kind for Hauler {
     }
 
     fn get_task(&self, creep: &Creep, home: &mut Shelter) -> Task {
-        get_active_job(home, creep)
-            .map(|req| req.kind.into())
+        home.plan_route(creep, Courier::Hauler)
+            .map(Task::Route)
             .or_else(|| {
-                (creep.store().get_used_capacity(None) > 0)
-                    .then(|| {
-                        let resource = creep
-                            .store()
-                            .store_types()
-                            .into_iter()
-                            .next()
-                            .expect("expect resource in a creep!");
-
-                        if let Some(structure) = home.closest_empty_structure(creep)
-                            && can_fill(structure.free_capacity(), creep)
-                        {
-                            return Task::FillStructure(structure);
-                        }
-
-                        if let Some(storage) = home
-                            .storage()
-                            .filter(|storage| storage.store().get_free_capacity(None) > 5000)
-                        {
-                            Task::DeliverToStructure(
-                                storage.pos(),
-                                storage.raw_id(),
-                                resource,
-                                None,
-                            )
-                        } else {
-                            warn!(
-                                "{} {} there is no place to store! drop?",
-                                home.name(),
-                                creep.name()
-                            );
-                            let _ = creep.drop(resource, None);
-                            Task::Idle(1)
-                        }
-                    })
-                    .or_else(|| {
-                        get_new_job(home).and_then(|req| home.take_request(&req)).map(|mut req| {
-                            // req.begin(creep.name());
-                            req.join(Some(creep.name()), None);
-                            home.add_request(req.clone());
-                            req.kind.into()
-                        })
-                    })
+                // nobody needs the cargo and there is no storage
+                creep.store().store_types().into_iter().next().map(|resource| {
+                    warn!("{} {} there is no place to store! drop?", home.name(), creep.name());
+                    let _ = creep.drop(resource, None);
+                    Task::Idle(1)
+                })
             })
-            .or_else(|| take_energy(home, creep))
             .unwrap_or_default()
     }
-}
-
-fn can_fill(str_free_capacity: i32, creep: &Creep) -> bool {
-    let energy_in_store = creep.store().get_used_capacity(Some(ResourceType::Energy));
-    u32::try_from(str_free_capacity).ok().is_some_and(|free_capacity| {
-        free_capacity <= energy_in_store || energy_in_store == creep.store().get_capacity(None)
-    })
-}
-
-fn take_energy(home: &Shelter, creep: &Creep) -> Option<Task> {
-    (home.closest_empty_structure(creep).is_some())
-        .then(|| {
-            home.storage()
-                .filter(|storage| {
-                    storage.store().get_used_capacity(Some(ResourceType::Energy)) > 2000
-                })
-                .map(|storage| {
-                    Task::TakeFromStructure(
-                        storage.pos(),
-                        storage.raw_id(),
-                        ResourceType::Energy,
-                        None,
-                    )
-                })
-                .or_else(|| {
-                    home.factory()
-                        .filter(|factory| {
-                            factory.store().get_used_capacity(Some(ResourceType::Energy)) > 1000
-                        })
-                        .map(|factory| {
-                            Task::TakeFromStructure(
-                                factory.pos(),
-                                factory.raw_id(),
-                                ResourceType::Energy,
-                                None,
-                            )
-                        })
-                })
-        })
-        .flatten()
-}
 
-fn get_active_job(home: &Shelter, creep: &Creep) -> Option<Request> {
-    home.requests()
-        .find(|r| {
-            matches!(&r.kind, RequestKind::Withdraw(_) | RequestKind::Pickup(_)
-            if matches!(*r.status(), Status::InProgress) && r.assigned_to(&creep.name()))
-        })
-        .cloned()
-}
-
-fn get_new_job(home: &Shelter) -> Option<Request> {
-    home.requests()
-        .find(|r| {
-            matches!(&r.kind, RequestKind::Withdraw(_) | RequestKind::Pickup(_)
-            if matches!(*r.status(), Status::Created))
-        })
-        .cloned()
+    fn preempt(&self, task: &Task, creep: &Creep, home: &Shelter) -> bool {
+        matches!(task, Task::Route(stops)
+            if logistics::should_preempt(home, creep, Courier::Hauler, stops))
+    }
 }
@@ -45,7 +45,7 @@ impl Kind for Dismantler {
     }
 
     fn boosts(&self, creep: &Creep) -> HashMap<Part, [ResourceType; 2]> {
-        if creep.ticks_to_live().is_some_and(|tick| tick > 1400) {
+        if creep.ticks_to_live().is_none_or(|tick| tick > 1400) {
             [(Part::Work, [ResourceType::CatalyzedZynthiumAcid, ResourceType::ZynthiumAcid])].into()
         } else {
             HashMap::new()
@@ -53,11 +53,7 @@ impl Kind for Dismantler {
     }
 
     fn get_task(&self, creep: &Creep, home: &mut Shelter) -> Task {
-        home.get_available_boost(creep, self.boosts(creep))
-            .map(|(id, body_part)| {
-                let parts_number = creep.body().iter().filter(|bp| bp.part() == body_part).count();
-                Task::Boost(id, u32::try_from(parts_number).ok())
-            })
+        home.boost_task(creep, self.boosts(creep))
             .or_else(|| {
                 get_active_job(home, creep).map(|req| req.kind.into()).or_else(|| {
                     get_new_job(home).and_then(|req| home.take_request(&req)).map(|mut req| {
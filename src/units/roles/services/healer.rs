@@ -70,7 +70,7 @@ impl Kind for Healer {
 
     fn boosts(&self, creep: &Creep) -> HashMap<Part, [ResourceType; 2]> {
         //todo change ticks to boost here when labs carry requests will be fixed
-        if creep.ticks_to_live().is_some_and(|tick| tick > 1300) {
+        if creep.ticks_to_live().is_none_or(|tick| tick > 1300) {
             [
                 (
                     Part::Move,
@@ -96,11 +96,7 @@ impl Kind for Healer {
     }
 
     fn get_task(&self, creep: &Creep, home: &mut Shelter) -> Task {
-        home.get_available_boost(creep, self.boosts(creep))
-            .map(|(id, body_part)| {
-                let parts_number = creep.body().iter().filter(|bp| bp.part() == body_part).count();
-                Task::Boost(id, u32::try_from(parts_number).ok())
-            })
+        home.boost_task(creep, self.boosts(creep))
             .or_else(|| self.squad_id
                 .as_ref()
                 .and_then(|sid| {
@@ -56,7 +56,7 @@ impl Kind for HouseKeeper {
     }
 
     fn boosts(&self, creep: &Creep) -> HashMap<Part, [ResourceType; 2]> {
-        if creep.ticks_to_live().is_some_and(|tick| tick > 800) {
+        if creep.ticks_to_live().is_none_or(|tick| tick > 800) {
             [(Part::Work, [ResourceType::CatalyzedLemergiumAcid, ResourceType::LemergiumAcid])]
                 .into()
         } else {
@@ -65,11 +65,7 @@ impl Kind for HouseKeeper {
     }
 
     fn get_task(&self, creep: &Creep, home: &mut Shelter) -> Task {
-        home.get_available_boost(creep, self.boosts(creep))
-            .map(|(id, body_part)| {
-                let parts_number = creep.body().iter().filter(|bp| bp.part() == body_part).count();
-                Task::Boost(id, u32::try_from(parts_number).ok())
-            })
+        home.boost_task(creep, self.boosts(creep))
             .or_else(|| {
                 (creep.store().get_used_capacity(Some(ResourceType::Energy)) == 0)
                     .then(|| find_energy(creep, home))
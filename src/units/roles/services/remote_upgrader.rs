@@ -47,7 +47,7 @@ impl Kind for RemoteUpgrader {
     }
 
     fn boosts(&self, creep: &Creep) -> HashMap<Part, [ResourceType; 2]> {
-        if self.boost && creep.ticks_to_live().is_some_and(|tick| tick > 1450) {
+        if self.boost && creep.ticks_to_live().is_none_or(|tick| tick > 1450) {
             [(Part::Carry, [ResourceType::CatalyzedKeaniumAcid, ResourceType::KeaniumAcid])].into()
         } else {
             HashMap::new()
@@ -89,11 +89,7 @@ impl Kind for RemoteUpgrader {
     }
 
     fn get_task(&self, creep: &Creep, home: &mut Shelter) -> Task {
-        home.get_available_boost(creep, self.boosts(creep))
-            .map(|(id, body_part)| {
-                let parts_number = creep.body().iter().filter(|bp| bp.part() == body_part).count();
-                Task::Boost(id, u32::try_from(parts_number).ok())
-            })
+        home.boost_task(creep, self.boosts(creep))
             .or_else(|| {
                 (creep.store().get_used_capacity(Some(ResourceType::Energy)) > 0)
                     .then(|| Task::Upgrade(self.ctrl, None))
@@ -1,15 +1,14 @@
 use std::fmt;
 
 use arrayvec::ArrayVec;
-use log::{debug, warn};
-use screeps::{Creep, HasId, HasPosition, Part, ResourceType, RoomName, SharedCreepProperties};
+use log::warn;
+use screeps::{Creep, HasId, HasPosition, Part, ResourceType, RoomName};
 use serde::{Deserialize, Serialize};
 
 use super::{Kind, Task, can_scale, default_parts_priority};
 use crate::movement::MovementProfile;
+use crate::rooms::logistics::{self, Courier};
 use crate::rooms::shelter::Shelter;
-use crate::rooms::state::requests::meta::Status;
-use crate::rooms::state::requests::{Request, RequestKind};
 use crate::utils::constants::MIN_ENERGY_CAPACITY;
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -54,40 +53,41 @@ impl Kind for Trader {
         creep.map(|c| c.body().len() * 3).or(Some(0))
     }
 
+    fn preempt(&self, task: &Task, creep: &Creep, home: &Shelter) -> bool {
+        matches!(task, Task::Route(stops)
+            if logistics::should_preempt(home, creep, Courier::Trader, stops))
+    }
+
     fn get_task(&self, creep: &Creep, home: &mut Shelter) -> Task {
-        get_active_job(home, creep)
-            .map(|req| req.kind.into())
-            .or_else(|| {
-                (creep.store().get_used_capacity(None) > 0)
-                    //if no active request found, but something is in store - get rid of it
-                    .then(|| {
-                        let resource = creep
-                            .store()
-                            .store_types()
-                            .into_iter()
-                            .next()
-                            .expect("expect something in the creep store");
-                        if let Some(sender) = home.empty_sender()
-                            && resource == ResourceType::Energy
-                        {
-                            //if resource energy and sender link is empty -> fill it
-                            Task::DeliverToStructure(
-                                sender.pos(),
-                                sender.raw_id(),
-                                ResourceType::Energy,
-                                None,
-                            )
-                        } else if let Some(bank) = home
-                            .storage()
-                            .filter(|storage| storage.store().get_free_capacity(None) > 10_000)
-                        {
-                            Task::DeliverToStructure(bank.pos(), bank.raw_id(), resource, None)
-                        } else {
-                            warn!("{} no free storage space here!", home.name());
-                            // let _ = creep.drop(resource, None);
-                            Task::Idle(1)
-                        }
-                    })
+        (creep.store().get_used_capacity(None) > 0)
+            //if no active request found, but something is in store - get rid of it
+            .then(|| {
+                let resource = creep
+                    .store()
+                    .store_types()
+                    .into_iter()
+                    .next()
+                    .expect("expect something in the creep store");
+                if let Some(sender) = home.empty_sender()
+                    && resource == ResourceType::Energy
+                {
+                    //if resource energy and sender link is empty -> fill it
+                    Task::DeliverToStructure(
+                        sender.pos(),
+                        sender.raw_id(),
+                        ResourceType::Energy,
+                        None,
+                    )
+                } else if let Some(bank) = home
+                    .storage()
+                    .filter(|storage| storage.store().get_free_capacity(None) > 10_000)
+                {
+                    Task::DeliverToStructure(bank.pos(), bank.raw_id(), resource, None)
+                } else {
+                    warn!("{} no free storage space here!", home.name());
+                    // let _ = creep.drop(resource, None);
+                    Task::Idle(1)
+                }
             })
             .or_else(|| {
                 home.full_receiver()
@@ -122,37 +122,12 @@ impl Kind for Trader {
                     })
                     .flatten()
             })
-            // get new job if more then 10 ticks
+            // plan a route if more then 10 ticks
             .or_else(|| {
                 (creep.ticks_to_live().is_some_and(|ticks| ticks > 10))
-                    .then(|| {
-                        get_new_job(home).and_then(|req| home.take_request(&req)).map(|mut req| {
-                            req.join(Some(creep.name()), None);
-                            home.add_request(req.clone());
-                            debug!("{}: {} got_new_job: {}", home.name(), creep.name(), req);
-                            req.kind.into()
-                        })
-                    })
+                    .then(|| home.plan_route(creep, Courier::Trader).map(Task::Route))
                     .flatten()
             })
             .unwrap_or_default()
     }
 }
-
-fn get_active_job(home: &Shelter, creep: &Creep) -> Option<Request> {
-    home.requests()
-        .find(|r| {
-            matches!(&r.kind, RequestKind::Carry(_) if
-            matches!(*r.status(), Status::InProgress) && r.assigned_to(&creep.name()))
-        })
-        .cloned()
-}
-
-fn get_new_job(home: &Shelter) -> Option<Request> {
-    home.requests()
-        .find(|r| {
-            matches!(&r.kind, RequestKind::Carry(_) if
-            matches!(*r.status(), Status::Created))
-        })
-        .cloned()
-}
@@ -12,6 +12,9 @@ use crate::rooms::shelter::Shelter;
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Upgrader {
     pub(crate) home: Option<RoomName>,
+    /// work parts planned by the upgrade strategy
+    #[serde(default)]
+    pub work: Option<u32>,
 }
 
 impl fmt::Debug for Upgrader {
@@ -22,7 +25,11 @@ impl fmt::Debug for Upgrader {
 
 impl Upgrader {
     pub const fn new(home: Option<RoomName>) -> Self {
-        Self { home }
+        Self { home, work: None }
+    }
+
+    pub const fn with_work(home: Option<RoomName>, work: u32) -> Self {
+        Self { home, work: Some(work) }
     }
 }
 
@@ -31,7 +38,9 @@ impl Kind for Upgrader {
         let scale_parts = [Part::Work, Part::Work, Part::Work, Part::Work, Part::Work, Part::Move];
         let mut body = iter::once(Part::Carry).collect::<ArrayVec<[Part; 50]>>();
 
-        let parts_limit = limit_based_on_controller_level(self.home);
+        // 5 work and a move per group
+        let parts_limit = self.work.map_or(50, |work| 1 + work.div_ceil(5) as usize * 6);
+        let parts_limit = limit_based_on_controller_level(self.home).min(parts_limit);
         while can_scale(body.clone(), scale_parts.to_vec(), room_energy, parts_limit) {
             body.extend(scale_parts.iter().copied());
         }
@@ -49,7 +58,7 @@ impl Kind for Upgrader {
     }
 
     fn boosts(&self, creep: &Creep) -> HashMap<Part, [ResourceType; 2]> {
-        if creep.ticks_to_live().is_some_and(|tick| tick > 1450) {
+        if creep.ticks_to_live().is_none_or(|tick| tick > 1450) {
             [(Part::Work, [ResourceType::CatalyzedGhodiumAcid, ResourceType::GhodiumAcid])].into()
         } else {
             HashMap::new()
@@ -57,11 +66,7 @@ impl Kind for Upgrader {
     }
 
     fn get_task(&self, creep: &Creep, home: &mut Shelter) -> Task {
-        home.get_available_boost(creep, self.boosts(creep))
-            .map(|(id, body_part)| {
-                let parts_number = creep.body().iter().filter(|bp| bp.part() == body_part).count();
-                Task::Boost(id, u32::try_from(parts_number).ok())
-            })
+        home.boost_task(creep, self.boosts(creep))
             .or_else(|| {
                 home.find_container_in_range(home.controller().pos(), 2).map(|(id, pos)| {
                     if creep.store().get_used_capacity(Some(ResourceType::Energy)) > 0 {
@@ -15,6 +15,8 @@ use crate::units::tasks::Task;
 pub struct ComDismantler {
     pub(crate) squad_id: Option<String>,
     pub(crate) home: Option<RoomName>,
+    #[serde(default = "super::boosted")]
+    pub(crate) boosted: bool,
 }
 
 impl fmt::Debug for ComDismantler {
@@ -31,7 +33,12 @@ impl fmt::Debug for ComDismantler {
 
 impl ComDismantler {
     pub const fn new(squad_id: Option<String>, home: Option<RoomName>) -> Self {
-        Self { squad_id, home }
+        Self { squad_id, home, boosted: true }
+    }
+
+    pub const fn with_boosts(mut self, boosted: bool) -> Self {
+        self.boosted = boosted;
+        self
     }
 }
 
@@ -64,7 +71,7 @@ impl Kind for ComDismantler {
 
     fn boosts(&self, creep: &Creep) -> HashMap<Part, [ResourceType; 2]> {
         //todo change ticks to boost here when labs carry requests will be fixed
-        if creep.ticks_to_live().is_some_and(|tick| tick > 1300) {
+        if self.boosted && creep.ticks_to_live().is_none_or(|tick| tick > 1300) {
             [
                 (
                     Part::Move,
@@ -86,11 +93,7 @@ impl Kind for ComDismantler {
     }
 
     fn get_task(&self, creep: &Creep, home: &mut Shelter) -> Task {
-        home.get_available_boost(creep, self.boosts(creep))
-            .map(|(id, body_part)| {
-                let parts_number = creep.body().iter().filter(|bp| bp.part() == body_part).count();
-                Task::Boost(id, u32::try_from(parts_number).ok())
-            })
+        home.boost_task(creep, self.boosts(creep))
             .or_else(|| self.squad_id
                 .as_ref()
                 .and_then(|sid| {
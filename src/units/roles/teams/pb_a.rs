@@ -1,10 +1,11 @@
+use std::collections::HashMap;
 use std::fmt;
 
 use arrayvec::ArrayVec;
 use log::debug;
 use screeps::objects::Creep;
 use screeps::prelude::*;
-use screeps::{Part, RoomName};
+use screeps::{Part, ResourceType, RoomName};
 use serde::{Deserialize, Serialize};
 
 use super::{Kind, Task, can_scale};
@@ -56,17 +57,33 @@ impl Kind for PBAttacker {
         MovementProfile::PlainsOneToOne
     }
 
+    fn boosts(&self, creep: &Creep) -> HashMap<Part, [ResourceType; 2]> {
+        if creep.ticks_to_live().is_none_or(|tick| tick > 1300) {
+            [(Part::Attack, [ResourceType::CatalyzedUtriumAcid, ResourceType::UtriumAcid])].into()
+        } else {
+            HashMap::new()
+        }
+    }
+
     fn get_task(&self, creep: &Creep, home: &mut Shelter) -> Task {
-        self.squad_id
-            .as_ref()
-            .and_then(|sid| {
-                get_request(home, sid).and_then(|req| home.take_request(&req)).map(|mut req| {
-                    debug!("{} found pb request {:?}", creep.name(), req);
-                    req.join(Some(creep.name()), Some(sid));
-                    home.add_request(req.clone());
-                    (req, Role::PBAttacker(self.clone())).into()
-                })
-            })
+        // boosts are reserved for the banks worth it
+        let boosted = self.squad_id.as_ref().and_then(|sid| get_request(home, sid)).is_some_and(
+            |req| matches!(&req.kind, RequestKind::Powerbank(d) if d.boosted()),
+        );
+
+        boosted
+            .then(|| home.boost_task(creep, self.boosts(creep)))
+            .flatten()
+            .or_else(|| self.squad_id
+                .as_ref()
+                .and_then(|sid| {
+                    get_request(home, sid).and_then(|req| home.take_request(&req)).map(|mut req| {
+                        debug!("{} found pb request {:?}", creep.name(), req);
+                        req.join(Some(creep.name()), Some(sid));
+                        home.add_request(req.clone());
+                        (req, Role::PBAttacker(self.clone())).into()
+                    })
+                }))
             .unwrap_or_default()
     }
 }
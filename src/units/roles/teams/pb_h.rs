@@ -1,8 +1,9 @@
+use std::collections::HashMap;
 use std::fmt;
 
 use arrayvec::ArrayVec;
 use screeps::objects::Creep;
-use screeps::{Part, RoomName, SharedCreepProperties};
+use screeps::{Part, ResourceType, RoomName, SharedCreepProperties};
 use serde::{Deserialize, Serialize};
 
 use super::{Kind, Task, can_scale};
@@ -52,16 +53,32 @@ impl Kind for PBHealer {
         MovementProfile::PlainsOneToOne
     }
 
+    fn boosts(&self, creep: &Creep) -> HashMap<Part, [ResourceType; 2]> {
+        if creep.ticks_to_live().is_none_or(|tick| tick > 1300) {
+            [(Part::Heal, [ResourceType::CatalyzedLemergiumAlkalide, ResourceType::LemergiumAlkalide])].into()
+        } else {
+            HashMap::new()
+        }
+    }
+
     fn get_task(&self, creep: &Creep, home: &mut Shelter) -> Task {
-        self.squad_id
-            .as_ref()
-            .and_then(|sid| {
-                get_request(home, sid).and_then(|req| home.take_request(&req)).map(|mut req| {
-                    req.join(Some(creep.name()), Some(sid));
-                    home.add_request(req.clone());
-                    (req, Role::PBHealer(self.clone())).into()
-                })
-            })
+        // boosts are reserved for the banks worth it
+        let boosted = self.squad_id.as_ref().and_then(|sid| get_request(home, sid)).is_some_and(
+            |req| matches!(&req.kind, RequestKind::Powerbank(d) if d.boosted()),
+        );
+
+        boosted
+            .then(|| home.boost_task(creep, self.boosts(creep)))
+            .flatten()
+            .or_else(|| self.squad_id
+                .as_ref()
+                .and_then(|sid| {
+                    get_request(home, sid).and_then(|req| home.take_request(&req)).map(|mut req| {
+                        req.join(Some(creep.name()), Some(sid));
+                        home.add_request(req.clone());
+                        (req, Role::PBHealer(self.clone())).into()
+                    })
+                }))
             .unwrap_or_default()
     }
 }
@@ -14,9 +14,11 @@ use super::roles::Role;
 use super::with_parts;
 use crate::commons::{closest_attacker, find_closest_exit, find_hostiles, say_message};
 use crate::movement::walker::Walker;
+use crate::rooms::logistics::Stop;
 use crate::rooms::state::requests::{Request, RequestKind};
 use crate::rooms::wrappers::Fillable;
 use crate::units::{MovementGoal, has_part};
+use crate::utils::profiler;
 
 /// Distance from center to edge
 const PROBE_DISTANCE: u32 = 24;
@@ -33,6 +35,7 @@ mod logistics;
 mod powerbank;
 mod repair;
 mod reservation;
+mod siege;
 mod upgrade;
 mod support;
 
@@ -62,6 +65,8 @@ pub enum Task {
     FillStructure(Box<dyn Fillable>),
     Dismantle(ObjectId<Structure>, Position),
     CombatDismantle(ObjectId<Structure>, Position, HashSet<String>),
+    Assemble(Position),
+    Breach(ObjectId<Structure>, Position, HashSet<String>, u8),
     PullTo(String, Position),
     TakeFromStructure(Position, RawObjectId, ResourceType, Option<u32>),
     DeliverToStructure(Position, RawObjectId, ResourceType, Option<u32>),
@@ -69,7 +74,9 @@ pub enum Task {
     LongRangeWithdraw(Position, RawObjectId, ResourceType, u32),
     GenerateSafeMode(Position, ObjectId<StructureController>, RawObjectId),
     Carry(RawObjectId, RawObjectId, ResourceType, u32, Option<Box<Task>>),
-    Boost(ObjectId<StructureLab>, Option<u32>),
+    Route(Vec<Stop>),
+    Boost(Vec<(ObjectId<StructureLab>, u32)>),
+    Unboost(ObjectId<StructureLab>, Position),
     Book(ObjectId<StructureController>, Position),
     Claim(ObjectId<StructureController>, Position),
     Crash(ObjectId<StructureInvaderCore>, Position),
@@ -90,6 +97,59 @@ impl Default for Task {
 
 impl Task {
     pub fn run_task(self, creep: &Creep, role: &Role) -> TaskResult {
+        profiler::measure(("task", self.name()), || self.run(creep, role))
+    }
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Task::DefendHome => "defend_home",
+            Task::MoveMe(..) => "move_me",
+            Task::Portal(..) => "portal",
+            Task::Flee(..) => "flee",
+            Task::Escape(..) => "escape",
+            Task::Provoke(..) => "provoke",
+            Task::Hide(..) => "hide",
+            Task::Build(..) => "build",
+            Task::Repair(..) => "repair",
+            Task::HarvestEnergyForever(..) => "harvest_energy_forever",
+            Task::HarvestMineral(..) => "harvest_mineral",
+            Task::Harvest(..) => "harvest",
+            Task::HarvestAndUpgrade(..) => "harvest_and_upgrade",
+            Task::PowerbankAttack(..) => "powerbank_attack",
+            Task::PowerbankCarry(..) => "powerbank_carry",
+            Task::DepositHarvest(..) => "deposit_harvest",
+            Task::DepositCarry(..) => "deposit_carry",
+            Task::Upgrade(..) => "upgrade",
+            Task::TakeResource(..) => "take_resource",
+            Task::FillStructure(..) => "fill_structure",
+            Task::Dismantle(..) => "dismantle",
+            Task::CombatDismantle(..) => "combat_dismantle",
+            Task::Assemble(..) => "assemble",
+            Task::Breach(..) => "breach",
+            Task::PullTo(..) => "pull_to",
+            Task::TakeFromStructure(..) => "take_from_structure",
+            Task::DeliverToStructure(..) => "deliver_to_structure",
+            Task::Withdraw(..) => "withdraw",
+            Task::LongRangeWithdraw(..) => "long_range_withdraw",
+            Task::GenerateSafeMode(..) => "generate_safe_mode",
+            Task::Carry(..) => "carry",
+            Task::Route(..) => "route",
+            Task::Boost(..) => "boost",
+            Task::Unboost(..) => "unboost",
+            Task::Book(..) => "book",
+            Task::Claim(..) => "claim",
+            Task::Crash(..) => "crash",
+            Task::Heal(..) => "heal",
+            Task::HealAll => "heal_all",
+            Task::Oversee(..) => "oversee",
+            Task::Protect(..) => "protect",
+            Task::Defend(..) => "defend",
+            Task::Idle(..) => "idle",
+            Task::Speak => "speak",
+        }
+    }
+
+    fn run(self, creep: &Creep, role: &Role) -> TaskResult {
         let room = creep.room().expect("expect creep is in a room!");
         let hostiles: Vec<Creep> = find_hostiles(&room, Vec::new()).collect();
 
@@ -241,7 +301,8 @@ impl Task {
                 role,
                 with_parts(hostiles, vec![Part::RangedAttack, Part::Attack]),
             ),
-            Task::Boost(id, parts_number) => boost::boost(id, parts_number, creep, role),
+            Task::Boost(labs) => boost::boost(labs, creep, role),
+            Task::Unboost(id, pos) => boost::unboost(id, pos, creep, role),
             Task::Harvest(workplace, id) => harvest::harvest_until_full(
                 workplace,
                 id,
@@ -352,6 +413,21 @@ impl Task {
                 role,
                 with_parts(hostiles, vec![Part::Attack, Part::RangedAttack]),
             ),
+            Task::Assemble(staging) => siege::assemble(
+                staging,
+                creep,
+                role,
+                with_parts(hostiles, vec![Part::Attack, Part::RangedAttack]),
+            ),
+            Task::Breach(id, workplace, members, retreat) => siege::breach(
+                id,
+                workplace,
+                members,
+                retreat,
+                creep,
+                role,
+                with_parts(hostiles, vec![Part::Attack, Part::RangedAttack]),
+            ),
             Task::TakeResource(id) => logistics::take_resource(
                 id,
                 creep,
@@ -418,6 +494,12 @@ impl Task {
                 role,
                 with_parts(hostiles, vec![Part::RangedAttack, Part::Attack]),
             ),
+            Task::Route(stops) => logistics::route(
+                stops,
+                creep,
+                role,
+                with_parts(hostiles, vec![Part::RangedAttack, Part::Attack]),
+            ),
             Task::DefendHome => combat::defend_home(creep, role, hostiles),
             Task::Crash(id, pos) => combat::crash(id, pos, creep, role, hostiles),
             Task::HealAll => combat::heal_all(creep, role, hostiles),
@@ -459,7 +541,8 @@ impl fmt::Debug for Task {
             Task::Oversee(room_name, target) => {
                 write!(f, "Task::Oversee[{room_name}, {target:?}]")
             }
-            Task::Boost(id, parts) => write!(f, "Task::Boost[{id}, {parts:?}]"),
+            Task::Boost(labs) => write!(f, "Task::Boost[{labs:?}]"),
+            Task::Unboost(id, pos) => write!(f, "Task::Unboost[{id}, {pos}]"),
             Task::Crash(id, pos) => write!(f, "Task::Crash[{id}, {pos:?}]"),
             Task::Defend(room_name) => {
                 write!(f, "Task::Defend[{room_name}]")
@@ -488,6 +571,10 @@ impl fmt::Debug for Task {
             Task::CombatDismantle(id, pos, members) => {
                 write!(f, "Task::CombatDismantle[{id}, {pos}, {members:?}]")
             }
+            Task::Assemble(pos) => write!(f, "Task::Assemble[{pos}]"),
+            Task::Breach(id, pos, members, retreat) => {
+                write!(f, "Task::Breach[{id}, {pos}, {members:?}, {retreat}]")
+            }
             Task::PullTo(name, pos) => write!(f, "Task::PullTo[{name}, {pos}]"),
             Task::Book(id, pos) => write!(f, "Task::Book[{id}, {pos}]"),
             Task::Claim(id, pos) => write!(f, "Task::Claim[{id}, {pos}]"),
@@ -514,6 +601,7 @@ impl fmt::Debug for Task {
             Task::Carry(from, to, resource, amount, _) => {
                 write!(f, "Task::Carry[{from}, {to}, {resource}, {amount}]")
             }
+            Task::Route(stops) => write!(f, "Task::Route[{} stops]", stops.len()),
         }
     }
 }
@@ -548,6 +636,16 @@ impl From<(Request, Role)> for Task {
                 .and_then(|squad_id| req.assignment.squads_members(squad_id))
                 .map(|members| Task::Heal(members))
                 .unwrap_or_default(),
+            (RequestKind::Siege(d), Role::CombatDismantler(com_d)) => com_d
+                .squad_id
+                .as_ref()
+                .and_then(|squad_id| req.assignment.squads_members(squad_id))
+                .map_or_else(Task::default, |members| d.dismantler_task(members)),
+            (RequestKind::Siege(_), Role::CombatHealer(h)) => h
+                .squad_id
+                .as_ref()
+                .and_then(|squad_id| req.assignment.squads_members(squad_id))
+                .map_or_else(Task::default, Task::Heal),
             (kind, _) => <Task as From<RequestKind>>::from(kind),
         }
     }
@@ -587,6 +685,10 @@ pub enum TaskResult {
     UpdateRequest(Task),        /* update room request (in the middle of doing something,
                                  * partially carried resource or repair structure) */
     AddNewRequest(Task, Task, Option<MovementGoal>),
+    ResolveAndContinue(Task, Request, Option<MovementGoal>), /* resolve the request served
+                                                               * by a route stop and keep going */
+    ResetAndContinue(Task, Request, Option<MovementGoal>), /* hand a request the route failed
+                                                             * back to others and keep going */
     Suicide, //finish a task with suicide
     Completed,
     #[default]
@@ -1,5 +1,5 @@
 use log::{error, info};
-use screeps::{Creep, HasId, HasPosition, ObjectId, SharedCreepProperties, StructureLab};
+use screeps::{Creep, HasId, HasPosition, ObjectId, Position, SharedCreepProperties, StructureLab};
 
 use crate::movement::MovementProfile;
 use crate::movement::walker::Walker;
@@ -8,42 +8,71 @@ use crate::units::{Task, TaskResult};
 use crate::utils::commons::find_walkable_positions_near_by;
 use crate::utils::constants::CLOSE_RANGE_ACTION;
 
-pub fn boost(
-    id: ObjectId<StructureLab>,
-    parts_number: Option<u32>,
-    creep: &Creep,
-    role: &Role,
-) -> TaskResult {
-    if let Some(lab) = id.resolve() {
-        if creep.pos().is_near_to(lab.pos()) {
-            let boost_result = lab.boost_creep(creep, parts_number);
-            info!("{} {} boosted: {:?}", creep.name(), id, boost_result);
+pub fn boost(labs: Vec<(ObjectId<StructureLab>, u32)>, creep: &Creep, role: &Role) -> TaskResult {
+    let resolved: Vec<(StructureLab, u32)> =
+        labs.iter().filter_map(|(id, parts)| id.resolve().map(|lab| (lab, *parts))).collect();
+    if let Some((lab, _)) = resolved.first() {
+        // the tile next to the most labs of the task, the boosts are taken in one tick
+        let near = |pos: &Position| {
+            resolved.iter().filter(|(other, _)| pos.is_near_to(other.pos())).count()
+        };
+        let spot = creep
+            .pos()
+            .is_near_to(lab.pos())
+            .then(|| creep.pos())
+            .into_iter()
+            .chain(find_walkable_positions_near_by(lab.pos(), true))
+            // the creep stays on a tie
+            .rev()
+            .max_by_key(near);
+
+        if spot == Some(creep.pos()) {
+            // the labs out of reach are left to the next task
+            for (lab, parts) in resolved.iter().filter(|(lab, _)| creep.pos().is_near_to(lab.pos()))
+            {
+                let boost_result = lab.boost_creep(creep, Some(*parts));
+                info!("{} {} boosted: {:?}", creep.name(), lab.id(), boost_result);
+            }
             TaskResult::Completed
         } else if let MovementProfile::Cargo = role.get_movement_profile(creep) {
-            if let Some(pos) = find_walkable_positions_near_by(lab.pos(), true).first() {
-                let another_task = Task::PullTo(creep.name(), *pos);
-                let goal = Walker::Immobile.walk(*pos, 0, creep, role, Vec::new());
-                TaskResult::AddNewRequest(
-                    Task::Boost(lab.id(), parts_number),
-                    another_task,
-                    Some(goal),
-                )
+            if let Some(pos) = spot {
+                let another_task = Task::PullTo(creep.name(), pos);
+                let goal = Walker::Immobile.walk(pos, 0, creep, role, Vec::new());
+                TaskResult::AddNewRequest(Task::Boost(labs), another_task, Some(goal))
             } else {
                 let goal = Walker::Immobile.walk(lab.pos(), 1, creep, role, Vec::new());
-                TaskResult::StillWorking(Task::Boost(lab.id(), parts_number), Some(goal))
+                TaskResult::StillWorking(Task::Boost(labs), Some(goal))
             }
         } else {
-            let goal = Walker::Exploring(false).walk(
-                lab.pos(),
-                CLOSE_RANGE_ACTION,
-                creep,
-                role,
-                Vec::new(),
-            );
-            TaskResult::StillWorking(Task::Boost(lab.id(), parts_number), Some(goal))
+            let (pos, range) = spot.map_or((lab.pos(), CLOSE_RANGE_ACTION), |pos| (pos, 0));
+            let goal = Walker::Exploring(false).walk(pos, range, creep, role, Vec::new());
+            TaskResult::StillWorking(Task::Boost(labs), Some(goal))
         }
     } else {
-        error!("{} {:?} invalid lab {}", creep.name(), creep.room(), id);
+        error!("{} {:?} invalid labs {:?}", creep.name(), creep.room(), labs);
         TaskResult::Abort
     }
 }
+
+pub fn unboost(
+    id: ObjectId<StructureLab>,
+    pos: Position,
+    creep: &Creep,
+    role: &Role,
+) -> TaskResult {
+    let Some(lab) = id.resolve() else {
+        error!("{} {:?} invalid unboost lab {}", creep.name(), creep.room(), id);
+        return TaskResult::Abort;
+    };
+
+    if creep.pos() != pos {
+        let goal = Walker::Exploring(false).walk(pos, 0, creep, role, Vec::new());
+        TaskResult::StillWorking(Task::Unboost(id, pos), Some(goal))
+    } else if lab.cooldown() > 0 {
+        TaskResult::StillWorking(Task::Unboost(id, pos), None)
+    } else {
+        let unboost_result = lab.unboost_creep(creep);
+        info!("{} {} unboosted: {:?}", creep.name(), id, unboost_result);
+        TaskResult::Completed
+    }
+}
@@ -9,6 +9,7 @@ use screeps::{
 use wasm_bindgen::JsCast;
 
 use crate::movement::walker::Walker;
+use crate::rooms::logistics::{Stop, StopAction};
 use crate::rooms::wrappers::Fillable;
 use crate::units::roles::Role;
 use crate::units::{Task, TaskResult};
@@ -539,6 +540,73 @@ pub fn pull_to(
     }
 }
 
+pub fn route(mut stops: Vec<Stop>, creep: &Creep, role: &Role, enemies: Vec<Creep>) -> TaskResult {
+    let Some(stop) = stops.first() else {
+        return TaskResult::Abort;
+    };
+
+    let result = match stop.action {
+        StopAction::Pickup => take_resource(stop.id.into(), creep, role, enemies.clone()),
+        StopAction::Withdraw => take_from_structure(
+            stop.pos,
+            stop.id,
+            stop.resource,
+            stop.amount,
+            creep,
+            role,
+            enemies.clone(),
+        ),
+        StopAction::Deliver => {
+            let carried = creep.store().get_used_capacity(Some(stop.resource));
+            if carried == 0 {
+                TaskResult::Abort
+            } else {
+                deliver_to_structure(
+                    stop.pos,
+                    stop.id,
+                    stop.resource,
+                    stop.amount.map(|amount| cmp::min(amount, carried)),
+                    creep,
+                    role,
+                    enemies.clone(),
+                )
+            }
+        }
+    };
+
+    match result {
+        TaskResult::StillWorking(_, movement_goal) => {
+            TaskResult::StillWorking(Task::Route(stops), movement_goal)
+        }
+        result => {
+            // the stop is over: done, picked up or nothing to do there anymore
+            let stop = stops.remove(0);
+            let aborted = matches!(result, TaskResult::Abort);
+            if aborted {
+                debug!("{} skipped route stop: {:?}", creep.name(), stop);
+                // nothing comes of the request anymore, its other stops are dropped
+                if let Some(request) = &stop.request {
+                    stops.retain(|next| next.request.as_ref() != Some(request));
+                }
+            }
+
+            let goal = stops.first().map(|next| {
+                Walker::Exploring(false).walk(next.pos, CLOSE_RANGE_ACTION, creep, role, enemies)
+            });
+            match stop.request.filter(|request| {
+                !stops.iter().any(|next| next.request.as_ref() == Some(request))
+            }) {
+                Some(request) if aborted => {
+                    TaskResult::ResetAndContinue(Task::Route(stops), request, goal)
+                }
+                Some(request) => TaskResult::ResolveAndContinue(Task::Route(stops), request, goal),
+                None if stops.is_empty() => TaskResult::Completed,
+                None => TaskResult::StillWorking(Task::Route(stops), goal),
+            }
+        }
+    }
+}
+
 fn has_enough_or_full(creep: &Creep, resource: ResourceType, amount: u32) -> bool {
     creep.store().get_used_capacity(Some(resource)) >= amount
         || creep.store().get_used_capacity(Some(resource)) == creep.store().get_capacity(None)
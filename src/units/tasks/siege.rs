@@ -0,0 +1,70 @@
+use std::collections::HashSet;
+
+use log::error;
+use screeps::{
+    Creep, HasPosition, ObjectId, Part, Position, SharedCreepProperties, Structure,
+    StructureObject, game,
+};
+
+use crate::commons::has_part;
+use crate::movement::walker::Walker;
+use crate::units::roles::Role;
+use crate::units::{Task, TaskResult};
+use crate::utils::constants::SIEGE_STAGING_RANGE;
+
+pub fn assemble(staging: Position, creep: &Creep, role: &Role, enemies: Vec<Creep>) -> TaskResult {
+    if creep.pos().get_range_to(staging) <= SIEGE_STAGING_RANGE {
+        //wait for the siege stage changes
+        TaskResult::Completed
+    } else {
+        let goal =
+            Walker::Exploring(false).walk(staging, SIEGE_STAGING_RANGE, creep, role, enemies);
+        TaskResult::StillWorking(Task::Assemble(staging), Some(goal))
+    }
+}
+
+pub fn breach(
+    id: ObjectId<Structure>,
+    workplace: Position,
+    members: HashSet<String>,
+    retreat: u8,
+    creep: &Creep,
+    role: &Role,
+    enemies: Vec<Creep>,
+) -> TaskResult {
+    let squad: Vec<Creep> =
+        members.iter().filter_map(|member| game::creeps().get(member.clone())).collect();
+
+    if squad.iter().any(|member| member.hits() * 100 < member.hits_max() * u32::from(retreat)) {
+        // the siege goes to the retreat stage on the same tick
+        return TaskResult::Abort;
+    }
+
+    let healers_near = squad
+        .iter()
+        .filter(|member| member.name() != creep.name() && has_part(&[Part::Heal], member, true))
+        .all(|healer| creep.pos().is_near_to(healer.pos()));
+
+    if !healers_near && !creep.pos().is_room_edge() {
+        //wait for healers
+        return TaskResult::StillWorking(Task::Breach(id, workplace, members, retreat), None);
+    }
+
+    let Some(structure) = id.resolve().map(StructureObject::from) else {
+        // destroyed, the next target is taken from the siege plan
+        return TaskResult::Completed;
+    };
+
+    let range = u32::from(structure.pos().is_equal_to(workplace));
+    if creep.pos().get_range_to(workplace) <= range {
+        if let Some(dismantleable) = structure.as_dismantleable() {
+            let _ = creep.dismantle(dismantleable);
+        } else {
+            error!("{} structure: {} isn't dismantleable!", creep.name(), id);
+            return TaskResult::Completed;
+        }
+    }
+
+    let goal = Walker::Exploring(false).walk(workplace, range, creep, role, enemies);
+    TaskResult::StillWorking(Task::Breach(id, workplace, members, retreat), Some(goal))
+}
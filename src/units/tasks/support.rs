@@ -1,8 +1,8 @@
 use std::collections::HashSet;
 
-use screeps::{Creep, game, HasPosition, SharedCreepProperties};
+use screeps::{Creep, game, HasPosition, Part, SharedCreepProperties};
 
-use crate::{movement::walker::Walker, units::{roles::Role, tasks::{Task, TaskResult}}};
+use crate::{commons::has_part, movement::walker::Walker, units::{roles::Role, tasks::{Task, TaskResult}}};
 
 pub fn heal(
     members: HashSet<String>,
@@ -10,13 +10,20 @@ pub fn heal(
     role: &Role,
     enemies: Vec<Creep>,
 ) -> TaskResult {
-    if let Some(member) = members
+    let squad: Vec<Creep> = members
         .iter()
-        .find(|name| **name != creep.name())
-        .and_then(|member| game::creeps().get(member.clone()))
+        .filter(|name| **name != creep.name())
+        .filter_map(|member| game::creeps().get(member.clone()))
+        .collect();
+
+    //follow the squad worker, another healer otherwise
+    if let Some(member) = squad
+        .iter()
+        .find(|member| !has_part(&[Part::Heal], member, false))
+        .or_else(|| squad.first())
     {
-        let injured = [creep, &member].into_iter()
-            .min_by_key(|c| c.hits()).unwrap_or_else(|| creep);
+        let injured = squad.iter().chain([creep])
+            .min_by_key(|c| c.hits()).unwrap_or(creep);
         match creep.pos().get_range_to(injured.pos()) {
             0 | 1 => {
                 let _ = creep.heal(injured);
@@ -36,4 +43,4 @@ pub fn heal(
         let _ = creep.heal(creep);
         TaskResult::Completed
     }
-}
\ No newline at end of file
+}
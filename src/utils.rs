@@ -1,2 +1,3 @@
 pub mod commons;
 pub mod constants;
+pub mod profiler;
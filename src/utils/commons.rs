@@ -13,8 +13,9 @@ use screeps::{
     ConstructionSite, Creep, Flag, HasId, HasPosition, HasStore, OwnedStructureProperties, Part,
     Position, ROOM_SIZE, RawObjectId, Resource, ResourceType, Room, RoomCoordinate, RoomName,
     RoomPosition, RoomXY, SharedCreepProperties, Source, StructureFactory, StructureKeeperLair,
-    StructureObject, StructureRampart, StructureStorage, StructureTerminal, StructureType, Terrain,
-    find, game,
+    StructureObject, StructureRampart, StructureStorage, StructureTerminal, StructureType,
+    TOWER_FALLOFF, TOWER_FALLOFF_RANGE, TOWER_OPTIMAL_RANGE, TOWER_POWER_ATTACK, Terrain, find,
+    game,
 };
 
 use crate::units::creeps::CreepMemory;
@@ -70,6 +71,15 @@ pub fn is_cpu_on_low() -> bool {
     }
 }
 
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn tower_attack_power(range: u32) -> u32 {
+    let optimal = u32::from(TOWER_OPTIMAL_RANGE);
+    let falloff = u32::from(TOWER_FALLOFF_RANGE);
+    let range = range.clamp(optimal, falloff);
+    let ratio = f64::from(range - optimal) / f64::from(falloff - optimal);
+    (f64::from(TOWER_POWER_ATTACK) * (1. - TOWER_FALLOFF * ratio)) as u32
+}
+
 pub fn get_random<T: SampleUniform + PartialOrd>(from: T, to: T) -> T {
     rand::thread_rng().gen_range(from..=to)
 }
@@ -13,13 +13,36 @@ pub const MY_ROOMS_PICKUP_RESOURCE_THRESHOLD: u32 = 1000;
 pub const FARM_ROOMS_PICKUP_RESOURCE_THRESHOLD: u32 = 1600;
 
 pub const DEPOSIT_REQUEST_THRESHOLD: u32 = 35;
+pub const DEPOSIT_MAX_MINERS: usize = 3;
+pub const DEPOSIT_MAX_HAULERS: u32 = 3;
+/// Rough travel time through one room for the deposit forecast
+pub const DEPOSIT_TICKS_PER_ROOM: u32 = 50;
+/// Next miners generation isn't spawned if it harvests less
+pub const DEPOSIT_MIN_GENERATION_YIELD: u32 = 1_000;
+/// Deposit yields older than this are forgotten
+pub const DEPOSIT_YIELD_WINDOW: u32 = 20_000;
 
 pub const LAB_PRODUCTION: u32 = 5;
 
 pub const MIN_PERIMETR_HITS: u32 = 100_000;
 // pub const MIN_PERIMETR_HITS: u32 = 1000000;
-pub const MAX_RAMPART_HITS: u32 = 299_000_000;
-pub const MAX_WALL_HITS: u32 = 100_000;
+/// Rampart hits a controller level affords with the normal storage and income
+pub const FORTIFY_LEVEL_HITS: [u32; 9] =
+    [0, 0, 30_000, 100_000, 300_000, 1_000_000, 3_000_000, 10_000_000, 30_000_000];
+/// Storage energy the level hits are kept with, the targets follow it from half to four times
+pub const FORTIFY_STORED_NORM: u32 = 100_000;
+/// Harvested energy a tick the level hits are kept with, the targets follow it from half to twice
+pub const FORTIFY_INCOME_NORM: u32 = 20;
+/// Ramparts this close to a spawn, the storage, the terminal or a tower are kept twice stronger
+pub const FORTIFY_CRITICAL_RANGE: u8 = 3;
+/// Ramparts hostiles came close to are kept this many times stronger
+pub const FORTIFY_THREAT_FACTOR: u32 = 3;
+/// Ticks a hostile near a rampart is remembered
+pub const FORTIFY_THREAT_TICKS: u32 = 20_000;
+/// The weakest ramparts against their targets handed out for repair at once
+pub const FORTIFY_REPAIRS: usize = 2;
+/// Safe mode is activated if a perimeter rampart falls faster than this many ticks
+pub const SAFE_MODE_BREACH_TICKS: u32 = 50;
 
 pub const ROOM_NUMBER_RE: &str = r"^[WE]([0-9]+)[NS]([0-9]+)$";
 /// Won't do pathing for moving creeps if current-tick CPU spend is above this
@@ -59,5 +82,143 @@ pub const HANDYMAN_ENERGY_WITHDRAW_THRESHOLD: u32 = 500;
 /// Hostile room avoidance timeout
 pub const AVOID_HOSTILE_ROOM_TIMEOUT: u32 = 100_000;
 
+/// Siege squads gather within this range of the staging position
+pub const SIEGE_STAGING_RANGE: u32 = 3;
+/// Siege squad leaves the target room when a member falls below this hits percent
+pub const SIEGE_RETREAT_PERCENT: u8 = 60;
+/// Dismantlers are added until the breach path falls within this many ticks
+pub const SIEGE_BREACH_TICKS: u32 = 1_000;
+pub const SIEGE_MAX_HEALERS: u32 = 3;
+pub const SIEGE_MAX_DISMANTLERS: u32 = 2;
+/// Siege is aborted after this many squads were lost
+pub const SIEGE_MAX_SQUADS: usize = 3;
+/// Barrier hits per one point of the breach path cost
+pub const SIEGE_HITS_PER_COST: u32 = 100_000;
+/// Ticks between two quad rotations, the quad moves on in between
+pub const QUAD_ROTATE_INTERVAL: u32 = 5;
+
+/// Rough travel time through one room for the power bank estimate
+pub const PB_TICKS_PER_ROOM: u32 = 50;
+pub const PB_MAX_SQUADS: u32 = 3;
+/// Power bank is skipped when spawning costs more energy per power unit
+pub const PB_MAX_ENERGY_PER_POWER: u32 = 10;
+/// Smaller banks aren't worth the boosts
+pub const PB_BOOST_POWER_THRESHOLD: u32 = 6_000;
+
+/// Energy promised by a hauler route is kept out of the demand for this many ticks
+pub const LOGISTICS_RESERVATION_TICKS: u32 = 50;
+
+/// Public `RawMemory` segment with the dashboard metrics
+pub const STATS_SEGMENT: u8 = 90;
+/// Metrics are exported every this many ticks
+pub const STATS_INTERVAL: u32 = 20;
+/// Exports kept in the segment history
+pub const STATS_HISTORY_SIZE: usize = 200;
+/// Segment size limit is 100 KB
+pub const STATS_SEGMENT_LIMIT: usize = 100 * 1024;
+
+/// Ticks the profiler aggregates cpu usage over
+pub const PROFILER_WINDOW: u32 = 100;
+
+/// Journal entries kept in memory
+pub const JOURNAL_SIZE: usize = 200;
+/// Events with the same key within this many ticks are merged into one entry
+pub const JOURNAL_DEDUP_TICKS: u32 = 100;
+
+/// Status changes kept in the request meta
+pub const REQUEST_HISTORY_SIZE: usize = 8;
+/// Ticks between two watchdog inspections of the room requests
+pub const REQUEST_WATCHDOG_INTERVAL: u32 = 50;
+/// Request priority grows by one every this many ticks of waiting
+pub const REQUEST_AGE_STEP: u32 = 500;
+/// Requests from this priority preempt the low priority hauler routes
+pub const URGENT_REQUEST_PRIORITY: u8 = 70;
+
+/// Storage energy a room keeps before spending on the controller
+pub const UPGRADE_ENERGY_RESERVE: u32 = 50_000;
+/// Storage energy above the reserve is spent on upgrading over this many ticks
+pub const UPGRADE_HORIZON: u32 = 3_000;
+/// Container and spawn energy of a room without storage is spent over this many ticks
+pub const UPGRADE_EARLY_HORIZON: u32 = 200;
+/// RCL 8 room farms GCL above this storage energy
+pub const UPGRADE_GCL_ENERGY: u32 = 250_000;
+
+/// Ticks between energy network plans
+pub const NETWORK_INTERVAL: u32 = 500;
+/// Energy the network moves in one unit of flow
+pub const NETWORK_CHUNK: u32 = 1_000;
+/// Rooms within this distance of their energy target neither send nor receive
+pub const NETWORK_DEADBAND: u32 = 20_000;
+/// Extra energy target of a room upgrading to its next level
+pub const NETWORK_UPGRADE_BONUS: u32 = 50_000;
+
+/// Ticks between mineral economy plans
+pub const MINERAL_INTERVAL: u32 = 1_000;
+/// Ticks ahead the mineral economy predicts output and lab demand
+pub const MINERAL_HORIZON: u32 = 3_000;
+/// Base mineral stock the colony keeps per terminal on top of the planned demand
+pub const MINERAL_STOCK_TARGET: u32 = 10_000;
+/// Minerals above the target kept raw before compressing
+pub const MINERAL_SURPLUS: u32 = 30_000;
+/// Bars of one mineral kept before selling
+pub const MINERAL_BAR_LIMIT: u32 = 20_000;
+/// Highest buy price as a share of the last average price
+pub const MINERAL_BUY_MARKUP: f64 = 1.2;
+/// Lowest sell price as a share of the last average price
+pub const MINERAL_SELL_MARKDOWN: f64 = 0.8;
+
+/// Ticks between the checks of our market orders
+pub const MARKET_INTERVAL: u32 = 100;
+/// Days of price history the order price follows
+pub const MARKET_HISTORY_DAYS: usize = 3;
+/// Standard deviations above the average we sell at and below it we buy at
+pub const MARKET_BAND: f64 = 0.5;
+/// Share of the price an order may drift from the band before it is repriced
+pub const MARKET_REPRICE: f64 = 0.1;
+/// Credits the orders of one resource may tie up unless a budget is set
+pub const MARKET_BUDGET: f64 = 100_000.;
+/// Trades of this amount get an order of our own, smaller ones deal
+pub const MARKET_ORDER_MIN_AMOUNT: u32 = 2_000;
+/// Ticks an order lives before it is cancelled and posted again
+pub const MARKET_ORDER_TTL: u32 = 50_000;
+
+/// Ticks a plan drawn from the console stays on the screen
+pub const PLAN_VISUAL_TICKS: u32 = 100;
+
+/// A link this close to the room edge takes the energy of the remote haulers
+pub const EDGE_LINK_RANGE: u8 = 3;
+/// The farm roads get an edge link when they enter the room farther than this from the hub
+pub const EDGE_LINK_HUB_RANGE: u32 = 10;
+/// Ticks between the farm replans that look for an edge link the home plan misses
+pub const EDGE_LINK_PLAN_INTERVAL: u32 = 10_000;
+
+/// Misplaced structures removed by a migration step
+pub const MIGRATION_BATCH: usize = 5;
+/// Spawns a base keeps while it moves onto a new plan
+pub const MIGRATION_MIN_SPAWNS: usize = 1;
+/// Towers a base keeps while it moves onto a new plan
+pub const MIGRATION_MIN_TOWERS: usize = 1;
+/// Resources a misplaced storage or terminal may hold when it is removed
+pub const MIGRATION_STORE_LEFT: u32 = 5_000;
+
+/// How often an expansion candidate is planned and scored
+pub const CANDIDATE_INTERVAL: u32 = 200;
+/// Ticks before a scored candidate is planned again
+pub const CANDIDATE_RESCORE: u32 = 50_000;
+/// Candidates the scouts have not seen for this many ticks are forgotten
+pub const CANDIDATE_TTL: u32 = 150_000;
+/// Expansion candidates kept in memory
+pub const CANDIDATE_MAX: usize = 50;
+/// Energy a tick hauled over a remote road tile to be worth its repairs
+pub const ROAD_MIN_TRAFFIC: f64 = 5.;
+/// Smallest link transfer worth a cooldown
+pub const LINK_BATCH: u32 = 400;
+/// A source or edge link sends its energy to the hub above this amount
+pub const LINK_SPILL: u32 = 700;
+/// Ticks the minerals stay reserved for a creep from the start of its spawning
+pub const BOOST_ORDER_TICKS: u32 = 500;
+/// Spare ticks a boosted creep heads to the unboost lab with, on top of the way there
+pub const UNBOOST_TICKS: u32 = 25;
+
 pub const LONG_RANGE_ACTION: u32 = CREEP_RANGED_ACTION_RANGE as u32;
 pub const CLOSE_RANGE_ACTION: u32 = 1;
@@ -0,0 +1,118 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use itertools::Itertools;
+use screeps::game;
+
+use crate::utils::constants::PROFILER_WINDOW;
+
+thread_local! {
+    static PROFILER: RefCell<Profiler> = RefCell::new(Profiler::default());
+}
+
+/// Profiled code: the subsystem and the phase, request kind, task or role inside it
+pub type Key = (&'static str, &'static str);
+
+#[derive(Debug, Clone, Copy)]
+struct Record {
+    ticks: u32,
+    calls: u32,
+    total: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for Record {
+    fn default() -> Self {
+        Self { ticks: 0, calls: 0, total: 0., min: f64::MAX, max: 0. }
+    }
+}
+
+impl Record {
+    fn add(&mut self, (calls, used): (u32, f64)) {
+        self.ticks += 1;
+        self.calls += calls;
+        self.total += used;
+        self.min = self.min.min(used);
+        self.max = self.max.max(used);
+    }
+}
+
+// cpu per tick is aggregated over the window, the finished window is kept for the report
+#[derive(Debug, Default)]
+struct Profiler {
+    tick: u32,
+    current: HashMap<Key, (u32, f64)>,
+    window_start: u32,
+    window: HashMap<Key, Record>,
+    finished: Option<(u32, u32, HashMap<Key, Record>)>,
+}
+
+impl Profiler {
+    fn record(&mut self, key: Key, used: f64) {
+        let time = game::time();
+        if self.tick != time {
+            self.flush();
+            self.tick = time;
+        }
+        let (calls, total) = self.current.entry(key).or_default();
+        *calls += 1;
+        *total += used;
+    }
+
+    fn flush(&mut self) {
+        for (key, tick_usage) in self.current.drain() {
+            self.window.entry(key).or_default().add(tick_usage);
+        }
+
+        if self.tick >= self.window_start + PROFILER_WINDOW {
+            let window = std::mem::take(&mut self.window);
+            self.finished = Some((self.window_start, self.tick, window));
+            self.window_start = self.tick + 1;
+        }
+    }
+}
+
+/// Runs `f` and adds its cpu to the key, nested measures are counted in both keys
+pub fn measure<T>(key: Key, f: impl FnOnce() -> T) -> T {
+    let cpu_start = game::cpu::get_used();
+    let result = f();
+    record(key, game::cpu::get_used() - cpu_start);
+    result
+}
+
+pub fn record(key: Key, used: f64) {
+    PROFILER.with(|profiler| profiler.borrow_mut().record(key, used));
+}
+
+/// Top consumers of the last finished window, or the current one right after a reset
+pub fn report(top: usize) -> String {
+    PROFILER.with(|profiler| {
+        let profiler = profiler.borrow();
+        let (from, to, records) = profiler.finished.as_ref().map_or_else(
+            || (profiler.window_start, profiler.tick, &profiler.window),
+            |(from, to, records)| (*from, *to, records),
+        );
+        let window_ticks = f64::from((to.saturating_sub(from)).max(1));
+
+        let mut result = format!(
+            "cpu profile for ticks {from}..{to}, per tick: avg (min..max) calls\n"
+        );
+        for ((subsystem, name), record) in records
+            .iter()
+            .sorted_by(|(_, first), (_, second)| second.total.total_cmp(&first.total))
+            .take(top)
+        {
+            let _ = writeln!(
+                result,
+                "{subsystem:>10} {name:<24} {:>8.3} ({:.3}..{:.3}) {:.1}",
+                record.total / window_ticks,
+                record.min,
+                record.max,
+                f64::from(record.calls) / f64::from(record.ticks.max(1)),
+            );
+        }
+        result
+    })
+}